@@ -0,0 +1,274 @@
+use std::{error, fmt};
+
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use db_rust::models;
+use db_rust::repository::Entity;
+use db_rust::transfer::{Count, Validation};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Format {
+  #[default]
+  Table,
+  Json
+}
+
+#[derive(Debug, Default)]
+pub struct Table {
+  headers: Vec<String>,
+  rows: Vec<Vec<String>>
+}
+
+impl Table {
+  pub fn new(headers: &[&str]) -> Self {
+    Self {
+      headers: headers.iter()
+        .map(|header| header.to_string())
+        .collect(),
+      rows: Vec::new()
+    }
+  }
+
+  pub fn add_row(&mut self, row: Vec<String>) {
+    self.rows.push(row);
+  }
+}
+
+impl fmt::Display for Table {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let mut widths: Vec<usize> = self.headers.iter()
+      .map(|header| header.chars().count())
+      .collect();
+
+    for row in &self.rows {
+      for (i, cell) in row.iter().enumerate() {
+        widths[i] = widths[i].max(cell.chars().count());
+      }
+    }
+
+    let separator: Vec<String> = widths.iter()
+      .map(|width| "-".repeat(*width))
+      .collect();
+
+    let lines = [&self.headers, &separator]
+      .into_iter()
+      .chain(self.rows.iter());
+
+    for line in lines {
+      let cells: Vec<String> = line.iter()
+        .zip(&widths)
+        .map(|(cell, width)| format!("{:width$}", cell, width = width))
+        .collect();
+
+      writeln!(f, "{}", cells.join("  ").trim_end())?;
+    }
+
+    Ok(())
+  }
+}
+
+// Every command result can be printed either way, so both
+// representations are built up front
+pub struct Output {
+  json: Value,
+  table: Table
+}
+
+impl Output {
+  pub fn field(name: &str, value: &dyn fmt::Display) -> Self {
+    let mut table = Table::new(&[name]);
+
+    table.add_row(vec![value.to_string()]);
+
+    Self {
+      json: json!({ name: value.to_string() }),
+      table
+    }
+  }
+
+  pub fn done() -> Self {
+    let mut table = Table::new(&["status"]);
+
+    table.add_row(vec!["ok".to_owned()]);
+
+    Self {
+      json: json!({ "status": "ok" }),
+      table
+    }
+  }
+
+  pub fn user(user: &models::User) -> Result<Self, Box<dyn error::Error>> {
+    let mut table = Table::new(
+      &["id", "first_name", "last_name", "email", "posts_per_page", "display_email"]
+    );
+
+    table.add_row(vec![
+      user.id.to_string(),
+      user.first_name.clone(),
+      user.last_name.clone(),
+      user.email.clone().unwrap_or_default(),
+      user.settings.posts_per_page.to_string(),
+      user.settings.display_email.to_string()
+    ]);
+
+    Self::new(user, table)
+  }
+
+  pub fn posts(posts: &[models::Post]) -> Result<Self, Box<dyn error::Error>> {
+    let mut table = Table::new(&["id", "title", "description", "author", "liked"]);
+
+    for post in posts {
+      let author = post.author.as_ref()
+        .map(|author| format!("{} {}", author.first_name, author.last_name))
+        .unwrap_or_default();
+
+      table.add_row(vec![
+        post.id.to_string(),
+        post.title.clone(),
+        post.description.clone().unwrap_or_default(),
+        author.trim().to_owned(),
+        post.liked.to_string()
+      ]);
+    }
+
+    Self::new(posts, table)
+  }
+
+  pub fn post(post: &models::Post) -> Result<Self, Box<dyn error::Error>> {
+    let mut output = Self::posts(std::slice::from_ref(post))?;
+
+    output.json = serde_json::to_value(post)?;
+
+    Ok(output)
+  }
+
+  // The table only counts, the whole document is printed as json
+  pub fn personal_data(personal_data: &models::PersonalData) -> Result<Self, Box<dyn error::Error>> {
+    let mut table = Table::new(&["entity", "count"]);
+
+    table.add_row(vec!["sessions".to_owned(), personal_data.sessions.len().to_string()]);
+    table.add_row(vec!["posts".to_owned(), personal_data.posts.len().to_string()]);
+    table.add_row(vec!["liked".to_owned(), personal_data.liked.len().to_string()]);
+
+    Self::new(personal_data, table)
+  }
+
+  pub fn validation(validation: &Validation) -> Self {
+    let mut table = Table::new(&["entity", "count"]);
+
+    for (entity, count) in &validation.counts {
+      table.add_row(vec![entity.to_string(), count.to_string()]);
+    }
+
+    for problem in &validation.problems {
+      table.add_row(vec!["problem".to_owned(), problem.clone()]);
+    }
+
+    let counts: serde_json::Map<String, Value> = validation.counts.iter()
+      .map(|(entity, count)| (entity.to_string(), json!(count)))
+      .collect();
+
+    Self {
+      json: json!({ "counts": counts, "problems": validation.problems }),
+      table
+    }
+  }
+
+  pub fn copied(counts: &[(Entity, usize)]) -> Self {
+    let mut table = Table::new(&["entity", "count"]);
+
+    for (entity, count) in counts {
+      table.add_row(vec![entity.to_string(), count.to_string()]);
+    }
+
+    let json: serde_json::Map<String, Value> = counts.iter()
+      .map(|(entity, count)| (entity.to_string(), json!(count)))
+      .collect();
+
+    Self {
+      json: Value::from(json),
+      table
+    }
+  }
+
+  pub fn counts(counts: &[Count]) -> Self {
+    let mut table = Table::new(&["entity", "source", "target", "matches"]);
+
+    for count in counts {
+      table.add_row(vec![
+        count.entity.to_string(),
+        count.source.to_string(),
+        count.target.to_string(),
+        count.matches().to_string()
+      ]);
+    }
+
+    let json: Vec<Value> = counts.iter()
+      .map(|count| json!({
+        "entity": count.entity,
+        "source": count.source,
+        "target": count.target,
+        "matches": count.matches()
+      }))
+      .collect();
+
+    Self {
+      json: Value::from(json),
+      table
+    }
+  }
+
+  pub fn render(&self, format: Format) -> Result<String, Box<dyn error::Error>> {
+    match format {
+      Format::Table => Ok(self.table.to_string()),
+      Format::Json => Ok(format!("{}\n", serde_json::to_string_pretty(&self.json)?))
+    }
+  }
+
+  fn new<T: Serialize + ?Sized>(value: &T, table: Table) -> Result<Self, Box<dyn error::Error>> {
+    Ok(
+      Self {
+        json: serde_json::to_value(value)?,
+        table
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use db_rust::models;
+
+  use super::{Format, Output};
+
+  #[test]
+  fn test_render() {
+    let mut user = models::User::new();
+
+    user.id = models::UserId::from(12);
+    user.first_name = "Ada".to_owned();
+    user.last_name = "Lovelace".to_owned();
+    user.password = Some("secret".to_owned());
+
+    let output = Output::user(&user).unwrap();
+
+    assert_eq!(
+      output.render(Format::Table).unwrap(),
+      "id  first_name  last_name  email  posts_per_page  display_email\n\
+       --  ----------  ---------  -----  --------------  -------------\n\
+       12  Ada         Lovelace          10              false\n"
+    );
+
+    let json = output.render(Format::Json).unwrap();
+
+    assert!(json.contains("\"id\": \"12\""));
+    assert!(!json.contains("secret"));
+
+    assert_eq!(
+      Output::field("code", &"abc").render(Format::Json).unwrap(),
+      "{\n  \"code\": \"abc\"\n}\n"
+    );
+  }
+}
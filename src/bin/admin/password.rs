@@ -0,0 +1,136 @@
+use std::{error, io::{self, BufRead, Write}};
+
+use db_rust::utils::error::{StringError, ValidationError};
+
+// Asks twice on the terminal with echo off, so the password stays
+// out of the screen, the shell history and the process list
+pub fn prompt() -> Result<String, Box<dyn error::Error>> {
+  let password = ask("Password: ")?;
+
+  if ask("Repeat the password: ")? != password {
+    return Err(
+      Box::new(
+        ValidationError::new("Passwords don't match")
+      )
+    );
+  }
+
+  Ok(password)
+}
+
+// The first line, without its line break
+pub fn read(reader: &mut impl BufRead) -> Result<String, Box<dyn error::Error>> {
+  let mut line = String::new();
+
+  reader.read_line(&mut line)?;
+
+  let password = line.strip_suffix('\n')
+    .map(|line| line.strip_suffix('\r').unwrap_or(line))
+    .unwrap_or(&line);
+
+  if password.is_empty() {
+    return Err(
+      Box::new(
+        ValidationError::new("Password should be non-empty")
+      )
+    );
+  }
+
+  Ok(password.to_owned())
+}
+
+fn ask(question: &str) -> Result<String, Box<dyn error::Error>> {
+  let echo = echo::Off::new()?;
+
+  let mut stderr = io::stderr();
+
+  stderr.write_all(question.as_bytes())?;
+  stderr.flush()?;
+
+  let res = read(&mut io::stdin().lock());
+
+  drop(echo);
+
+  // The line break wasn't echoed either
+  stderr.write_all(b"\n")?;
+
+  res
+}
+
+#[cfg(unix)]
+mod echo {
+  use std::{error, mem};
+
+  use super::StringError;
+
+  // Restores the terminal when dropped, also when reading failed
+  pub struct Off(libc::termios);
+
+  impl Off {
+    pub fn new() -> Result<Self, Box<dyn error::Error>> {
+      let mut termios = unsafe { mem::zeroed::<libc::termios>() };
+
+      if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+        return Err(
+          Box::new(
+            StringError::new("stdin is not a terminal, pass the password with --password-stdin")
+          )
+        );
+      }
+
+      let mut silent = termios;
+
+      silent.c_lflag &= !libc::ECHO;
+
+      if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &silent) } != 0 {
+        return Err(
+          Box::new(
+            StringError::new("Can't turn off the terminal echo, pass the password with --password-stdin")
+          )
+        );
+      }
+
+      Ok(Self(termios))
+    }
+  }
+
+  impl Drop for Off {
+    fn drop(&mut self) {
+      unsafe {
+        libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0);
+      }
+    }
+  }
+}
+
+#[cfg(not(unix))]
+mod echo {
+  use std::error;
+
+  use super::StringError;
+
+  pub struct Off;
+
+  impl Off {
+    pub fn new() -> Result<Self, Box<dyn error::Error>> {
+      Err(
+        Box::new(
+          StringError::new("Prompting for a password needs a Unix terminal, pass it with --password-stdin")
+        )
+      )
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::read;
+
+  #[test]
+  fn test_read() {
+    assert_eq!(read(&mut "secret\r\nrest\n".as_bytes()).unwrap(), "secret");
+    assert_eq!(read(&mut " spaced \n".as_bytes()).unwrap(), " spaced ");
+    assert!(read(&mut "\n".as_bytes()).is_err());
+    assert!(read(&mut "".as_bytes()).is_err());
+  }
+}
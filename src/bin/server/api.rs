@@ -0,0 +1,129 @@
+use std::{error, fmt};
+
+use serde::Serialize;
+use serde_json::{json, Value};
+
+use db_rust::utils::error::{ConflictError, InvalidIdError, NotFoundError, ValidationError};
+
+pub struct Request {
+  pub method: String,
+  pub path: String,
+  pub query: String,
+  pub authorization: Option<String>,
+  pub user_agent: Option<String>,
+  pub ip: Option<String>,
+  pub body: String
+}
+
+impl Request {
+  pub fn new(method: &str, path: &str) -> Self {
+    let (path, query) = path.split_once('?')
+      .unwrap_or((path, ""));
+
+    Self {
+      method: method.to_uppercase(),
+      path: path.to_owned(),
+      query: query.to_owned(),
+      authorization: None,
+      user_agent: None,
+      ip: None,
+      body: String::new()
+    }
+  }
+
+  // The first value of a query parameter, nothing here needs them decoded
+  pub fn param(&self, name: &str) -> Option<&str> {
+    self.query.split('&')
+      .filter_map(|pair| pair.split_once('='))
+      .find(|(key, _)| *key == name)
+      .map(|(_, value)| value)
+  }
+
+  pub fn bearer(&self) -> Option<&str> {
+    self.authorization.as_deref()
+      .and_then(|value| value.strip_prefix("Bearer "))
+      .map(|code| code.trim())
+  }
+}
+
+pub struct Response {
+  pub status: u16,
+  pub body: Option<Value>
+}
+
+impl Response {
+  pub fn json<T: Serialize>(status: u16, value: &T) -> Result<Self, Box<dyn error::Error>> {
+    Ok(
+      Self {
+        status,
+        body: Some(serde_json::to_value(value)?)
+      }
+    )
+  }
+
+  pub fn no_content() -> Self {
+    Self {
+      status: 204,
+      body: None
+    }
+  }
+
+  pub fn error(status: u16, message: &str) -> Self {
+    Self {
+      status,
+      body: Some(json!({ "error": message }))
+    }
+  }
+
+  pub fn from_error(err: Box<dyn error::Error>) -> Self {
+    if err.is::<UnauthorizedError>() {
+      Self::error(401, &err.to_string())
+    } else if err.is::<NotFoundError>() {
+      Self::error(404, &err.to_string())
+    } else if err.is::<ConflictError>() {
+      Self::error(409, &err.to_string())
+    } else if err.is::<PayloadTooLargeError>() {
+      Self::error(413, &err.to_string())
+    } else if err.is::<ValidationError>() || err.is::<InvalidIdError>() || err.is::<serde_json::Error>() {
+      Self::error(400, &err.to_string())
+    } else {
+      eprintln!("Internal error: {}", err);
+
+      Self::error(500, "Internal server error")
+    }
+  }
+}
+
+#[derive(Debug)]
+pub struct UnauthorizedError(String);
+
+impl UnauthorizedError {
+  pub fn new(message: &str) -> Self {
+    Self(message.to_owned())
+  }
+}
+
+impl fmt::Display for UnauthorizedError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl error::Error for UnauthorizedError { }
+
+#[derive(Debug)]
+pub struct PayloadTooLargeError(String);
+
+impl PayloadTooLargeError {
+  pub fn new(message: &str) -> Self {
+    Self(message.to_owned())
+  }
+}
+
+impl fmt::Display for PayloadTooLargeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl error::Error for PayloadTooLargeError { }
@@ -0,0 +1,206 @@
+use std::{env, error, io::Read, sync::Arc, thread, time::Duration};
+
+use dotenv::dotenv;
+use tiny_http::{Header, Server};
+
+use db_rust::repository;
+use db_rust::utils::error::{StringError, ValidationError};
+
+mod api;
+mod routes;
+
+// Larger bodies are answered with 413 before more than this is read
+const MAX_BODY: usize = 1024 * 1024;
+
+fn read_request(request: &mut tiny_http::Request) -> Result<api::Request, Box<dyn error::Error>> {
+  let mut api_request = api::Request::new(
+    request.method().as_str(),
+    request.url()
+  );
+
+  for header in request.headers() {
+    if header.field.equiv("Authorization") {
+      api_request.authorization = Some(header.value.to_string());
+    } else if header.field.equiv("User-Agent") {
+      api_request.user_agent = Some(header.value.to_string());
+    }
+  }
+
+  api_request.ip = request.remote_addr()
+    .map(|address| address.ip().to_string());
+
+  if request.body_length().is_some_and(|length| length > MAX_BODY) {
+    return Err(Box::new(too_large()));
+  }
+
+  // Chunked bodies have no length up front
+  request.as_reader()
+    .take(MAX_BODY as u64 + 1)
+    .read_to_string(&mut api_request.body)
+    .map_err(|err| ValidationError::new(&format!("Invalid body: {}", err)))?;
+
+  if api_request.body.len() > MAX_BODY {
+    return Err(Box::new(too_large()));
+  }
+
+  Ok(api_request)
+}
+
+fn too_large() -> api::PayloadTooLargeError {
+  api::PayloadTooLargeError::new(&format!("Body should be at most {} bytes", MAX_BODY))
+}
+
+// Each worker takes the next request off the server, so at most as
+// many requests as there are workers are handled at once
+fn serve(server: &Server, repositories: &repository::Repositories) {
+  for mut request in server.incoming_requests() {
+    let response = match read_request(&mut request) {
+      Ok(api_request) => routes::handle(repositories, &api_request),
+      Err(err) => api::Response::from_error(err)
+    };
+
+    let body = response.body
+      .map(|body| body.to_string())
+      .unwrap_or_default();
+
+    let mut http_response = tiny_http::Response::from_string(body)
+      .with_status_code(response.status);
+
+    if response.status != 204 {
+      http_response.add_header(
+        Header::from_bytes("Content-Type", "application/json").unwrap()
+      );
+    }
+
+    if let Err(err) = request.respond(http_response) {
+      eprintln!("Failed to respond: {}", err);
+    }
+  }
+}
+
+// Webhooks are delivered from a thread of their own, so slow
+// receivers don't hold up requests
+#[cfg(feature = "webhooks")]
+fn spawn_dispatcher(repositories: Arc<repository::Repositories>) {
+  use db_rust::webhook::Dispatcher;
+
+  let dispatcher = Dispatcher::new(repositories);
+
+  thread::spawn(move || loop {
+    match dispatcher.dispatch() {
+      Ok(0) => thread::sleep(Duration::from_secs(1)),
+      Ok(_) => {},
+      Err(err) => {
+        eprintln!("Failed to dispatch webhooks: {}", err);
+
+        thread::sleep(Duration::from_secs(5));
+      }
+    }
+  });
+}
+
+fn main() -> Result<(), Box<dyn error::Error>> {
+  dotenv().ok();
+
+  let backend = env::var("BACKEND").unwrap_or("postgres".to_owned());
+  let address = env::var("SERVER_ADDRESS").unwrap_or("127.0.0.1:8080".to_owned());
+  let workers: usize = env::var("SERVER_WORKERS").unwrap_or("8".to_owned()).parse()?;
+
+  let config: repository::Config = backend.parse()?;
+  // Sessions are checked on every request, the short ttl bounds how long
+  // one revoked by another process, like the admin CLI, keeps working here
+  let repositories = Arc::new(
+    repository::Repositories::from_config(&config)
+      .cached(
+        repository::cache::Cache::with_session_ttl(10_000, Duration::from_secs(60), Duration::from_secs(10))
+      )
+  );
+
+  #[cfg(feature = "webhooks")]
+  spawn_dispatcher(repositories.clone());
+
+  let server = Arc::new(
+    Server::http(&address)
+      .map_err(|err| StringError::new(&err.to_string()))?
+  );
+
+  println!("Listening on {} with the {} backend and {} workers", address, config.backend, workers);
+
+  let handles: Vec<_> = (0..workers.max(1))
+    .map(|_| {
+      let server = server.clone();
+      let repositories = repositories.clone();
+
+      thread::spawn(move || serve(&server, &repositories))
+    })
+    .collect();
+
+  for handle in handles {
+    handle.join().ok();
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    sync::Arc,
+    thread
+  };
+
+  use tiny_http::Server;
+
+  use db_rust::repository::{Backend, Repositories};
+
+  use super::{serve, MAX_BODY};
+
+  fn start() -> String {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let address = server.server_addr().to_ip().unwrap().to_string();
+    let repositories = Arc::new(Repositories::with_backend(Backend::Memory));
+
+    thread::spawn(move || serve(&server, &repositories));
+
+    address
+  }
+
+  fn status(address: &str, head: &str, body: &[u8]) -> String {
+    let mut stream = TcpStream::connect(address).unwrap();
+
+    stream.write_all(head.as_bytes()).unwrap();
+    stream.write_all(body).unwrap();
+
+    let mut response = String::new();
+
+    stream.read_to_string(&mut response).ok();
+
+    response.split_whitespace().nth(1).unwrap_or_default().to_owned()
+  }
+
+  #[test]
+  fn test_body_limit() {
+    let address = start();
+
+    // Refused from the header alone, the body is never read
+    let head = format!(
+      "POST /users HTTP/1.1\r\nHost: test\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+      MAX_BODY + 1
+    );
+
+    assert_eq!(status(&address, &head, b""), "413");
+
+    // A chunked body is cut off once it passes the limit
+    let chunk = vec![b'a'; MAX_BODY + 1];
+    let head = "POST /users HTTP/1.1\r\nHost: test\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n";
+    let body = [format!("{:x}\r\n", chunk.len()).as_bytes(), &chunk, b"\r\n0\r\n\r\n"].concat();
+
+    assert_eq!(status(&address, head, &body), "413");
+
+    let head = "POST /users HTTP/1.1\r\nHost: test\r\nContent-Length: 2\r\nConnection: close\r\n\r\n";
+
+    assert_eq!(status(&address, head, b"{}"), "400");
+  }
+}
@@ -0,0 +1,970 @@
+use std::error;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use db_rust::models;
+use db_rust::repository::Repositories;
+use db_rust::utils::error::{NotFoundError, ValidationError};
+use db_rust::utils::url::Url;
+
+use super::api::{Request, Response, UnauthorizedError};
+
+#[derive(Deserialize)]
+struct Credentials {
+  email: String,
+  password: String
+}
+
+#[derive(Deserialize)]
+struct Profile {
+  first_name: String,
+  last_name: String
+}
+
+#[derive(Deserialize)]
+struct Email {
+  email: String
+}
+
+#[derive(Deserialize)]
+struct Passwords {
+  old_password: String,
+  new_password: String
+}
+
+#[derive(Deserialize)]
+struct NotificationIds {
+  ids: Vec<models::NotificationId>
+}
+
+pub fn handle(repositories: &Repositories, request: &Request) -> Response {
+  route(repositories, request)
+    .unwrap_or_else(Response::from_error)
+}
+
+fn route(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let segments: Vec<&str> = request.path
+    .trim_matches('/')
+    .split('/')
+    .collect();
+
+  match (request.method.as_str(), segments.as_slice()) {
+    ("POST", ["users"]) => sign_up(repositories, request),
+    ("POST", ["sessions"]) => login(repositories, request),
+    ("DELETE", ["sessions"]) => logout(repositories, request),
+    ("GET", ["settings"]) => get_settings(repositories, request),
+    ("PUT", ["settings"]) => edit_settings(repositories, request),
+    ("PUT", ["profile"]) => edit_profile(repositories, request),
+    ("PUT", ["email"]) => change_email(repositories, request),
+    ("PUT", ["password"]) => change_password(repositories, request),
+    ("DELETE", ["account"]) => delete_account(repositories, request),
+    ("GET", ["account", "data"]) => export_personal_data(repositories, request),
+    ("POST", ["posts"]) => create_post(repositories, request),
+    ("GET", ["posts"]) => list_posts(repositories, request),
+    ("GET", ["posts", "liked"]) => liked_posts(repositories, request),
+    ("GET", ["posts", id]) => get_post(repositories, request, id),
+    ("PUT", ["posts", id, "like"]) => like(repositories, request, id),
+    ("DELETE", ["posts", id, "like"]) => unlike(repositories, request, id),
+    ("GET", ["feed"]) => feed(repositories, request),
+    ("PUT", ["users", id, "follow"]) => follow(repositories, request, id),
+    ("DELETE", ["users", id, "follow"]) => unfollow(repositories, request, id),
+    ("GET", ["users", id, "followers"]) => followers(repositories, request, id),
+    ("GET", ["users", id, "following"]) => following(repositories, request, id),
+    ("GET", ["users", id, "follows"]) => follow_counts(repositories, request, id),
+    ("POST", ["webhooks"]) => create_webhook(repositories, request),
+    ("GET", ["webhooks"]) => list_webhooks(repositories, request),
+    ("GET", ["webhooks", "dead-letters"]) => dead_letters(repositories, request),
+    ("DELETE", ["webhooks", id]) => delete_webhook(repositories, request, id),
+    ("GET", ["webhooks", id, "deliveries"]) => deliveries(repositories, request, id),
+    ("GET", ["notifications"]) => list_notifications(repositories, request),
+    ("GET", ["notifications", "unread"]) => count_unread(repositories, request),
+    ("PUT", ["notifications", "read"]) => mark_read(repositories, request),
+    _ => Err(
+      Box::new(
+        NotFoundError::new("Route not found")
+      )
+    )
+  }
+}
+
+fn session_code(request: &Request) -> Result<models::SessionCode, UnauthorizedError> {
+  let code = request.bearer()
+    .ok_or(UnauthorizedError::new("Bearer session code is required"))?;
+
+  code.parse::<models::SessionCode>()
+    .map_err(|_| UnauthorizedError::new("Invalid session code"))
+}
+
+fn authenticate(repositories: &Repositories, request: &Request) -> Result<models::UserId, Box<dyn error::Error>> {
+  let code = session_code(request)?;
+
+  repositories.session.get_user_id(&code)
+    .map_err(|err| {
+      if err.is::<NotFoundError>() {
+        Box::new(UnauthorizedError::new("Invalid session code"))
+      } else {
+        err
+      }
+    })
+}
+
+fn authenticate_optional(
+  repositories: &Repositories,
+  request: &Request
+) -> Result<Option<models::UserId>, Box<dyn error::Error>> {
+  if request.authorization.is_some() {
+    Ok(Some(authenticate(repositories, request)?))
+  } else {
+    Ok(None)
+  }
+}
+
+fn sign_up(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let mut user: models::User = serde_json::from_str(&request.body)?;
+
+  user.id = models::UserId::new();
+
+  let user_id = repositories.user.create(&mut user)?;
+
+  Response::json(201, &json!({ "id": user_id }))
+}
+
+fn login(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let credentials: Credentials = serde_json::from_str(&request.body)?;
+
+  let user_id = repositories.user.get_id(&credentials.email, &credentials.password)
+    .map_err(|err| {
+      if err.is::<NotFoundError>() {
+        Box::new(UnauthorizedError::new("Wrong email or password"))
+      } else {
+        err
+      }
+    })?;
+
+  let code = models::SessionCode::generate();
+
+  repositories.session.create_with_metadata(
+    &user_id, &code,
+    &models::SessionMetadata {
+      user_agent: request.user_agent.clone(),
+      ip: request.ip.clone()
+    }
+  )?;
+
+  Response::json(201, &json!({ "code": code, "user_id": user_id }))
+}
+
+fn logout(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  authenticate(repositories, request)?;
+
+  repositories.session.delete(&session_code(request)?)?;
+
+  Ok(Response::no_content())
+}
+
+fn get_settings(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let user = repositories.user.get_user_settings(&user_id)?;
+
+  Response::json(200, &user)
+}
+
+fn edit_settings(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let mut settings: models::Settings = serde_json::from_str(&request.body)?;
+
+  if settings.posts_per_page < 1 {
+    return Err(
+      Box::new(
+        ValidationError::new("posts_per_page should be positive")
+      )
+    );
+  }
+
+  settings.user_id = user_id;
+
+  repositories.user.edit(&settings)?;
+
+  Ok(Response::no_content())
+}
+
+fn edit_profile(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let profile: Profile = serde_json::from_str(&request.body)?;
+
+  repositories.user.edit_profile(&user_id, &profile.first_name, &profile.last_name)?;
+
+  Ok(Response::no_content())
+}
+
+fn change_email(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let email: Email = serde_json::from_str(&request.body)?;
+
+  repositories.user.change_email(&user_id, &email.email)?;
+
+  Ok(Response::no_content())
+}
+
+// Ends every session of the user, this one included
+fn change_password(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let passwords: Passwords = serde_json::from_str(&request.body)?;
+
+  repositories.user.change_password(&user_id, &passwords.old_password, &passwords.new_password)
+    .map_err(|err| {
+      if err.is::<NotFoundError>() {
+        Box::new(UnauthorizedError::new("Wrong password"))
+      } else {
+        err
+      }
+    })?;
+
+  Ok(Response::no_content())
+}
+
+fn delete_account(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.user.delete(&user_id)?;
+
+  Ok(Response::no_content())
+}
+
+fn export_personal_data(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let personal_data = repositories.user.export_personal_data(&user_id)?;
+
+  Response::json(200, &personal_data)
+}
+
+fn create_post(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let mut post: models::Post = serde_json::from_str(&request.body)?;
+
+  let mut author = models::User::new();
+
+  author.id = user_id;
+
+  post.id = models::PostId::new();
+  post.liked = false;
+  post.author = Some(author);
+
+  let post_id = repositories.post.create(&post)?;
+
+  Response::json(201, &json!({ "id": post_id }))
+}
+
+fn get_post(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate_optional(repositories, request)?;
+
+  let post = repositories.post.get(&id.parse()?, user_id.as_ref())?;
+
+  Response::json(200, &post)
+}
+
+fn list_posts(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate_optional(repositories, request)?;
+
+  let posts = repositories.post.list(user_id.as_ref())?;
+
+  Response::json(200, &posts)
+}
+
+fn liked_posts(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let posts = repositories.post.liked_list(&user_id)?;
+
+  Response::json(200, &posts)
+}
+
+fn like(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.like.create(&user_id, &id.parse()?)?;
+
+  Ok(Response::no_content())
+}
+
+fn unlike(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.like.delete(&user_id, &id.parse()?)?;
+
+  Ok(Response::no_content())
+}
+
+fn feed(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let page = match request.param("page") {
+    Some(page) => page.parse::<u32>()
+      .map_err(|_| ValidationError::new("page should be a non-negative number"))?,
+    None => 0
+  };
+
+  let posts = repositories.post.feed(&user_id, page)?;
+
+  Response::json(200, &posts)
+}
+
+fn follow(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.follow.follow(&user_id, &id.parse()?)?;
+
+  Ok(Response::no_content())
+}
+
+fn unfollow(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.follow.unfollow(&user_id, &id.parse()?)?;
+
+  Ok(Response::no_content())
+}
+
+fn followers(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  authenticate_optional(repositories, request)?;
+
+  let user_ids = repositories.follow.followers(&id.parse()?)?;
+
+  Response::json(200, &user_ids)
+}
+
+fn following(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  authenticate_optional(repositories, request)?;
+
+  let user_ids = repositories.follow.following(&id.parse()?)?;
+
+  Response::json(200, &user_ids)
+}
+
+fn follow_counts(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  authenticate_optional(repositories, request)?;
+
+  let counts = repositories.follow.counts(&id.parse()?)?;
+
+  Response::json(200, &counts)
+}
+
+fn create_webhook(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let mut webhook: models::Webhook = serde_json::from_str(&request.body)?;
+
+  // Checked again before every delivery, the host may resolve differently by then
+  Url::parse(&webhook.url)?.resolve(false)?;
+
+  webhook.id = models::WebhookId::new();
+  webhook.user_id = user_id;
+
+  if webhook.secret.is_empty() {
+    webhook.secret = uuid::Uuid::new_v4().simple().to_string();
+  }
+
+  let webhook_id = repositories.webhook.create(&webhook)?;
+
+  // The only response the secret is ever part of
+  Response::json(201, &json!({ "id": webhook_id, "secret": webhook.secret }))
+}
+
+fn list_webhooks(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let webhooks = repositories.webhook.list(&user_id)?;
+
+  Response::json(200, &webhooks)
+}
+
+fn delete_webhook(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  repositories.webhook.delete(&id.parse()?, &user_id)?;
+
+  Ok(Response::no_content())
+}
+
+fn deliveries(repositories: &Repositories, request: &Request, id: &str) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let deliveries = repositories.webhook.deliveries(&id.parse()?, &user_id)?;
+
+  Response::json(200, &deliveries)
+}
+
+fn dead_letters(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let deliveries = repositories.webhook.dead_letters(&user_id)?;
+
+  Response::json(200, &deliveries)
+}
+
+fn list_notifications(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let notifications = repositories.notification.list(&user_id)?;
+
+  Response::json(200, &notifications)
+}
+
+fn count_unread(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let count = repositories.notification.count_unread(&user_id)?;
+
+  Response::json(200, &json!({ "count": count }))
+}
+
+fn mark_read(repositories: &Repositories, request: &Request) -> Result<Response, Box<dyn error::Error>> {
+  let user_id = authenticate(repositories, request)?;
+
+  let notification_ids: NotificationIds = serde_json::from_str(&request.body)?;
+
+  repositories.notification.mark_read(&user_id, &notification_ids.ids)?;
+
+  Ok(Response::no_content())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{collections::HashMap, error, sync::Mutex, time::SystemTime};
+
+  use serde_json::json;
+
+  use db_rust::{models, repository};
+  use db_rust::repository::{Backend, Repositories};
+  use db_rust::utils::error::{ConflictError, InvalidIdError, NotFoundError, StringError, ValidationError};
+
+  use crate::api::PayloadTooLargeError;
+
+  use super::{handle, Request, Response, UnauthorizedError};
+
+  struct User {}
+
+  impl repository::User for User {
+    fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+      if user.email.as_deref().is_some_and(|email| email.eq_ignore_ascii_case("user@test.test")) {
+        return Err(Box::new(ConflictError::new("User with this email already exists")));
+      }
+
+      user.id = models::UserId::from(1);
+
+      Ok(user.id)
+    }
+
+    fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+      if email == "user@test.test" && password == "secret" {
+        Ok(models::UserId::from(1))
+      } else {
+        Err(Box::new(NotFoundError::new("User with this email and password doesn't exist")))
+      }
+    }
+
+    fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+      let mut user = models::User::new();
+
+      user.id = *id;
+      user.settings.user_id = *id;
+
+      Ok(user)
+    }
+
+    fn edit(&self, _settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn edit_profile(&self, _id: &models::UserId, _first_name: &str, _last_name: &str) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn change_email(&self, _id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+      if email.is_empty() {
+        Err(Box::new(ValidationError::new("Email should be non-empty")))
+      } else {
+        Ok(())
+      }
+    }
+
+    fn change_password(&self, _id: &models::UserId, old_password: &str, _new_password: &str) -> Result<(), Box<dyn error::Error>> {
+      if old_password == "secret" {
+        Ok(())
+      } else {
+        Err(Box::new(NotFoundError::new("User with this id and password doesn't exist")))
+      }
+    }
+
+    fn delete(&self, _id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+      Ok(
+        models::PersonalData {
+          user: self.get_user_settings(id)?,
+          sessions: vec![models::SessionExport::new(&models::SessionCode::new("code"), models::SessionMetadata::new())],
+          posts: Vec::new(),
+          liked: Vec::new()
+        }
+      )
+    }
+  }
+
+  struct Session {
+    codes: Mutex<HashMap<models::SessionCode, models::UserId>>
+  }
+
+  impl repository::Session for Session {
+    fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+      self.codes.lock().unwrap()
+        .get(code)
+        .copied()
+        .ok_or(Box::new(NotFoundError::new("User with this session code doesn't exist")))
+    }
+
+    fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+      self.codes.lock().unwrap().insert(code.clone(), *user_id);
+
+      Ok(())
+    }
+
+    fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+      self.codes.lock().unwrap().remove(code);
+
+      Ok(())
+    }
+  }
+
+  struct Post {}
+
+  impl repository::Post for Post {
+    fn create(&self, _post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+      Ok(models::PostId::from(1))
+    }
+
+    fn get(&self, id: &models::PostId, _user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+      Err(Box::new(NotFoundError::new(&format!("Post {} not found", id))))
+    }
+
+    fn list(&self, _user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn liked_list(&self, _user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn feed(&self, _user_id: &models::UserId, _page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+  }
+
+  struct Like {}
+
+  impl repository::Like for Like {
+    fn create(&self, _user_id: &models::UserId, _post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn delete(&self, _user_id: &models::UserId, _post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+  }
+
+  struct Outbox {}
+
+  impl repository::Outbox for Outbox {
+    fn poll(&self, _consumer: &str, _limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn acknowledge(&self, _consumer: &str, _ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+  }
+
+  struct Webhook {}
+
+  impl repository::Webhook for Webhook {
+    fn create(&self, _webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>> {
+      Ok(models::WebhookId::from(1))
+    }
+
+    fn list(&self, _user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn delete(&self, id: &models::WebhookId, _user_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+      if *id == models::WebhookId::from(1) {
+        Ok(())
+      } else {
+        Err(Box::new(NotFoundError::new(&format!("Webhook {} not found", id))))
+      }
+    }
+
+    fn subscribers(
+      &self,
+      _topic: &models::Topic, _owner: Option<&models::UserId>
+    ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn record(&self, _delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+      Ok(models::DeliveryId::from(1))
+    }
+
+    fn due(
+      &self,
+      _now: SystemTime, _limit: usize
+    ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn deliveries(
+      &self,
+      _id: &models::WebhookId, _user_id: &models::UserId
+    ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn dead_letters(&self, _user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+  }
+
+  struct Notification {}
+
+  impl repository::Notification for Notification {
+    fn list(&self, _user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn mark_read(&self, _user_id: &models::UserId, _ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn count_unread(&self, _user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>> {
+      Ok(3)
+    }
+  }
+
+  struct Follow {}
+
+  impl repository::Follow for Follow {
+    fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+      if follower_id == author_id {
+        Err(Box::new(ValidationError::new("Users can't follow themselves")))
+      } else {
+        Ok(())
+      }
+    }
+
+    fn unfollow(&self, _follower_id: &models::UserId, _author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+      Ok(())
+    }
+
+    fn followers(&self, _user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+      Ok(vec![models::UserId::from(2)])
+    }
+
+    fn following(&self, _user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+      Ok(Vec::new())
+    }
+
+    fn counts(&self, _user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+      Ok(models::FollowCounts { followers: 1, following: 0 })
+    }
+  }
+
+  fn repositories() -> Repositories {
+    Repositories {
+      user: Box::new(User {}),
+      session: Box::new(Session { codes: Mutex::new(HashMap::new()) }),
+      post: Box::new(Post {}),
+      like: Box::new(Like {}),
+      outbox: Box::new(Outbox {}),
+      webhook: Box::new(Webhook {}),
+      notification: Box::new(Notification {}),
+      follow: Box::new(Follow {})
+    }
+  }
+
+  fn request(method: &str, path: &str, body: &str, code: Option<&str>) -> Request {
+    let mut request = Request::new(method, path);
+
+    request.body = body.to_owned();
+    request.authorization = code.map(|code| format!("Bearer {}", code));
+
+    request
+  }
+
+  // Signs in the mock user and returns the session code
+  fn login(repositories: &Repositories) -> String {
+    let response = handle(
+      repositories,
+      &request("POST", "/sessions", r#"{"email": "user@test.test", "password": "secret"}"#, None)
+    );
+
+    assert_eq!(response.status, 201);
+
+    response.body.unwrap()["code"].as_str().unwrap().to_owned()
+  }
+
+  #[test]
+  fn test_errors() {
+    let statuses = [
+      (Response::from_error(Box::new(UnauthorizedError::new("error"))), 401),
+      (Response::from_error(Box::new(NotFoundError::new("error"))), 404),
+      (Response::from_error(Box::new(ConflictError::new("error"))), 409),
+      (Response::from_error(Box::new(PayloadTooLargeError::new("error"))), 413),
+      (Response::from_error(Box::new(ValidationError::new("error"))), 400),
+      (Response::from_error(Box::new(InvalidIdError::new("error"))), 400),
+      (Response::from_error(Box::new(StringError::new("error"))), 500)
+    ];
+
+    for (response, status) in statuses {
+      assert_eq!(response.status, status);
+    }
+  }
+
+  #[test]
+  fn test_sessions() {
+    let repositories = repositories();
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/users", r#"{"first_name": "a", "last_name": "b", "password": "secret"}"#, None)
+    );
+
+    assert_eq!(response.status, 201);
+    assert_eq!(response.body.unwrap()["id"], "1");
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/users", r#"{"first_name": "a", "last_name": "b", "email": "User@test.test", "password": "secret"}"#, None)
+    );
+
+    assert_eq!(response.status, 409);
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/sessions", r#"{"email": "user@test.test", "password": "wrong"}"#, None)
+    );
+
+    assert_eq!(response.status, 401);
+
+    let code = login(&repositories);
+
+    assert_eq!(handle(&repositories, &request("GET", "/settings", "", None)).status, 401);
+    assert_eq!(handle(&repositories, &request("GET", "/settings", "", Some("unknown"))).status, 401);
+    assert_eq!(handle(&repositories, &request("GET", "/settings", "", Some(&code))).status, 200);
+
+    assert_eq!(handle(&repositories, &request("DELETE", "/sessions", "", Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("GET", "/settings", "", Some(&code))).status, 401);
+  }
+
+  // Through the memory backend rather than the mocks, so the conflict
+  // comes from the repository's own check of emails ignoring case
+  #[test]
+  fn test_email_conflicts() {
+    let repositories = Repositories::with_backend(Backend::Memory);
+
+    for (email, status) in [("user@test.test", 201), ("User@Test.test", 409), ("other@test.test", 201)] {
+      let body = json!({ "first_name": "a", "last_name": "b", "email": email, "password": "secret" });
+
+      assert_eq!(handle(&repositories, &request("POST", "/users", &body.to_string(), None)).status, status);
+    }
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/sessions", r#"{"email": "OTHER@test.test", "password": "secret"}"#, None)
+    );
+
+    assert_eq!(response.status, 201);
+
+    let code = response.body.unwrap()["code"].as_str().unwrap().to_owned();
+
+    let response = handle(&repositories, &request("PUT", "/email", r#"{"email": "USER@test.test"}"#, Some(&code)));
+
+    assert_eq!(response.status, 409);
+
+    let response = handle(&repositories, &request("PUT", "/email", r#"{"email": "Other@test.test"}"#, Some(&code)));
+
+    assert_eq!(response.status, 204);
+  }
+
+  #[test]
+  fn test_settings() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    let response = handle(&repositories, &request("GET", "/settings", "", Some(&code)));
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.unwrap()["settings"]["user_id"], "1");
+
+    let response = handle(
+      &repositories,
+      &request("PUT", "/settings", r#"{"posts_per_page": 0, "display_email": true}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 400);
+
+    let response = handle(
+      &repositories,
+      &request("PUT", "/settings", r#"{"posts_per_page": 20, "display_email": true}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 204);
+  }
+
+  #[test]
+  fn test_account() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    let response = handle(
+      &repositories,
+      &request("PUT", "/profile", r#"{"first_name": "first", "last_name": "last"}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 204);
+    assert_eq!(handle(&repositories, &request("PUT", "/email", r#"{"email": "new@test.test"}"#, Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("PUT", "/email", r#"{"email": ""}"#, Some(&code))).status, 400);
+
+    let response = handle(
+      &repositories,
+      &request("PUT", "/password", r#"{"old_password": "wrong", "new_password": "new"}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 401);
+
+    let response = handle(
+      &repositories,
+      &request("PUT", "/password", r#"{"old_password": "secret", "new_password": "new"}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 204);
+
+    let response = handle(&repositories, &request("GET", "/account/data", "", Some(&code)));
+
+    assert_eq!(response.status, 200);
+
+    let body = response.body.unwrap();
+
+    assert_eq!(body["user"]["id"], "1");
+    assert_eq!(body["sessions"][0]["code_hash"].as_str().unwrap().len(), 32);
+
+    assert_eq!(handle(&repositories, &request("GET", "/account/data", "", None)).status, 401);
+    assert_eq!(handle(&repositories, &request("DELETE", "/account", "", None)).status, 401);
+    assert_eq!(handle(&repositories, &request("DELETE", "/account", "", Some(&code))).status, 204);
+  }
+
+  #[test]
+  fn test_posts() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    let response = handle(&repositories, &request("POST", "/posts", r#"{"title": "title"}"#, Some(&code)));
+
+    assert_eq!(response.status, 201);
+
+    assert_eq!(handle(&repositories, &request("GET", "/posts?page=1", "", None)).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/posts/liked", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/posts/2", "", None)).status, 404);
+    assert_eq!(handle(&repositories, &request("GET", "/posts/not-an-id", "", None)).status, 400);
+    assert_eq!(handle(&repositories, &request("PUT", "/posts/2/like", "", Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("DELETE", "/posts/2/like", "", Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("PUT", "/posts/2/like", "", None)).status, 401);
+    assert_eq!(handle(&repositories, &request("GET", "/unknown", "", None)).status, 404);
+  }
+
+  #[test]
+  fn test_follows() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    assert_eq!(handle(&repositories, &request("GET", "/feed?page=2", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/feed?page=-1", "", Some(&code))).status, 400);
+    assert_eq!(handle(&repositories, &request("GET", "/feed", "", None)).status, 401);
+    assert_eq!(handle(&repositories, &request("PUT", "/users/2/follow", "", Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("PUT", "/users/1/follow", "", Some(&code))).status, 400);
+    assert_eq!(handle(&repositories, &request("DELETE", "/users/2/follow", "", Some(&code))).status, 204);
+
+    let response = handle(&repositories, &request("GET", "/users/1/followers", "", None));
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.unwrap()[0], "2");
+
+    let response = handle(&repositories, &request("GET", "/users/1/follows", "", None));
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.unwrap()["followers"], 1);
+  }
+
+  #[test]
+  fn test_webhooks() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/webhooks", r#"{"url": "http://93.184.215.14/hook", "topic": {"type": "post_liked"}}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 201);
+    assert!(!response.body.unwrap()["secret"].as_str().unwrap().is_empty());
+
+    for url in ["http://127.0.0.1/hook", "http://localhost:8080/hook", "http://169.254.169.254/latest/meta-data", "https://10.0.0.1/hook"] {
+      let body = json!({ "url": url, "topic": { "type": "post_liked" } }).to_string();
+
+      assert_eq!(handle(&repositories, &request("POST", "/webhooks", &body, Some(&code))).status, 400, "{}", url);
+    }
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/webhooks", r#"{"url": "ftp://127.0.0.1/hook", "topic": {"type": "post_liked"}}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 400);
+
+    let response = handle(
+      &repositories,
+      &request("POST", "/webhooks", r#"{"url": "http://127.0.0.1/hook HTTP/1.1\r\nX-Injected: 1", "topic": {"type": "post_liked"}}"#, Some(&code))
+    );
+
+    assert_eq!(response.status, 400);
+    assert_eq!(handle(&repositories, &request("GET", "/webhooks", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/webhooks/dead-letters", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/webhooks/1/deliveries", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("DELETE", "/webhooks/2", "", Some(&code))).status, 404);
+    assert_eq!(handle(&repositories, &request("DELETE", "/webhooks/1", "", Some(&code))).status, 204);
+  }
+
+  #[test]
+  fn test_notifications() {
+    let repositories = repositories();
+    let code = login(&repositories);
+
+    assert_eq!(handle(&repositories, &request("GET", "/notifications", "", Some(&code))).status, 200);
+    assert_eq!(handle(&repositories, &request("GET", "/notifications", "", None)).status, 401);
+
+    let response = handle(&repositories, &request("GET", "/notifications/unread", "", Some(&code)));
+
+    assert_eq!(response.status, 200);
+    assert_eq!(response.body.unwrap()["count"], 3);
+
+    assert_eq!(handle(&repositories, &request("PUT", "/notifications/read", r#"{"ids": ["1", 2]}"#, Some(&code))).status, 204);
+    assert_eq!(handle(&repositories, &request("PUT", "/notifications/read", r#"{"ids": ["x"]}"#, Some(&code))).status, 400);
+  }
+}
@@ -0,0 +1,94 @@
+use std::time::SystemTime;
+
+use super::{EventId, PostId, UserId};
+
+// Only ids are carried, consumers read the current state themselves
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Event {
+  UserRegistered { user_id: UserId },
+  SettingsChanged { user_id: UserId },
+  UserDeleted { user_id: UserId },
+  PostPublished { post_id: PostId, user_id: Option<UserId> },
+  PostLiked { user_id: UserId, post_id: PostId },
+  PostUnliked { user_id: UserId, post_id: PostId }
+}
+
+impl Event {
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Event::UserRegistered { .. } => "user_registered",
+      Event::SettingsChanged { .. } => "settings_changed",
+      Event::UserDeleted { .. } => "user_deleted",
+      Event::PostPublished { .. } => "post_published",
+      Event::PostLiked { .. } => "post_liked",
+      Event::PostUnliked { .. } => "post_unliked"
+    }
+  }
+
+  pub fn user_id(&self) -> Option<UserId> {
+    match *self {
+      Event::UserRegistered { user_id }
+      | Event::SettingsChanged { user_id }
+      | Event::UserDeleted { user_id }
+      | Event::PostLiked { user_id, .. }
+      | Event::PostUnliked { user_id, .. } => Some(user_id),
+      Event::PostPublished { user_id, .. } => user_id
+    }
+  }
+
+  pub fn post_id(&self) -> Option<PostId> {
+    match *self {
+      Event::PostPublished { post_id, .. }
+      | Event::PostLiked { post_id, .. }
+      | Event::PostUnliked { post_id, .. } => Some(post_id),
+      _ => None
+    }
+  }
+
+  // The inverse of kind, user_id and post_id, which is how backends store events
+  pub fn from_parts(kind: &str, user_id: Option<UserId>, post_id: Option<PostId>) -> Option<Self> {
+    match (kind, user_id, post_id) {
+      ("user_registered", Some(user_id), _) => Some(Event::UserRegistered { user_id }),
+      ("settings_changed", Some(user_id), _) => Some(Event::SettingsChanged { user_id }),
+      ("user_deleted", Some(user_id), _) => Some(Event::UserDeleted { user_id }),
+      ("post_published", user_id, Some(post_id)) => Some(Event::PostPublished { post_id, user_id }),
+      ("post_liked", Some(user_id), Some(post_id)) => Some(Event::PostLiked { user_id, post_id }),
+      ("post_unliked", Some(user_id), Some(post_id)) => Some(Event::PostUnliked { user_id, post_id }),
+      _ => None
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct OutboxEvent {
+  pub id: EventId,
+  pub event: Event,
+  pub created_at: SystemTime
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{Event, PostId, UserId};
+
+  #[test]
+  fn test_parts() {
+    let events = [
+      Event::UserRegistered { user_id: UserId::from(1) },
+      Event::SettingsChanged { user_id: UserId::from(1) },
+      Event::UserDeleted { user_id: UserId::from(1) },
+      Event::PostPublished { post_id: PostId::from(2), user_id: None },
+      Event::PostLiked { user_id: UserId::from(1), post_id: PostId::from(2) },
+      Event::PostUnliked { user_id: UserId::from(1), post_id: PostId::from(2) }
+    ];
+
+    for event in events {
+      assert_eq!(Event::from_parts(event.kind(), event.user_id(), event.post_id()), Some(event));
+    }
+
+    assert_eq!(Event::from_parts("post_liked", Some(UserId::from(1)), None), None);
+    assert_eq!(Event::from_parts("unknown", None, None), None);
+  }
+}
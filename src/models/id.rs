@@ -0,0 +1,246 @@
+use std::{fmt, str::FromStr};
+
+use bson::oid::ObjectId;
+
+use crate::utils::error::InvalidIdError;
+
+// Postgres keys rows by serial integers, MongoDB by ObjectIds,
+// an entity that isn't stored yet has no key at all
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+enum Key {
+  #[default]
+  Empty,
+  Int(i32),
+  ObjectId(ObjectId)
+}
+
+macro_rules! entity_id {
+  ($name:ident) => {
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct $name(Key);
+
+    impl $name {
+      pub fn new() -> Self {
+        Self(Key::Empty)
+      }
+
+      pub fn is_empty(&self) -> bool {
+        self.0 == Key::Empty
+      }
+
+      pub fn as_i32(&self) -> Result<i32, InvalidIdError> {
+        match self.0 {
+          Key::Int(id) => Ok(id),
+          _ => Err(InvalidIdError::new(&self.to_string()))
+        }
+      }
+
+      pub fn as_object_id(&self) -> Result<ObjectId, InvalidIdError> {
+        match self.0 {
+          Key::ObjectId(id) => Ok(id),
+          _ => Err(InvalidIdError::new(&self.to_string()))
+        }
+      }
+    }
+
+    impl From<i32> for $name {
+      fn from(id: i32) -> Self {
+        Self(Key::Int(id))
+      }
+    }
+
+    impl From<ObjectId> for $name {
+      fn from(id: ObjectId) -> Self {
+        Self(Key::ObjectId(id))
+      }
+    }
+
+    impl FromStr for $name {
+      type Err = InvalidIdError;
+
+      fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(id) = s.parse::<i32>() {
+          Ok(Self(Key::Int(id)))
+        } else if let Ok(id) = ObjectId::parse_str(s) {
+          Ok(Self(Key::ObjectId(id)))
+        } else {
+          Err(InvalidIdError::new(s))
+        }
+      }
+    }
+
+    impl fmt::Display for $name {
+      fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0 {
+          Key::Empty => Ok(()),
+          Key::Int(id) => write!(f, "{}", id),
+          Key::ObjectId(id) => write!(f, "{}", id)
+        }
+      }
+    }
+
+    #[cfg(feature = "serde")]
+    impl serde::Serialize for $name {
+      fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+      }
+    }
+
+    #[cfg(feature = "serde")]
+    impl<'de> serde::Deserialize<'de> for $name {
+      fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(KeyVisitor)
+          .map(Self)
+      }
+    }
+  };
+}
+
+entity_id!(UserId);
+entity_id!(PostId);
+entity_id!(EventId);
+entity_id!(WebhookId);
+entity_id!(DeliveryId);
+entity_id!(NotificationId);
+
+#[cfg(feature = "serde")]
+struct KeyVisitor;
+
+#[cfg(feature = "serde")]
+impl<'de> serde::de::Visitor<'de> for KeyVisitor {
+  type Value = Key;
+
+  fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "an integer or an ObjectId string")
+  }
+
+  fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Key, E> {
+    i32::try_from(v)
+      .map(Key::Int)
+      .map_err(|_| E::custom(InvalidIdError::new(&v.to_string())))
+  }
+
+  fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Key, E> {
+    i32::try_from(v)
+      .map(Key::Int)
+      .map_err(|_| E::custom(InvalidIdError::new(&v.to_string())))
+  }
+
+  fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Key, E> {
+    if v.is_empty() {
+      return Ok(Key::Empty);
+    }
+
+    UserId::from_str(v)
+      .map(|id| id.0)
+      .map_err(E::custom)
+  }
+
+  fn visit_unit<E: serde::de::Error>(self) -> Result<Key, E> {
+    Ok(Key::Empty)
+  }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct SessionCode(String);
+
+impl SessionCode {
+  pub fn new(code: &str) -> Self {
+    Self(code.to_owned())
+  }
+
+  pub fn generate() -> Self {
+    Self(uuid::Uuid::new_v4().to_string())
+  }
+
+  pub fn as_str(&self) -> &str {
+    &self.0
+  }
+}
+
+impl FromStr for SessionCode {
+  type Err = InvalidIdError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    if s.is_empty() {
+      Err(InvalidIdError::new(s))
+    } else {
+      Ok(Self::new(s))
+    }
+  }
+}
+
+impl fmt::Display for SessionCode {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for SessionCode {
+  fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&self.0)
+  }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for SessionCode {
+  fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    let code = String::deserialize(deserializer)?;
+
+    code.parse()
+      .map_err(serde::de::Error::custom)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use bson::oid::ObjectId;
+
+  use super::{PostId, SessionCode, UserId};
+
+  #[test]
+  fn test_parse() {
+    let id: UserId = "42".parse().unwrap();
+
+    assert_eq!(id, UserId::from(42));
+    assert_eq!(id.as_i32().unwrap(), 42);
+    assert!(id.as_object_id().is_err());
+    assert_eq!(id.to_string(), "42");
+
+    let object_id = ObjectId::new();
+    let id: PostId = object_id.to_string().parse().unwrap();
+
+    assert_eq!(id, PostId::from(object_id));
+    assert_eq!(id.as_object_id().unwrap(), object_id);
+    assert!(id.as_i32().is_err());
+    assert_eq!(id.to_string(), object_id.to_string());
+
+    assert!("not-an-id".parse::<UserId>().is_err());
+    assert!("".parse::<UserId>().is_err());
+    assert!("".parse::<SessionCode>().is_err());
+
+    assert!(UserId::new().is_empty());
+    assert_eq!(UserId::new().to_string(), "");
+  }
+
+  #[cfg(feature = "serde")]
+  #[test]
+  fn test_serde() {
+    let id = UserId::from(7);
+
+    assert_eq!(serde_json::to_string(&id).unwrap(), "\"7\"");
+    assert_eq!(serde_json::from_str::<UserId>("7").unwrap(), id);
+    assert_eq!(serde_json::from_str::<UserId>("\"7\"").unwrap(), id);
+
+    let id = PostId::from(ObjectId::new());
+    let json = serde_json::to_string(&id).unwrap();
+
+    assert_eq!(serde_json::from_str::<PostId>(&json).unwrap(), id);
+
+    let code = SessionCode::generate();
+    let json = serde_json::to_string(&code).unwrap();
+
+    assert_eq!(serde_json::from_str::<SessionCode>(&json).unwrap(), code);
+  }
+}
@@ -1,7 +1,23 @@
+//! The data the repositories store, independent of the backend.
+
 mod user;
 mod settings;
 mod post;
+mod session;
+mod event;
+mod webhook;
+mod notification;
+mod follow;
+mod personal_data;
+mod id;
 
 pub use user::User;
 pub use settings::Settings;
 pub use post::Post;
+pub use session::SessionMetadata;
+pub use event::{Event, OutboxEvent};
+pub use webhook::{Webhook, Topic, Delivery, DeliveryStatus};
+pub use notification::{Notification, NotificationKind};
+pub use follow::FollowCounts;
+pub use personal_data::{PersonalData, SessionExport};
+pub use id::{UserId, PostId, EventId, WebhookId, DeliveryId, NotificationId, SessionCode};
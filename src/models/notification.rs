@@ -0,0 +1,52 @@
+use std::time::SystemTime;
+
+use super::{NotificationId, PostId, UserId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NotificationKind {
+  PostLiked
+}
+
+impl NotificationKind {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      NotificationKind::PostLiked => "post_liked"
+    }
+  }
+
+  pub fn parse(s: &str) -> Option<Self> {
+    match s {
+      "post_liked" => Some(NotificationKind::PostLiked),
+      _ => None
+    }
+  }
+}
+
+// Tells user_id that actor_id did something to their post
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Notification {
+  pub id: NotificationId,
+  pub user_id: UserId,
+  pub kind: NotificationKind,
+  pub actor_id: UserId,
+  pub post_id: PostId,
+  pub read: bool,
+  pub created_at: SystemTime
+}
+
+impl Notification {
+  pub fn post_liked(author_id: UserId, user_id: UserId, post_id: PostId) -> Self {
+    Self {
+      id: NotificationId::new(),
+      user_id: author_id,
+      kind: NotificationKind::PostLiked,
+      actor_id: user_id,
+      post_id,
+      read: false,
+      created_at: SystemTime::now()
+    }
+  }
+}
@@ -0,0 +1,30 @@
+use super::{Post, SessionCode, SessionMetadata, User};
+
+// Everything stored about one user, for answering a data subject access
+// request. The user's email is included whatever their settings say
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PersonalData {
+  pub user: User,
+  pub sessions: Vec<SessionExport>,
+  pub posts: Vec<Post>,
+  pub liked: Vec<Post>
+}
+
+// Session codes are credentials, so only their hashes leave the store
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionExport {
+  pub code_hash: String,
+  #[cfg_attr(feature = "serde", serde(flatten))]
+  pub metadata: SessionMetadata
+}
+
+impl SessionExport {
+  pub fn new(code: &SessionCode, metadata: SessionMetadata) -> Self {
+    Self {
+      code_hash: format!("{:x}", md5::compute(code.as_str())),
+      metadata
+    }
+  }
+}
@@ -1,12 +1,18 @@
-use super::User;
+use super::{PostId, User};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Post {
-  pub id: String,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub id: PostId,
   pub title: String,
+  #[cfg_attr(feature = "serde", serde(default))]
   pub text: Option<String>,
+  #[cfg_attr(feature = "serde", serde(default))]
   pub description: Option<String>,
+  #[cfg_attr(feature = "serde", serde(default))]
   pub liked: bool,
   
+  #[cfg_attr(feature = "serde", serde(default))]
   pub author: Option<User>
 }
@@ -0,0 +1,15 @@
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionMetadata {
+  pub user_agent: Option<String>,
+  pub ip: Option<String>
+}
+
+impl SessionMetadata {
+  pub fn new() -> Self {
+    Self {
+      user_agent: None,
+      ip: None
+    }
+  }
+}
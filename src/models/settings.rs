@@ -1,7 +1,12 @@
-#[derive(Debug)]
+use super::UserId;
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Settings {
+  #[cfg_attr(feature = "serde", serde(default))]
   pub id: String,
-  pub user_id: String,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub user_id: UserId,
   pub posts_per_page: i32,
   pub display_email: bool
 }
@@ -10,9 +15,15 @@ impl Settings {
   pub fn new() -> Self {
     Self {
       id: String::new(),
-      user_id: String::new(),
+      user_id: UserId::new(),
       posts_per_page: 10,
       display_email: false
     }
   }
 }
+
+impl Default for Settings {
+  fn default() -> Self {
+    Self::new()
+  }
+}
@@ -1,20 +1,25 @@
-use super::Settings;
+use super::{Settings, UserId};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct User {
-  pub id: String,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub id: UserId,
   pub first_name: String,
   pub last_name: String,
+  #[cfg_attr(feature = "serde", serde(default, skip_serializing_if = "Option::is_none"))]
   pub email: Option<String>,
+  #[cfg_attr(feature = "serde", serde(default, skip_serializing))]
   pub password: Option<String>,
 
+  #[cfg_attr(feature = "serde", serde(default))]
   pub settings: Settings  
 }
 
 impl User {
   pub fn new() -> Self {
     Self {
-      id: String::new(),
+      id: UserId::new(),
       first_name: String::new(),
       last_name: String::new(),
       email: None,
@@ -24,3 +29,44 @@ impl User {
     }
   }
 }
+
+impl Default for User {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+  use super::User;
+
+  #[test]
+  fn test_serde() {
+    let mut user = User::new();
+
+    user.id = 1.into();
+    user.first_name = "first".to_owned();
+    user.last_name = "last".to_owned();
+    user.password = Some("secret".to_owned());
+
+    let json = serde_json::to_value(&user).unwrap();
+
+    assert_eq!(json["id"], "1");
+    assert!(json.get("password").is_none());
+    assert!(json.get("email").is_none());
+
+    user.email = Some("first@last.test".to_owned());
+
+    let json = serde_json::to_value(&user).unwrap();
+
+    assert_eq!(json["email"], "first@last.test");
+
+    let user: User = serde_json::from_str(
+      r#"{"first_name": "a", "last_name": "b", "password": "secret"}"#
+    ).unwrap();
+
+    assert!(user.id.is_empty());
+    assert_eq!(user.password.as_deref(), Some("secret"));
+    assert_eq!(user.settings.posts_per_page, 10);
+  }
+}
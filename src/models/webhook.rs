@@ -0,0 +1,116 @@
+use std::time::SystemTime;
+
+use super::{DeliveryId, EventId, UserId, WebhookId};
+
+// What a webhook is called for: likes on its owner's posts,
+// or posts published by one author
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum Topic {
+  PostLiked,
+  PostPublished { author_id: UserId }
+}
+
+impl Topic {
+  pub fn kind(&self) -> &'static str {
+    match self {
+      Topic::PostLiked => "post_liked",
+      Topic::PostPublished { .. } => "post_published"
+    }
+  }
+
+  pub fn author_id(&self) -> Option<UserId> {
+    match *self {
+      Topic::PostPublished { author_id } => Some(author_id),
+      _ => None
+    }
+  }
+
+  pub fn from_parts(kind: &str, author_id: Option<UserId>) -> Option<Self> {
+    match (kind, author_id) {
+      ("post_liked", _) => Some(Topic::PostLiked),
+      ("post_published", Some(author_id)) => Some(Topic::PostPublished { author_id }),
+      _ => None
+    }
+  }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Webhook {
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub id: WebhookId,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub user_id: UserId,
+  pub url: String,
+  // Only the owner sees it, once, when the webhook is registered
+  #[cfg_attr(feature = "serde", serde(default, skip_serializing))]
+  pub secret: String,
+  pub topic: Topic
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum DeliveryStatus {
+  Delivered,
+  // The attempt failed and another one follows
+  Failed,
+  // The last attempt failed, the delivery is kept as a dead letter
+  Dead
+}
+
+impl DeliveryStatus {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DeliveryStatus::Delivered => "delivered",
+      DeliveryStatus::Failed => "failed",
+      DeliveryStatus::Dead => "dead"
+    }
+  }
+
+  pub fn parse(s: &str) -> Option<Self> {
+    match s {
+      "delivered" => Some(DeliveryStatus::Delivered),
+      "failed" => Some(DeliveryStatus::Failed),
+      "dead" => Some(DeliveryStatus::Dead),
+      _ => None
+    }
+  }
+}
+
+// One attempt to deliver an event, the payload is kept so dead letters can be replayed
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Delivery {
+  pub id: DeliveryId,
+  pub webhook_id: WebhookId,
+  pub event_id: EventId,
+  pub attempt: i32,
+  pub status: DeliveryStatus,
+  pub response_status: Option<i32>,
+  pub error: Option<String>,
+  pub payload: String,
+  pub created_at: SystemTime,
+  // When a failed attempt is retried, cleared once the retry is recorded
+  pub next_attempt_at: Option<SystemTime>
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{DeliveryStatus, Topic, UserId};
+
+  #[test]
+  fn test_parts() {
+    for topic in [Topic::PostLiked, Topic::PostPublished { author_id: UserId::from(1) }] {
+      assert_eq!(Topic::from_parts(topic.kind(), topic.author_id()), Some(topic));
+    }
+
+    assert_eq!(Topic::from_parts("post_published", None), None);
+
+    for status in [DeliveryStatus::Delivered, DeliveryStatus::Failed, DeliveryStatus::Dead] {
+      assert_eq!(DeliveryStatus::parse(status.as_str()), Some(status));
+    }
+  }
+}
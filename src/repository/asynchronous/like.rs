@@ -0,0 +1,12 @@
+use async_trait::async_trait;
+
+use crate::models;
+
+use super::Error;
+
+#[async_trait]
+pub trait Like: Send + Sync {
+  async fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error>;
+
+  async fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error>;
+}
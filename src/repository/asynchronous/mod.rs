@@ -0,0 +1,19 @@
+use std::error;
+
+mod user;
+mod session;
+mod post;
+mod like;
+
+#[cfg(feature = "postgres")]
+pub mod postgresql;
+#[cfg(feature = "mongodb")]
+pub mod mongodb;
+
+pub use user::User;
+pub use session::Session;
+pub use post::Post;
+pub use like::Like;
+
+// Errors cross await points and tasks, so they have to be sendable
+pub type Error = Box<dyn error::Error + Send + Sync>;
@@ -0,0 +1,117 @@
+use async_trait::async_trait;
+
+use mongodb::{
+  bson::{doc, Document},
+  ClientSession
+};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError};
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Like {}
+
+#[async_trait]
+impl asynchronous::Like for Like {
+  async fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.create_ws(user_id, post_id, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.delete_ws(user_id, post_id, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+}
+
+impl Like {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_ws(
+    &self,
+    user_id: &models::UserId, post_id: &models::PostId,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    // Looked up first, so a missing post fails before anything is written
+    let author_id = super::Post::new().get_ws(post_id, None, session).await?
+      .author
+      .map(|author| author.id)
+      .filter(|author_id| author_id != user_id);
+
+    let like = doc! {
+      "user_id": user_id.as_object_id()?,
+      "post_id": post_id.as_object_id()?
+    };
+
+    let likes = session.client().default_database().unwrap()
+      .collection::<Document>("likes");
+
+    // A failed write would abort the whole transaction, so a repeated like is caught first
+    if likes.find_one_with_session(like.clone(), None, session).await?.is_some() {
+      return Err(Box::new(ConflictError::new("Post is already liked by this user")));
+    }
+
+    likes.insert_one_with_session(like, None, session).await
+      .map_err(|err| utils::conflict(err.into(), "Post is already liked by this user"))?;
+
+    if let Some(author_id) = author_id {
+      super::Notification::new().create_ws(
+        &models::Notification::post_liked(author_id, *user_id, *post_id),
+        session
+      ).await?;
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostLiked { user_id: *user_id, post_id: *post_id },
+      session
+    ).await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_ws(
+    &self,
+    user_id: &models::UserId, post_id: &models::PostId,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("likes")
+      .delete_one_with_session(
+        doc! {
+          "user_id": user_id.as_object_id()?,
+          "post_id": post_id.as_object_id()?
+        },
+        None,
+        session
+      ).await?;
+
+    if res.deleted_count == 0 {
+      return Err(Box::new(NotFoundError::new("Post is not liked by this user")));
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostUnliked { user_id: *user_id, post_id: *post_id },
+      session
+    ).await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,14 @@
+mod user;
+mod session;
+mod post;
+mod like;
+mod outbox;
+mod notification;
+mod utils;
+
+pub use user::User;
+pub use session::Session;
+pub use post::Post;
+pub use like::Like;
+pub use outbox::Outbox;
+pub use notification::Notification;
@@ -0,0 +1,31 @@
+use mongodb::{bson::Document, ClientSession};
+
+use crate::repository::asynchronous::Error;
+use crate::repository::mongodb::document;
+use crate::models;
+
+// Listing and marking read are left to the sync repository::Notification
+#[derive(Default)]
+pub struct Notification {}
+
+impl Notification {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_ws(
+    &self,
+    notification: &models::Notification,
+    session: &mut ClientSession
+  ) -> Result<models::NotificationId, Error> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("notifications")
+      .insert_one_with_session(
+        document::notification_document(notification)?,
+        None,
+        session
+      ).await?;
+
+    Ok(models::NotificationId::from(res.inserted_id.as_object_id().unwrap()))
+  }
+}
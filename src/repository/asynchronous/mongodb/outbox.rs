@@ -0,0 +1,32 @@
+use mongodb::{bson::Document, ClientSession};
+
+use crate::repository::asynchronous::Error;
+use crate::repository::mongodb::document;
+use crate::models;
+
+// Polling and acknowledging are left to the sync repository::Outbox,
+// async writes only append to the same collection
+#[derive(Default)]
+pub struct Outbox {}
+
+impl Outbox {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn append_ws(
+    &self,
+    event: &models::Event,
+    session: &mut ClientSession
+  ) -> Result<models::EventId, Error> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("outbox")
+      .insert_one_with_session(
+        document::event_document(event)?,
+        None,
+        session
+      ).await?;
+
+    Ok(models::EventId::from(res.inserted_id.as_object_id().unwrap()))
+  }
+}
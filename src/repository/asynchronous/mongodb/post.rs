@@ -0,0 +1,199 @@
+use async_trait::async_trait;
+
+use mongodb::{
+  bson::{doc, Document},
+  ClientSession
+};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::repository::mongodb::document::{self, Decoding, PostDocument};
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Post {
+  decoding: Decoding
+}
+
+#[async_trait]
+impl asynchronous::Post for Post {
+  async fn create(&self, post: &models::Post) -> Result<models::PostId, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.create_ws(post, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.get_ws(id, user_id, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.list_ws(user_id, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.liked_list_ws(user_id, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+}
+
+impl Post {
+  pub fn new() -> Self {
+    Self {
+      decoding: Decoding::Strict
+    }
+  }
+
+  pub fn with_decoding(decoding: Decoding) -> Self {
+    Self { decoding }
+  }
+
+  pub async fn create_ws(
+    &self,
+    post: &models::Post,
+    session: &mut ClientSession
+  ) -> Result<models::PostId, Error> {
+    let res = session.client().default_database().unwrap()
+      .collection::<PostDocument>("posts")
+      .insert_one_with_session(
+        PostDocument::try_from(post)?,
+        None,
+        session
+      ).await?;
+
+    let post_id = models::PostId::from(res.inserted_id.as_object_id().unwrap());
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostPublished {
+        post_id,
+        user_id: post.author.as_ref().map(|author| author.id)
+      },
+      session
+    ).await?;
+
+    Ok(post_id)
+  }
+
+  pub async fn get_ws(
+    &self,
+    id: &models::PostId, user_id: Option<&models::UserId>,
+    session: &mut ClientSession
+  ) -> Result<models::Post, Error> {
+    let id = id.as_object_id()?;
+    let user_id = user_id.map(|user_id| user_id.as_object_id()).transpose()?;
+
+    let mut pipeline = vec![
+      doc! {
+        "$match": doc! {
+          "_id": id
+        }
+      }
+    ];
+
+    pipeline.extend(document::post_pipeline(user_id));
+
+    let posts = self.aggregate_ws(pipeline, session).await?;
+
+    posts.into_iter()
+      .next()
+      .ok_or(
+        Box::new(
+          NotFoundError::new(
+            "Post with this id not found"
+          )
+        )
+      )
+  }
+
+  pub async fn list_ws(
+    &self,
+    user_id: Option<&models::UserId>,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Post>, Error> {
+    let user_id = user_id.map(|user_id| user_id.as_object_id()).transpose()?;
+
+    self.aggregate_ws(document::post_pipeline(user_id), session).await
+  }
+
+  pub async fn liked_list_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Post>, Error> {
+    let user_id = user_id.as_object_id()?;
+
+    let mut pipeline = document::post_pipeline(Some(user_id));
+
+    pipeline.push(
+      doc! {
+        "$match": doc! {
+          "liked": true
+        }
+      }
+    );
+
+    self.aggregate_ws(pipeline, session).await
+  }
+
+  async fn aggregate_ws(
+    &self,
+    pipeline: Vec<Document>,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Post>, Error> {
+    let mut data = Vec::new();
+
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("posts")
+      .aggregate_with_session(
+        pipeline,
+        None,
+        session
+      ).await?;
+
+    while let Some(doc) = cursor.next(session).await {
+      data.push(
+        self.read(doc?)?
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn read(&self, doc: Document) -> Result<models::Post, Error> {
+    Ok(document::decode_post(doc, self.decoding)?)
+  }
+}
@@ -0,0 +1,170 @@
+use async_trait::async_trait;
+
+use mongodb::{
+  bson::{doc, DateTime, Document},
+  options::FindOneOptions, ClientSession
+};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::repository::mongodb::SESSION_TTL;
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Session {}
+
+#[async_trait]
+impl asynchronous::Session for Session {
+  async fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.get_user_id_ws(code, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Error> {
+    self.create_with_metadata(user_id, code, &models::SessionMetadata::new()).await
+  }
+
+  async fn create_with_metadata(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    metadata: &models::SessionMetadata
+  ) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.create_with_metadata_ws(user_id, code, metadata, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn delete(&self, code: &models::SessionCode) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.delete_ws(code, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+}
+
+impl Session {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn get_user_id_ws(
+    &self,
+    code: &models::SessionCode,
+    session: &mut ClientSession
+  ) -> Result<models::UserId, Error> {
+    // The TTL monitor only runs once a minute, so expired
+    // sessions can still be around for a while
+    let expired_at = DateTime::from_millis(
+      DateTime::now().timestamp_millis() - SESSION_TTL.as_millis() as i64
+    );
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("sessions")
+      .find_one_with_session(
+        doc! {
+          "code": code.as_str(),
+          "created_at": doc! {
+            "$gt": expired_at
+          }
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "user_id": 1
+            }
+          )
+          .build(),
+        session
+      ).await?;
+
+    res.and_then(
+      |doc| doc.get("user_id")
+        .and_then(|id| id.as_object_id()
+          .map(models::UserId::from))
+    )
+    .ok_or(
+      Box::new(
+        NotFoundError::new("User with this session code doesn't exist")
+      )
+    )
+  }
+
+  pub async fn create_ws(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    self.create_with_metadata_ws(
+      user_id, code,
+      &models::SessionMetadata::new(),
+      session
+    ).await
+  }
+
+  pub async fn create_with_metadata_ws(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    metadata: &models::SessionMetadata,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let user_id = user_id.as_object_id()?;
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("sessions")
+      .insert_one_with_session(
+        doc! {
+          "code": code.as_str(),
+          "user_id": user_id,
+          "created_at": DateTime::now(),
+          "user_agent": &metadata.user_agent,
+          "ip": &metadata.ip
+        },
+        None,
+        session
+      ).await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_ws(
+    &self,
+    code: &models::SessionCode,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    session.client().default_database().unwrap()
+      .collection::<Document>("sessions")
+      .delete_one_with_session(
+        doc! {
+          "code": code.as_str()
+        },
+        None,
+        session
+      ).await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,633 @@
+use async_trait::async_trait;
+
+use mongodb::{
+  bson::{doc, oid::ObjectId, Document},
+  options::FindOneOptions, ClientSession
+};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::repository::mongodb::document::{self, Decoding, UserDocument};
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError, ValidationError};
+
+use crate::repository::mongodb::email_collation;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct User {
+  decoding: Decoding
+}
+
+#[async_trait]
+impl asynchronous::User for User {
+  async fn create(&self, user: &mut models::User) -> Result<models::UserId, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.create_ws(user, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.get_id_ws(email, password, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.get_user_settings_ws(id, &mut session).await;
+
+    session.commit_transaction().await?;
+
+    res
+  }
+
+  async fn edit(&self, settings: &models::Settings) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.edit_ws(settings, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.edit_profile_ws(id, first_name, last_name, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.change_email_ws(id, email, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.change_password_ws(id, old_password, new_password, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+
+  async fn delete(&self, id: &models::UserId) -> Result<(), Error> {
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    let res = self.delete_ws(id, &mut session).await;
+
+    utils::commit_or_abort(&mut session, res).await
+  }
+}
+
+impl User {
+  pub fn new() -> Self {
+    Self {
+      decoding: Decoding::Strict
+    }
+  }
+
+  pub fn with_decoding(decoding: Decoding) -> Self {
+    Self { decoding }
+  }
+
+  pub async fn create_ws(
+    &self,
+    user: &mut models::User,
+    session: &mut ClientSession
+  ) -> Result<models::UserId, Error> {
+    if let Some(password) = user.password.as_ref() {
+      if let Some(email) = user.email.as_deref() {
+        self.check_email_ws(email, None, session).await?;
+      }
+
+      let password = format!("{:x}", md5::compute(password));
+
+      let settings_id = ObjectId::new();
+
+      let mut document = UserDocument::try_from(&*user)?;
+
+      document.password = Some(password);
+
+      if let Some(settings) = document.settings.as_mut() {
+        settings.id = Some(settings_id);
+      }
+
+      let res = session.client().default_database().unwrap()
+        .collection::<UserDocument>("users")
+        .insert_one_with_session(
+          &document,
+          None,
+          session
+        ).await
+        .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
+
+      user.id = models::UserId::from(res.inserted_id.as_object_id().unwrap());
+      user.settings.id = settings_id.to_string();
+      user.settings.user_id = user.id;
+
+      super::Outbox::new().append_ws(
+        &models::Event::UserRegistered { user_id: user.id },
+        session
+      ).await?;
+
+      Ok(user.id)
+    } else {
+      Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
+        )
+      )
+    }
+  }
+
+  // The email_unique_ci index enforces this too, and the writes turn its
+  // error into the same ConflictError when a concurrent one got there first
+  pub async fn check_email_ws(
+    &self,
+    email: &str, except: Option<&models::UserId>,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let except = except.map(|id| id.as_object_id()).transpose()?;
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "email": email
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "_id": 1
+            }
+          )
+          .collation(email_collation())
+          .build(),
+        session
+      ).await?;
+
+    let taken = res.and_then(|doc| doc.get_object_id("_id").ok())
+      .is_some_and(|id| Some(id) != except);
+
+    if taken {
+      return Err(
+        Box::new(
+          ConflictError::new("User with this email already exists")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub async fn get_id_ws(
+    &self,
+    email: &str, password: &str,
+    session: &mut ClientSession
+  ) -> Result<models::UserId, Error> {
+    let password = format!("{:x}", md5::compute(password));
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "email": email,
+          "password": password
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "_id": 1
+            }
+          )
+          .collation(email_collation())
+          .build(),
+        session
+      ).await?;
+
+    res.and_then(
+      |doc| doc.get("_id")
+        .and_then(|id| id.as_object_id()
+          .map(models::UserId::from))
+    )
+    .ok_or(
+      Box::new(
+        NotFoundError::new("User with this email and password doesn't exist")
+      )
+    )
+  }
+
+  pub async fn get_user_settings_ws(
+    &self,
+    id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<models::User, Error> {
+    let id = id.as_object_id()?;
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "_id": id
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "first_name": 1,
+              "last_name": 1,
+              "email": doc! {
+                "$cond": doc! {
+                  "if": "$settings.display_email",
+                  "then": "$email",
+                  "else": "$$REMOVE"
+                }
+              },
+              "settings": 1
+            }
+          )
+          .build(),
+        session
+      ).await?;
+
+    match res {
+      Some(doc) => self.read(doc),
+      None => Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      )
+    }
+  }
+
+  pub async fn edit_ws(
+    &self,
+    settings: &models::Settings,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let user_id = settings.user_id.as_object_id()?;
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .update_one_with_session(
+        doc! {
+          "_id": user_id
+        },
+        doc! {
+          "$set": doc! {
+            "settings.posts_per_page": settings.posts_per_page,
+            "settings.display_email": settings.display_email
+          }
+        },
+        None,
+        session
+      ).await?;
+
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::SettingsChanged { user_id: settings.user_id },
+      session
+    ).await?;
+
+    Ok(())
+  }
+
+  pub async fn edit_profile_ws(
+    &self,
+    id: &models::UserId, first_name: &str, last_name: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    self.update_ws(
+      id,
+      doc! {
+        "first_name": first_name,
+        "last_name": last_name
+      },
+      session
+    ).await
+  }
+
+  pub async fn change_email_ws(
+    &self,
+    id: &models::UserId, email: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    if email.trim().is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Email should be non-empty")
+        )
+      );
+    }
+
+    self.check_email_ws(email, Some(id), session).await?;
+
+    self.update_ws(
+      id,
+      doc! {
+        "email": email
+      },
+      session
+    ).await
+    .map_err(|err| utils::conflict(err, "User with this email already exists"))
+  }
+
+  pub async fn change_password_ws(
+    &self,
+    id: &models::UserId, old_password: &str, new_password: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    if new_password.is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
+        )
+      );
+    }
+
+    let user_id = id.as_object_id()?;
+    let old_password = format!("{:x}", md5::compute(old_password));
+    let new_password = format!("{:x}", md5::compute(new_password));
+
+    let db = session.client().default_database().unwrap();
+
+    let res = db.collection::<Document>("users")
+      .update_one_with_session(
+        doc! {
+          "_id": user_id,
+          "password": old_password
+        },
+        doc! {
+          "$set": doc! {
+            "password": new_password
+          }
+        },
+        None,
+        session
+      ).await?;
+
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id and password doesn't exist")
+        )
+      );
+    }
+
+    db.collection::<Document>("sessions")
+      .delete_many_with_session(
+        doc! {
+          "user_id": user_id
+        },
+        None,
+        session
+      ).await?;
+
+    Ok(())
+  }
+
+  // Nothing cascades in MongoDB, so everything referencing the user goes here
+  pub async fn delete_ws(
+    &self,
+    id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let user_id = id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    let res = db.collection::<Document>("users")
+      .delete_one_with_session(
+        doc! {
+          "_id": user_id
+        },
+        None,
+        session
+      ).await?;
+
+    if res.deleted_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    let mut webhook_ids = Vec::new();
+
+    let mut cursor = db.collection::<Document>("webhooks")
+      .find_with_session(
+        doc! {
+          "$or": [
+            doc! { "user_id": user_id },
+            doc! { "author_id": user_id }
+          ]
+        },
+        None,
+        session
+      ).await?;
+
+    while let Some(doc) = cursor.next(session).await {
+      webhook_ids.push(doc?.get_object_id("_id")?);
+    }
+
+    let deletes = [
+      ("sessions", doc! { "user_id": user_id }),
+      ("likes", doc! { "user_id": user_id }),
+      ("follows", doc! { "$or": [doc! { "follower_id": user_id }, doc! { "author_id": user_id }] }),
+      ("notifications", doc! { "$or": [doc! { "user_id": user_id }, doc! { "actor_id": user_id }] }),
+      ("webhooks", doc! { "_id": doc! { "$in": webhook_ids.clone() } }),
+      ("webhook_deliveries", doc! { "webhook_id": doc! { "$in": webhook_ids } })
+    ];
+
+    for (collection, filter) in deletes {
+      db.collection::<Document>(collection)
+        .delete_many_with_session(filter, None, session).await?;
+    }
+
+    db.collection::<Document>("posts")
+      .update_many_with_session(
+        doc! {
+          "user_id": user_id
+        },
+        doc! {
+          "$unset": doc! {
+            "user_id": ""
+          }
+        },
+        None,
+        session
+      ).await?;
+
+    super::Outbox::new().append_ws(
+      &models::Event::UserDeleted { user_id: *id },
+      session
+    ).await?;
+
+    Ok(())
+  }
+
+  async fn update_ws(
+    &self,
+    id: &models::UserId, set: Document,
+    session: &mut ClientSession
+  ) -> Result<(), Error> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .update_one_with_session(
+        doc! {
+          "_id": id.as_object_id()?
+        },
+        doc! {
+          "$set": set
+        },
+        None,
+        session
+      ).await?;
+
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub fn read(&self, doc: Document) -> Result<models::User, Error> {
+    Ok(document::decode_user(doc, self.decoding)?)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::models;
+  use crate::repository::asynchronous::{mongodb, Error};
+
+  #[tokio::test]
+  async fn test_user() -> Result<(), Error> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_1__".to_owned(),
+      last_name: "__test_1__".to_owned(),
+      email: Some("__test_1__@1.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = mongodb::User::new();
+    let session_repository = mongodb::Session::new();
+
+    let client = utils::connect().await?;
+    let mut session = client.start_session(None).await?;
+
+    session.start_transaction(None).await?;
+
+    user_repository.create_ws(&mut user, &mut session).await?;
+
+    assert!(!user.id.is_empty());
+    assert!(!user.settings.id.is_empty());
+
+    let user_id = user_repository.get_id_ws(
+      user.email.as_ref().unwrap(),
+      user.password.as_ref().unwrap(),
+      &mut session
+    ).await?;
+
+    assert_eq!(user_id, user.id);
+
+    let mut user_settings = user_repository.get_user_settings_ws(&user.id, &mut session).await?;
+
+    assert_eq!(user_settings.settings.id, user.settings.id);
+
+    user_settings.settings.posts_per_page = 30;
+    user_settings.settings.display_email = true;
+
+    user_repository.edit_ws(&user_settings.settings, &mut session).await?;
+
+    let user_settings_2 = user_repository.get_user_settings_ws(&user.id, &mut session).await?;
+
+    assert_eq!(user_settings_2.email, user.email);
+    assert_eq!(user_settings_2.settings.posts_per_page, 30);
+
+    let code = models::SessionCode::generate();
+
+    session_repository.create_ws(&user_id, &code, &mut session).await?;
+
+    let user_id = session_repository.get_user_id_ws(&code, &mut session).await?;
+
+    assert_eq!(user_id, user.id);
+
+    user_repository.edit_profile_ws(&user.id, "__test_1b__", "__test_1b__", &mut session).await?;
+    user_repository.change_email_ws(&user.id, "__test_1b__@1.again", &mut session).await?;
+    user_repository.change_password_ws(&user.id, "test", "test2", &mut session).await?;
+
+    // A new password ends the sessions
+    assert!(session_repository.get_user_id_ws(&code, &mut session).await.is_err());
+
+    assert_eq!(user_repository.get_id_ws("__test_1b__@1.again", "test2", &mut session).await?, user.id);
+
+    session_repository.create_ws(&user.id, &code, &mut session).await?;
+    session_repository.delete_ws(&code, &mut session).await?;
+
+    assert!(session_repository.get_user_id_ws(&code, &mut session).await.is_err());
+
+    user_repository.delete_ws(&user.id, &mut session).await?;
+
+    assert!(user_repository.get_user_settings_ws(&user.id, &mut session).await.is_err());
+
+    session.abort_transaction().await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,48 @@
+use std::env;
+
+use mongodb::{Client, ClientSession, options::ClientOptions};
+
+use crate::repository::mongodb::is_duplicate_key;
+use crate::utils::error::ConflictError;
+
+use super::super::Error;
+
+pub async fn connect() -> Result<Client, Error> {
+  let mut client_options = ClientOptions::parse_async(
+    env::var("MONGODB_CS")?
+  ).await?;
+
+  client_options.default_database = Some(
+    env::var("MONGODB_DB")?
+  );
+
+  Ok(
+    Client::with_options(client_options)?
+  )
+}
+
+// Commits when the call succeeded and aborts otherwise, as the sync
+// utils::commit_or_abort does
+pub async fn commit_or_abort<T>(session: &mut ClientSession, res: Result<T, Error>) -> Result<T, Error> {
+  match res {
+    Ok(value) => {
+      session.commit_transaction().await?;
+
+      Ok(value)
+    },
+    Err(err) => {
+      session.abort_transaction().await.ok();
+
+      Err(err)
+    }
+  }
+}
+
+// Turns a duplicate key error into a ConflictError, as the sync
+// utils::conflict does
+pub fn conflict(err: Error, message: &str) -> Error {
+  match err.downcast_ref::<mongodb::error::Error>() {
+    Some(db_err) if is_duplicate_key(db_err) => Box::new(ConflictError::new(message)),
+    _ => err
+  }
+}
@@ -0,0 +1,16 @@
+use async_trait::async_trait;
+
+use crate::models;
+
+use super::Error;
+
+#[async_trait]
+pub trait Post: Send + Sync {
+  async fn create(&self, post: &models::Post) -> Result<models::PostId, Error>;
+
+  async fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Error>;
+
+  async fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Error>;
+
+  async fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Error>;
+}
@@ -0,0 +1,115 @@
+use async_trait::async_trait;
+
+use tokio_postgres::Transaction;
+
+use crate::repository::asynchronous::{self, Error};
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError};
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Like {}
+
+#[async_trait]
+impl asynchronous::Like for Like {
+  async fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.create_wt(user_id, post_id, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.delete_wt(user_id, post_id, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+}
+
+impl Like {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_wt(
+    &self,
+    user_id: &models::UserId, post_id: &models::PostId,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    // Looked up first, so a missing post fails before anything is written
+    let author_id = super::Post::new().get_wt(post_id, None, transaction).await?
+      .author
+      .map(|author| author.id)
+      .filter(|author_id| author_id != user_id);
+
+    let liked: bool = transaction.query_one(
+      "select exists (select from likes where user_id = $1 and post_id = $2);",
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    ).await?
+    .get(0);
+
+    if liked {
+      return Err(Box::new(ConflictError::new("Post is already liked by this user")));
+    }
+
+    transaction.execute(
+      "
+        insert into likes(user_id, post_id)
+        values ($1, $2);
+      ",
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    ).await
+    .map_err(|err| utils::conflict(err.into(), "Post is already liked by this user"))?;
+
+    if let Some(author_id) = author_id {
+      super::Notification::new().create_wt(
+        &models::Notification::post_liked(author_id, *user_id, *post_id),
+        transaction
+      ).await?;
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostLiked { user_id: *user_id, post_id: *post_id },
+      transaction
+    ).await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_wt(
+    &self,
+    user_id: &models::UserId, post_id: &models::PostId,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    let res = transaction.execute(
+      "
+        delete from
+          likes
+        where
+          user_id = $1
+          and post_id = $2;
+      ",
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    ).await?;
+
+    if res == 0 {
+      return Err(Box::new(NotFoundError::new("Post is not liked by this user")));
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostUnliked { user_id: *user_id, post_id: *post_id },
+      transaction
+    ).await?;
+
+    Ok(())
+  }
+}
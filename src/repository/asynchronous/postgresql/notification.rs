@@ -0,0 +1,36 @@
+use tokio_postgres::Transaction;
+
+use crate::repository::asynchronous::Error;
+use crate::models;
+
+// Listing and marking read are left to the sync repository::Notification
+#[derive(Default)]
+pub struct Notification {}
+
+impl Notification {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_wt(
+    &self,
+    notification: &models::Notification,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::NotificationId, Error> {
+    let row = transaction.query_one(
+      "
+        insert into notifications(user_id, kind, actor_id, post_id)
+        values ($1, $2, $3, $4)
+        returning id;
+      ",
+      &[
+        &notification.user_id.as_i32()?, &notification.kind.as_str(),
+        &notification.actor_id.as_i32()?, &notification.post_id.as_i32()?
+      ]
+    ).await?;
+
+    let notification_id: i32 = row.get(0);
+
+    Ok(models::NotificationId::from(notification_id))
+  }
+}
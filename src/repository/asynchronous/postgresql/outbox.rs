@@ -0,0 +1,37 @@
+use tokio_postgres::Transaction;
+
+use crate::repository::asynchronous::Error;
+use crate::models;
+
+// Polling and acknowledging are left to the sync repository::Outbox,
+// async writes only append to the same table
+#[derive(Default)]
+pub struct Outbox {}
+
+impl Outbox {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn append_wt(
+    &self,
+    event: &models::Event,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::EventId, Error> {
+    let user_id = event.user_id().map(|user_id| user_id.as_i32()).transpose()?;
+    let post_id = event.post_id().map(|post_id| post_id.as_i32()).transpose()?;
+
+    let row = transaction.query_one(
+      "
+        insert into outbox(kind, user_id, post_id)
+        values ($1, $2, $3)
+        returning id;
+      ",
+      &[&event.kind(), &user_id, &post_id]
+    ).await?;
+
+    let event_id: i32 = row.get(0);
+
+    Ok(models::EventId::from(event_id))
+  }
+}
@@ -0,0 +1,252 @@
+use async_trait::async_trait;
+
+use tokio_postgres::{Row, Transaction};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Post {}
+
+#[async_trait]
+impl asynchronous::Post for Post {
+  async fn create(&self, post: &models::Post) -> Result<models::PostId, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.create_wt(post, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.get_wt(id, user_id, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.list_wt(user_id, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.liked_list_wt(user_id, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+}
+
+impl Post {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_wt(
+    &self,
+    post: &models::Post,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::PostId, Error> {
+    let mut user_id = None;
+
+    if let Some(author) = post.author.as_ref() {
+      user_id = Some(author.id.as_i32()?);
+    }
+
+    let row = transaction.query_one(
+      "
+        insert into posts(user_id, title, text, abstract)
+        values ($1, $2, $3, $4)
+        returning id;
+      ",
+      &[&user_id, &post.title, &post.text, &post.description]
+    ).await?;
+
+    let post_id = models::PostId::from(row.get::<_, i32>(0));
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostPublished {
+        post_id,
+        user_id: post.author.as_ref().map(|author| author.id)
+      },
+      transaction
+    ).await?;
+
+    Ok(post_id)
+  }
+
+  pub async fn get_wt(
+    &self,
+    id: &models::PostId, user_id: Option<&models::UserId>,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::Post, Error> {
+    let user_id = user_id.map(|user_id| user_id.as_i32()).transpose()?;
+
+    let row = transaction.query_opt(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email is null or s.display_email = false then null
+            else u.email
+          end email,
+          p.id post_id, p.title, p.text, p.abstract,
+          case
+            when l.id is null then false
+            else true
+          end liked
+        from
+          posts p
+        left join
+          users u
+          on p.user_id = u.id
+        left join
+          settings s
+          on p.user_id = s.user_id
+        left join
+          likes l
+          on p.id = l.post_id
+            and l.user_id = $2
+        where
+          p.id = $1;
+      ",
+      &[&id.as_i32()?, &user_id]
+    ).await?
+    .ok_or(NotFoundError::new("Post with this id not found"))?;
+
+    Ok(self.read(&row))
+  }
+
+  pub async fn list_wt(
+    &self,
+    user_id: Option<&models::UserId>,
+    transaction: &mut Transaction<'_>
+  ) -> Result<Vec<models::Post>, Error> {
+    let user_id = user_id.map(|user_id| user_id.as_i32()).transpose()?;
+
+    let rows = transaction.query(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email is null or s.display_email = false then null
+            else u.email
+          end email,
+          p.id post_id, p.title, p.text, p.abstract,
+          case
+            when l.id is null then false
+            else true
+          end liked
+        from
+          posts p
+        left join
+          users u
+          on p.user_id = u.id
+        left join
+          settings s
+          on p.user_id = s.user_id
+        left join
+          likes l
+          on p.id = l.post_id
+            and l.user_id = $1;
+      ",
+      &[&user_id]
+    ).await?;
+
+    Ok(
+      rows.iter()
+        .map(|row| self.read(row))
+        .collect()
+    )
+  }
+
+  pub async fn liked_list_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut Transaction<'_>
+  ) -> Result<Vec<models::Post>, Error> {
+    let rows = transaction.query(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email is null or s.display_email = false then null
+            else u.email
+          end email,
+          p.id post_id, p.title, p.text, p.abstract,
+          case
+            when l.id is null then false
+            else true
+          end liked
+        from
+          posts p
+        left join
+          users u
+          on p.user_id = u.id
+        left join
+          settings s
+          on p.user_id = s.user_id
+        inner join
+          likes l
+          on p.id = l.post_id
+            and l.user_id = $1;
+      ",
+      &[&user_id.as_i32()?]
+    ).await?;
+
+    Ok(
+      rows.iter()
+        .map(|row| self.read(row))
+        .collect()
+    )
+  }
+
+  pub fn read(&self, row: &Row) -> models::Post {
+    let user_id: Option<i32> = row.get("user_id");
+    let post_id: i32 = row.get("post_id");
+
+    let post_id = models::PostId::from(post_id);
+
+    models::Post {
+      id: post_id,
+      title: row.get("title"),
+      text: row.get("text"),
+      description: row.get("abstract"),
+      liked: row.get("liked"),
+
+      author: user_id.map(|user_id| models::User {
+        id: models::UserId::from(user_id),
+        first_name: row.get("first_name"),
+        last_name: row.get("last_name"),
+        email: row.get("email"),
+        password: None,
+
+        settings: models::Settings::new()
+      })
+    }
+  }
+}
@@ -0,0 +1,103 @@
+use async_trait::async_trait;
+
+use tokio_postgres::Transaction;
+
+use crate::repository::asynchronous::{self, Error};
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Session {}
+
+#[async_trait]
+impl asynchronous::Session for Session {
+  async fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.get_user_id_wt(code, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.create_wt(user_id, code, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn delete(&self, code: &models::SessionCode) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.delete_wt(code, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+}
+
+impl Session {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn get_user_id_wt(
+    &self,
+    code: &models::SessionCode,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::UserId, Error> {
+    let row = transaction.query_opt(
+      "select user_id from sessions where code = $1;",
+      &[&code.as_str()]
+    ).await?
+    .ok_or(NotFoundError::new("User with this session code doesn't exist"))?;
+
+    let user_id: i32 = row.get("user_id");
+
+    Ok(models::UserId::from(user_id))
+  }
+
+  pub async fn create_wt(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    transaction.execute(
+      "
+        insert into sessions(user_id, code)
+        values ($1, $2);
+      ",
+      &[&user_id.as_i32()?, &code.as_str()]
+    ).await?;
+
+    Ok(())
+  }
+
+  pub async fn delete_wt(
+    &self,
+    code: &models::SessionCode,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    transaction.execute(
+      "delete from sessions where code = $1;",
+      &[&code.as_str()]
+    ).await?;
+
+    Ok(())
+  }
+}
@@ -0,0 +1,511 @@
+use async_trait::async_trait;
+
+use tokio_postgres::{Row, Transaction};
+
+use crate::repository::asynchronous::{self, Error};
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError, ValidationError};
+
+use super::utils;
+
+#[derive(Default)]
+pub struct User {}
+
+#[async_trait]
+impl asynchronous::User for User {
+  async fn create(&self, user: &mut models::User) -> Result<models::UserId, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.create_wt(user, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.get_id_wt(email, password, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.get_user_settings_wt(id, &mut transaction).await;
+
+    transaction.commit().await?;
+
+    res
+  }
+
+  async fn edit(&self, settings: &models::Settings) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.edit_wt(settings, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.edit_profile_wt(id, first_name, last_name, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.change_email_wt(id, email, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.change_password_wt(id, old_password, new_password, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+
+  async fn delete(&self, id: &models::UserId) -> Result<(), Error> {
+    let mut connection = utils::connect().await?;
+
+    let mut transaction = connection.transaction().await?;
+
+    let res = self.delete_wt(id, &mut transaction).await;
+
+    utils::commit_or_rollback(transaction, res).await
+  }
+}
+
+impl User {
+  pub fn new() -> Self {
+    Self {}
+  }
+
+  pub async fn create_wt(
+    &self,
+    user: &mut models::User,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::UserId, Error> {
+    let user_id = self.create_user_wt(user, transaction).await?;
+
+    user.id = user_id;
+    user.settings.user_id = user_id;
+
+    let settings_id = self.create_settings_wt(&user.settings, transaction).await?;
+
+    user.settings.id = settings_id;
+
+    super::Outbox::new().append_wt(
+      &models::Event::UserRegistered { user_id },
+      transaction
+    ).await?;
+
+    Ok(user_id)
+  }
+
+  pub async fn create_user_wt(
+    &self,
+    user: &models::User,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::UserId, Error> {
+    if let Some(password) = user.password.as_ref() {
+      if let Some(email) = user.email.as_deref() {
+        self.check_email_wt(email, None, transaction).await?;
+      }
+
+      let password = format!("{:x}", md5::compute(password));
+
+      let row = transaction.query_one(
+        "
+          insert into users(first_name, last_name, email, password)
+          values ($1, $2, $3, $4)
+          returning id;
+        ",
+        &[&user.first_name, &user.last_name, &user.email, &password]
+      ).await
+      .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
+
+      let user_id: i32 = row.get(0);
+
+      Ok(models::UserId::from(user_id))
+    } else {
+      Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
+        )
+      )
+    }
+  }
+
+  pub async fn create_settings_wt(
+    &self,
+    settings: &models::Settings,
+    transaction: &mut Transaction<'_>
+  ) -> Result<String, Error> {
+    let row = transaction.query_one(
+      "
+        insert into settings(user_id, posts_per_page, display_email)
+        values ($1, $2, $3)
+        returning id;
+      ",
+      &[&settings.user_id.as_i32()?, &settings.posts_per_page, &settings.display_email]
+    ).await?;
+
+    let settings_id: i32 = row.get(0);
+
+    Ok(settings_id.to_string())
+  }
+
+  // The users_email_lower index enforces this too, and the writes turn its
+  // violation into the same ConflictError when a concurrent one got there first
+  pub async fn check_email_wt(
+    &self,
+    email: &str, except: Option<&models::UserId>,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    let rows = transaction.query(
+      "select id from users where lower(email) = lower($1);",
+      &[&email]
+    ).await?;
+
+    let taken = rows.iter()
+      .map(|row| models::UserId::from(row.get::<_, i32>("id")))
+      .any(|user_id| Some(&user_id) != except);
+
+    if taken {
+      return Err(
+        Box::new(
+          ConflictError::new("User with this email already exists")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub async fn get_id_wt(
+    &self,
+    email: &str, password: &str,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::UserId, Error> {
+    let password = format!("{:x}", md5::compute(password));
+
+    let row = transaction.query_opt(
+      "select id from users where lower(email) = lower($1) and password = $2;",
+      &[&email, &password]
+    ).await?
+    .ok_or(NotFoundError::new("User with this email and password doesn't exist"))?;
+
+    let user_id: i32 = row.get("id");
+
+    Ok(models::UserId::from(user_id))
+  }
+
+  pub async fn get_user_settings_wt(
+    &self,
+    id: &models::UserId,
+    transaction: &mut Transaction<'_>
+  ) -> Result<models::User, Error> {
+    let row = transaction.query_opt(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email = false then null
+            else u.email
+          end email,
+          s.id settings_id, s.posts_per_page, s.display_email
+        from
+          users u, settings s
+        where
+          s.user_id = u.id
+          and u.id = $1;
+      ",
+      &[&id.as_i32()?]
+    ).await?
+    .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
+
+    Ok(self.read(&row))
+  }
+
+  pub async fn edit_wt(
+    &self,
+    settings: &models::Settings,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    let res = transaction.execute(
+      "
+        update
+          settings
+        set
+          posts_per_page = $1,
+          display_email = $2
+        where
+          user_id = $3;
+      ",
+      &[&settings.posts_per_page, &settings.display_email, &settings.user_id.as_i32()?]
+    ).await?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::SettingsChanged { user_id: settings.user_id },
+      transaction
+    ).await?;
+
+    Ok(())
+  }
+
+  pub async fn edit_profile_wt(
+    &self,
+    id: &models::UserId, first_name: &str, last_name: &str,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    let res = transaction.execute(
+      "update users set first_name = $1, last_name = $2 where id = $3;",
+      &[&first_name, &last_name, &id.as_i32()?]
+    ).await?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub async fn change_email_wt(
+    &self,
+    id: &models::UserId, email: &str,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    if email.trim().is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Email should be non-empty")
+        )
+      );
+    }
+
+    self.check_email_wt(email, Some(id), transaction).await?;
+
+    let res = transaction.execute(
+      "update users set email = $1 where id = $2;",
+      &[&email, &id.as_i32()?]
+    ).await
+    .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub async fn change_password_wt(
+    &self,
+    id: &models::UserId, old_password: &str, new_password: &str,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    if new_password.is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
+        )
+      );
+    }
+
+    let id = id.as_i32()?;
+    let old_password = format!("{:x}", md5::compute(old_password));
+    let new_password = format!("{:x}", md5::compute(new_password));
+
+    let res = transaction.execute(
+      "update users set password = $1 where id = $2 and password = $3;",
+      &[&new_password, &id, &old_password]
+    ).await?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id and password doesn't exist")
+        )
+      );
+    }
+
+    transaction.execute("delete from sessions where user_id = $1;", &[&id]).await?;
+
+    Ok(())
+  }
+
+  // Follows, webhooks and notifications go with the user by cascade
+  pub async fn delete_wt(
+    &self,
+    id: &models::UserId,
+    transaction: &mut Transaction<'_>
+  ) -> Result<(), Error> {
+    let user_id = id.as_i32()?;
+
+    transaction.query_opt("select id from users where id = $1 for update;", &[&user_id]).await?
+      .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
+
+    transaction.execute("delete from sessions where user_id = $1;", &[&user_id]).await?;
+    transaction.execute("delete from likes where user_id = $1;", &[&user_id]).await?;
+    transaction.execute("delete from settings where user_id = $1;", &[&user_id]).await?;
+    transaction.execute("update posts set user_id = null where user_id = $1;", &[&user_id]).await?;
+    transaction.execute("delete from users where id = $1;", &[&user_id]).await?;
+
+    super::Outbox::new().append_wt(
+      &models::Event::UserDeleted { user_id: *id },
+      transaction
+    ).await?;
+
+    Ok(())
+  }
+
+  pub fn read(&self, row: &Row) -> models::User {
+    let user_id: i32 = row.get("user_id");
+    let settings_id: i32 = row.get("settings_id");
+
+    let user_id = models::UserId::from(user_id);
+    let settings_id = settings_id.to_string();
+
+    models::User {
+      id: user_id,
+      first_name: row.get("first_name"),
+      last_name: row.get("last_name"),
+      email: row.get("email"),
+      password: None,
+
+      settings: models::Settings {
+        id: settings_id,
+        user_id,
+        display_email: row.get("display_email"),
+        posts_per_page: row.get("posts_per_page")
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::models;
+  use crate::repository::asynchronous::{postgresql, Error};
+
+  #[tokio::test]
+  async fn test_user() -> Result<(), Error> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_1__".to_owned(),
+      last_name: "__test_1__".to_owned(),
+      email: Some("__test_1__@1.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = postgresql::User::new();
+    let session_repository = postgresql::Session::new();
+
+    let mut connection = utils::connect().await?;
+    let mut transaction = connection.transaction().await?;
+
+    user_repository.create_wt(&mut user, &mut transaction).await?;
+
+    assert!(!user.id.is_empty());
+    assert!(!user.settings.id.is_empty());
+
+    let user_id = user_repository.get_id_wt(
+      user.email.as_ref().unwrap(),
+      user.password.as_ref().unwrap(),
+      &mut transaction
+    ).await?;
+
+    assert_eq!(user_id, user.id);
+
+    let mut user_settings = user_repository.get_user_settings_wt(&user.id, &mut transaction).await?;
+
+    assert_eq!(user_settings.settings.id, user.settings.id);
+    assert_eq!(user_settings.settings.posts_per_page, user.settings.posts_per_page);
+
+    user_settings.settings.posts_per_page = 30;
+    user_settings.settings.display_email = true;
+
+    user_repository.edit_wt(&user_settings.settings, &mut transaction).await?;
+
+    let user_settings_2 = user_repository.get_user_settings_wt(&user.id, &mut transaction).await?;
+
+    assert_eq!(user_settings_2.email, user.email);
+    assert_eq!(user_settings_2.settings.posts_per_page, 30);
+
+    let code = models::SessionCode::generate();
+
+    session_repository.create_wt(&user_id, &code, &mut transaction).await?;
+
+    let user_id = session_repository.get_user_id_wt(&code, &mut transaction).await?;
+
+    assert_eq!(user_id, user.id);
+
+    user_repository.edit_profile_wt(&user.id, "__test_1b__", "__test_1b__", &mut transaction).await?;
+    user_repository.change_email_wt(&user.id, "__test_1b__@1.again", &mut transaction).await?;
+    user_repository.change_password_wt(&user.id, "test", "test2", &mut transaction).await?;
+
+    // A new password ends the sessions
+    assert!(session_repository.get_user_id_wt(&code, &mut transaction).await.is_err());
+
+    assert_eq!(user_repository.get_id_wt("__test_1b__@1.again", "test2", &mut transaction).await?, user.id);
+
+    session_repository.create_wt(&user.id, &code, &mut transaction).await?;
+    session_repository.delete_wt(&code, &mut transaction).await?;
+
+    assert!(session_repository.get_user_id_wt(&code, &mut transaction).await.is_err());
+
+    user_repository.delete_wt(&user.id, &mut transaction).await?;
+
+    assert!(user_repository.get_user_settings_wt(&user.id, &mut transaction).await.is_err());
+
+    transaction.rollback().await?;
+
+    Ok(())
+  }
+}
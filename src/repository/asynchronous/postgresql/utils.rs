@@ -0,0 +1,53 @@
+use tokio_postgres::{error::SqlState, Client, Config, Transaction};
+
+use std::env;
+
+use crate::utils::{error::ConflictError, tls};
+
+use super::super::Error;
+
+// The connection drives the socket and has to be polled on its own task
+pub async fn connect() -> Result<Client, Error> {
+  let mut config = Config::new();
+
+  config.host(&env::var("POSTGRES_HOST")?);
+  config.user(&env::var("POSTGRES_USER")?);
+  config.password(&env::var("POSTGRES_PASSWORD")?);
+  config.dbname(&env::var("POSTGRES_DB")?);
+
+  let (client, connection) = config.connect(tls::connector()?).await?;
+
+  tokio::spawn(async move {
+    if let Err(err) = connection.await {
+      eprintln!("Postgres connection error: {}", err);
+    }
+  });
+
+  Ok(client)
+}
+
+// Commits when the call succeeded and rolls back otherwise, as the
+// sync utils::commit_or_rollback does
+pub async fn commit_or_rollback<T>(transaction: Transaction<'_>, res: Result<T, Error>) -> Result<T, Error> {
+  match res {
+    Ok(value) => {
+      transaction.commit().await?;
+
+      Ok(value)
+    },
+    Err(err) => {
+      transaction.rollback().await.ok();
+
+      Err(err)
+    }
+  }
+}
+
+// Turns a unique violation into a ConflictError, as the sync
+// utils::conflict does
+pub fn conflict(err: Error, message: &str) -> Error {
+  match err.downcast_ref::<tokio_postgres::Error>() {
+    Some(db_err) if db_err.code() == Some(&SqlState::UNIQUE_VIOLATION) => Box::new(ConflictError::new(message)),
+    _ => err
+  }
+}
@@ -0,0 +1,22 @@
+use async_trait::async_trait;
+
+use crate::models;
+
+use super::Error;
+
+#[async_trait]
+pub trait Session: Send + Sync {
+  async fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Error>;
+
+  async fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Error>;
+
+  async fn create_with_metadata(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    _metadata: &models::SessionMetadata
+  ) -> Result<(), Error> {
+    self.create(user_id, code).await
+  }
+
+  async fn delete(&self, code: &models::SessionCode) -> Result<(), Error>;
+}
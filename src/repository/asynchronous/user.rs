@@ -0,0 +1,24 @@
+use async_trait::async_trait;
+
+use crate::models;
+
+use super::Error;
+
+#[async_trait]
+pub trait User: Send + Sync {
+  async fn create(&self, user: &mut models::User) -> Result<models::UserId, Error>;
+
+  async fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Error>;
+
+  async fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Error>;
+
+  async fn edit(&self, settings: &models::Settings) -> Result<(), Error>;
+
+  async fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Error>;
+
+  async fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Error>;
+
+  async fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Error>;
+
+  async fn delete(&self, id: &models::UserId) -> Result<(), Error>;
+}
@@ -0,0 +1,36 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Cache;
+
+// Only invalidates, so that cached posts show the reader's likes
+pub struct Like<L> {
+  inner: L,
+  cache: Cache
+}
+
+impl<L: repository::Like> repository::Like for Like<L> {
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.create(user_id, post_id);
+
+    self.cache.invalidate_like(user_id, post_id);
+
+    res
+  }
+
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.delete(user_id, post_id);
+
+    self.cache.invalidate_like(user_id, post_id);
+
+    res
+  }
+}
+
+impl<L: repository::Like> Like<L> {
+  pub fn new(inner: L, cache: Cache) -> Self {
+    Self { inner, cache }
+  }
+}
@@ -0,0 +1,133 @@
+use std::{collections::{BTreeMap, HashMap}, hash::Hash, time::{Duration, Instant}};
+
+struct Entry<V> {
+  value: V,
+  inserted: Instant,
+  used: u64
+}
+
+// Least recently used entries are evicted first, entries older
+// than the ttl are treated as missing
+pub struct Lru<K, V> {
+  capacity: usize,
+  ttl: Duration,
+  entries: HashMap<K, Entry<V>>,
+  order: BTreeMap<u64, K>,
+  tick: u64
+}
+
+impl<K: Eq + Hash + Clone, V> Lru<K, V> {
+  pub fn new(capacity: usize, ttl: Duration) -> Self {
+    Self {
+      capacity,
+      ttl,
+      entries: HashMap::new(),
+      order: BTreeMap::new(),
+      tick: 0
+    }
+  }
+
+  pub fn get(&mut self, key: &K) -> Option<&V> {
+    let expired = self.entries.get(key)?.inserted.elapsed() >= self.ttl;
+
+    if expired {
+      self.remove(key);
+
+      return None;
+    }
+
+    self.tick += 1;
+
+    let entry = self.entries.get_mut(key)?;
+
+    self.order.remove(&entry.used);
+    self.order.insert(self.tick, key.clone());
+
+    entry.used = self.tick;
+
+    Some(&entry.value)
+  }
+
+  pub fn insert(&mut self, key: K, value: V) {
+    if self.capacity == 0 {
+      return;
+    }
+
+    self.remove(&key);
+
+    while self.entries.len() >= self.capacity {
+      match self.order.pop_first() {
+        Some((_, oldest)) => self.entries.remove(&oldest),
+        None => break
+      };
+    }
+
+    self.tick += 1;
+
+    self.order.insert(self.tick, key.clone());
+    self.entries.insert(
+      key,
+      Entry {
+        value,
+        inserted: Instant::now(),
+        used: self.tick
+      }
+    );
+  }
+
+  pub fn remove(&mut self, key: &K) {
+    if let Some(entry) = self.entries.remove(key) {
+      self.order.remove(&entry.used);
+    }
+  }
+
+  pub fn retain<F: Fn(&K, &V) -> bool>(&mut self, keep: F) {
+    let order = &mut self.order;
+
+    self.entries.retain(|key, entry| {
+      let keep = keep(key, &entry.value);
+
+      if !keep {
+        order.remove(&entry.used);
+      }
+
+      keep
+    });
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::time::Duration;
+
+  use super::Lru;
+
+  #[test]
+  fn test_lru() {
+    let mut lru = Lru::new(2, Duration::from_secs(60));
+
+    lru.insert(1, "a");
+    lru.insert(2, "b");
+
+    assert_eq!(lru.get(&1), Some(&"a"));
+
+    // 2 is the least recently used now
+    lru.insert(3, "c");
+
+    assert_eq!(lru.get(&2), None);
+    assert_eq!(lru.get(&1), Some(&"a"));
+    assert_eq!(lru.entries.len(), 2);
+
+    lru.retain(|key, _| *key != 1);
+
+    assert_eq!(lru.get(&1), None);
+    assert_eq!(lru.get(&3), Some(&"c"));
+
+    let mut lru = Lru::new(2, Duration::ZERO);
+
+    lru.insert(1, "a");
+
+    assert_eq!(lru.get(&1), None);
+    assert_eq!(lru.entries.len(), 0);
+  }
+}
@@ -0,0 +1,79 @@
+use std::{sync::{Arc, Mutex, MutexGuard}, time::Duration};
+
+use crate::models;
+
+mod lru;
+mod user;
+mod post;
+mod like;
+mod session;
+
+pub use user::User;
+pub use post::Post;
+pub use like::Like;
+pub use session::Session;
+
+use lru::Lru;
+
+// Posts are kept per reader, since `liked` depends on who reads.
+// Sessions usually want a shorter ttl than the rest: a session revoked
+// in another process stays valid here until its entry expires
+#[derive(Clone)]
+pub struct Cache(Arc<Entries>);
+
+struct Entries {
+  posts: Mutex<Lru<(models::PostId, Option<models::UserId>), models::Post>>,
+  users: Mutex<Lru<models::UserId, models::User>>,
+  sessions: Mutex<Lru<models::SessionCode, Option<models::UserId>>>
+}
+
+impl Cache {
+  pub fn new(capacity: usize, ttl: Duration) -> Self {
+    Self::with_session_ttl(capacity, ttl, ttl)
+  }
+
+  pub fn with_session_ttl(capacity: usize, ttl: Duration, session_ttl: Duration) -> Self {
+    Self(
+      Arc::new(
+        Entries {
+          posts: Mutex::new(Lru::new(capacity, ttl)),
+          users: Mutex::new(Lru::new(capacity, ttl)),
+          sessions: Mutex::new(Lru::new(capacity, session_ttl))
+        }
+      )
+    )
+  }
+
+  // For changes made without the decorators, like deleting a user
+  pub fn invalidate_user(&self, id: &models::UserId) {
+    self.users().remove(id);
+    self.sessions().retain(|_, user_id| *user_id != Some(*id));
+    self.posts().retain(|_, post| post.author.as_ref().is_none_or(|author| author.id != *id));
+  }
+
+  fn posts(&self) -> MutexGuard<'_, Lru<(models::PostId, Option<models::UserId>), models::Post>> {
+    self.0.posts.lock().unwrap_or_else(|err| err.into_inner())
+  }
+
+  fn users(&self) -> MutexGuard<'_, Lru<models::UserId, models::User>> {
+    self.0.users.lock().unwrap_or_else(|err| err.into_inner())
+  }
+
+  fn sessions(&self) -> MutexGuard<'_, Lru<models::SessionCode, Option<models::UserId>>> {
+    self.0.sessions.lock().unwrap_or_else(|err| err.into_inner())
+  }
+
+  fn invalidate_post(&self, id: &models::PostId) {
+    self.posts().retain(|(post_id, _), _| post_id != id);
+  }
+
+  fn invalidate_like(&self, user_id: &models::UserId, post_id: &models::PostId) {
+    self.posts().remove(&(*post_id, Some(*user_id)));
+  }
+
+  // Posts carry their author's email, which the settings may hide
+  fn invalidate_settings(&self, id: &models::UserId) {
+    self.users().remove(id);
+    self.posts().retain(|_, post| post.author.as_ref().is_none_or(|author| author.id != *id));
+  }
+}
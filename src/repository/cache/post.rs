@@ -0,0 +1,116 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Cache;
+
+pub struct Post<P> {
+  inner: P,
+  cache: Cache
+}
+
+impl<P: repository::Post> repository::Post for Post<P> {
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+    let post_id = self.inner.create(post)?;
+
+    self.cache.invalidate_post(&post_id);
+
+    Ok(post_id)
+  }
+
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+    let key = (*id, user_id.copied());
+
+    if let Some(post) = self.cache.posts().get(&key) {
+      return Ok(post.clone());
+    }
+
+    let post = self.inner.get(id, user_id)?;
+
+    self.cache.posts().insert(key, post.clone());
+
+    Ok(post)
+  }
+
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    self.inner.list(user_id)
+  }
+
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    self.inner.liked_list(user_id)
+  }
+
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    self.inner.feed(user_id, page)
+  }
+}
+
+impl<P: repository::Post> Post<P> {
+  pub fn new(inner: P, cache: Cache) -> Self {
+    Self { inner, cache }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, time::Duration};
+
+  use crate::{models, repository::{self, User, Post, Like}};
+
+  use super::Cache;
+
+  #[test]
+  fn test_post() -> Result<(), Box<dyn error::Error>> {
+    let store = repository::memory::Store::new();
+    let cache = Cache::new(100, Duration::from_secs(60));
+
+    let user_repository = repository::cache::User::new(repository::memory::User::with_store(store.clone()), cache.clone());
+    let post_repository = repository::cache::Post::new(repository::memory::Post::with_store(store.clone()), cache.clone());
+    let like_repository = repository::cache::Like::new(repository::memory::Like::with_store(store.clone()), cache);
+
+    // Writes behind the cache's back show which reads were cached
+    let uncached_like_repository = repository::memory::Like::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+    user.email = Some("first@last.test".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    let post_id = post_repository.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(user)
+      }
+    )?;
+
+    assert!(!post_repository.get(&post_id, Some(&user_id))?.liked);
+
+    uncached_like_repository.create(&user_id, &post_id)?;
+
+    assert!(!post_repository.get(&post_id, Some(&user_id))?.liked);
+    assert!(!post_repository.get(&post_id, None)?.liked);
+
+    like_repository.delete(&user_id, &post_id)?;
+    like_repository.create(&user_id, &post_id)?;
+
+    assert!(post_repository.get(&post_id, Some(&user_id))?.liked);
+    assert!(!post_repository.get(&post_id, None)?.liked);
+
+    let mut settings = user_repository.get_user_settings(&user_id)?.settings;
+
+    settings.display_email = true;
+
+    user_repository.edit(&settings)?;
+
+    assert!(post_repository.get(&post_id, None)?.author.unwrap().email.is_some());
+
+    Ok(())
+  }
+}
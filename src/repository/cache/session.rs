@@ -0,0 +1,130 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::Cache;
+
+// Unknown codes are cached too, so guessing codes doesn't reach the database
+pub struct Session<S> {
+  inner: S,
+  cache: Cache
+}
+
+impl<S: repository::Session> repository::Session for Session<S> {
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+    if let Some(user_id) = self.cache.sessions().get(code) {
+      return user_id.ok_or(
+        Box::new(
+          NotFoundError::new("User with this session code doesn't exist")
+        )
+      );
+    }
+
+    match self.inner.get_user_id(code) {
+      Ok(user_id) => {
+        self.cache.sessions().insert(code.clone(), Some(user_id));
+
+        Ok(user_id)
+      },
+      Err(err) => {
+        if err.is::<NotFoundError>() {
+          self.cache.sessions().insert(code.clone(), None);
+        }
+
+        Err(err)
+      }
+    }
+  }
+
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.create(user_id, code);
+
+    self.cache.sessions().remove(code);
+
+    res
+  }
+
+  fn create_with_metadata(
+    &self, 
+    user_id: &models::UserId, code: &models::SessionCode, 
+    metadata: &models::SessionMetadata
+  ) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.create_with_metadata(user_id, code, metadata);
+
+    self.cache.sessions().remove(code);
+
+    res
+  }
+
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.delete(code);
+
+    self.cache.sessions().remove(code);
+
+    res
+  }
+}
+
+impl<S: repository::Session> Session<S> {
+  pub fn new(inner: S, cache: Cache) -> Self {
+    Self { inner, cache }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, time::Duration};
+
+  use crate::{models, repository::{self, User, Session}};
+  use crate::utils::error::NotFoundError;
+
+  use super::Cache;
+
+  #[test]
+  fn test_session() -> Result<(), Box<dyn error::Error>> {
+    let store = repository::memory::Store::new();
+    let cache = Cache::new(100, Duration::from_secs(60));
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let session_repository = repository::cache::Session::new(repository::memory::Session::with_store(store.clone()), cache.clone());
+    let uncached_session_repository = repository::memory::Session::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+    let code = models::SessionCode::new("code");
+
+    // The miss is cached until the session is created through the cache
+    assert!(session_repository.get_user_id(&code).unwrap_err().is::<NotFoundError>());
+
+    uncached_session_repository.create(&user_id, &code)?;
+
+    assert!(session_repository.get_user_id(&code).is_err());
+
+    session_repository.delete(&code)?;
+    session_repository.create(&user_id, &code)?;
+
+    assert_eq!(session_repository.get_user_id(&code)?, user_id);
+
+    uncached_session_repository.delete(&code)?;
+
+    assert_eq!(session_repository.get_user_id(&code)?, user_id);
+
+    cache.invalidate_user(&user_id);
+
+    assert!(session_repository.get_user_id(&code).is_err());
+
+    session_repository.create(&user_id, &code)?;
+    session_repository.get_user_id(&code)?;
+    session_repository.delete(&code)?;
+
+    assert!(session_repository.get_user_id(&code).is_err());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,151 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Cache;
+
+pub struct User<U> {
+  inner: U,
+  cache: Cache
+}
+
+impl<U: repository::User> repository::User for User<U> {
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+    self.inner.create(user)
+  }
+
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+    self.inner.get_id(email, password)
+  }
+
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+    if let Some(user) = self.cache.users().get(id) {
+      return Ok(user.clone());
+    }
+
+    let user = self.inner.get_user_settings(id)?;
+
+    self.cache.users().insert(*id, user.clone());
+
+    Ok(user)
+  }
+
+  fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.edit(settings);
+
+    self.cache.invalidate_settings(&settings.user_id);
+
+    res
+  }
+
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.edit_profile(id, first_name, last_name);
+
+    self.cache.invalidate_settings(id);
+
+    res
+  }
+
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.change_email(id, email);
+
+    self.cache.invalidate_settings(id);
+
+    res
+  }
+
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.change_password(id, old_password, new_password);
+
+    self.cache.invalidate_user(id);
+
+    res
+  }
+
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let res = self.inner.delete(id);
+
+    self.cache.invalidate_user(id);
+
+    res
+  }
+
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    self.inner.export_personal_data(id)
+  }
+}
+
+impl<U: repository::User> User<U> {
+  pub fn new(inner: U, cache: Cache) -> Self {
+    Self { inner, cache }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, time::Duration};
+
+  use crate::{models, repository::{self, User}};
+
+  use super::Cache;
+
+  #[test]
+  fn test_user() -> Result<(), Box<dyn error::Error>> {
+    let store = repository::memory::Store::new();
+
+    let user_repository = repository::cache::User::new(
+      repository::memory::User::with_store(store.clone()),
+      Cache::new(100, Duration::from_secs(60))
+    );
+    let uncached_user_repository = repository::memory::User::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    let mut settings = user_repository.get_user_settings(&user_id)?.settings;
+
+    settings.posts_per_page = 5;
+
+    uncached_user_repository.edit(&settings)?;
+
+    assert_eq!(user_repository.get_user_settings(&user_id)?.settings.posts_per_page, 10);
+
+    settings.posts_per_page = 20;
+
+    user_repository.edit(&settings)?;
+
+    assert_eq!(user_repository.get_user_settings(&user_id)?.settings.posts_per_page, 20);
+
+    user_repository.edit_profile(&user_id, "renamed", "")?;
+
+    assert_eq!(user_repository.get_user_settings(&user_id)?.first_name, "renamed");
+
+    user_repository.delete(&user_id)?;
+
+    assert!(user_repository.get_user_settings(&user_id).is_err());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    settings.user_id = user_id;
+
+    // Expired entries are read again
+    let user_repository = repository::cache::User::new(
+      uncached_user_repository,
+      Cache::new(100, Duration::ZERO)
+    );
+
+    user_repository.get_user_settings(&user_id)?;
+
+    settings.posts_per_page = 30;
+
+    user_repository.edit(&settings)?;
+
+    assert_eq!(user_repository.get_user_settings(&user_id)?.settings.posts_per_page, 30);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,43 @@
+use std::error;
+
+use crate::models;
+
+/// Who follows whom, `Post::feed` reads the posts of followed authors
+pub trait Follow: Send + Sync {
+  /// The follower follows the author, following twice changes nothing
+  fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>>;
+
+  /// Stops following, if the follower did
+  fn unfollow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>>;
+
+  /// Users following the user, latest first
+  fn followers(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>>;
+
+  /// Users the user follows, latest first
+  fn following(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>>;
+
+  /// How many followers the user has and how many users they follow
+  fn counts(&self, user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>>;
+}
+
+impl<T: Follow + ?Sized> Follow for Box<T> {
+  fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    (**self).follow(follower_id, author_id)
+  }
+
+  fn unfollow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    (**self).unfollow(follower_id, author_id)
+  }
+
+  fn followers(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    (**self).followers(user_id)
+  }
+
+  fn following(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    (**self).following(user_id)
+  }
+
+  fn counts(&self, user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    (**self).counts(user_id)
+  }
+}
@@ -1,7 +1,23 @@
 use std::error;
 
-pub trait Like {
-  fn create(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>>;
+use crate::models;
 
-  fn delete(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>>;
+/// Likes of posts by users
+pub trait Like: Send + Sync {
+  /// Likes the post on behalf of the user
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>>;
+
+  /// Takes the like back, fails with `NotFoundError` when the post
+  /// isn't liked by the user
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>>;
+}
+
+impl<T: Like + ?Sized> Like for Box<T> {
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    (**self).create(user_id, post_id)
+  }
+
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    (**self).delete(user_id, post_id)
+  }
 }
@@ -0,0 +1,165 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::ValidationError;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Follow {
+  store: Store
+}
+
+impl repository::Follow for Follow {
+  fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    if follower_id == author_id {
+      return Err(Box::new(ValidationError::new("Users can't follow themselves")));
+    }
+
+    let mut data = self.store.lock();
+
+    data.user(follower_id)?;
+    data.user(author_id)?;
+
+    if !data.follows.contains(&(*follower_id, *author_id)) {
+      data.follows.push((*follower_id, *author_id));
+    }
+
+    Ok(())
+  }
+
+  fn unfollow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    self.store.lock().follows.retain(|follow| *follow != (*follower_id, *author_id));
+
+    Ok(())
+  }
+
+  fn followers(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().follows.iter()
+        .rev()
+        .filter(|(_, author_id)| author_id == user_id)
+        .map(|(follower_id, _)| *follower_id)
+        .collect()
+    )
+  }
+
+  fn following(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().follows.iter()
+        .rev()
+        .filter(|(follower_id, _)| follower_id == user_id)
+        .map(|(_, author_id)| *author_id)
+        .collect()
+    )
+  }
+
+  fn counts(&self, user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    Ok(
+      models::FollowCounts {
+        followers: data.follows.iter().filter(|(_, author_id)| author_id == user_id).count() as u64,
+        following: data.follows.iter().filter(|(follower_id, _)| follower_id == user_id).count() as u64
+      }
+    )
+  }
+}
+
+impl Follow {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use crate::{models, repository::{self, User, Post, Like, Follow}};
+
+  use super::Store;
+
+  #[test]
+  fn test_follow() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store.clone());
+    let follow_repository = repository::memory::Follow::with_store(store);
+
+    let mut users = Vec::new();
+
+    for _ in 0..3 {
+      let mut user = models::User::new();
+
+      user.password = Some("secret".to_owned());
+      user.settings.posts_per_page = 2;
+
+      user_repository.create(&mut user)?;
+
+      users.push(user);
+    }
+
+    let (reader_id, author_id, other_id) = (users[0].id, users[1].id, users[2].id);
+
+    let mut post_ids = Vec::new();
+
+    for (author, title) in [(&users[1], "1"), (&users[2], "2"), (&users[1], "3"), (&users[1], "4")] {
+      post_ids.push(
+        post_repository.create(
+          &models::Post {
+            id: models::PostId::new(),
+            title: title.to_owned(),
+            text: None,
+            description: None,
+            liked: false,
+            author: Some(author.clone())
+          }
+        )?
+      );
+    }
+
+    assert!(post_repository.feed(&reader_id, 0)?.is_empty());
+
+    follow_repository.follow(&reader_id, &author_id)?;
+    follow_repository.follow(&reader_id, &author_id)?;
+    follow_repository.follow(&other_id, &author_id)?;
+
+    assert!(follow_repository.follow(&reader_id, &reader_id).is_err());
+
+    assert_eq!(follow_repository.followers(&author_id)?, vec![other_id, reader_id]);
+    assert_eq!(follow_repository.following(&reader_id)?, vec![author_id]);
+    assert_eq!(
+      follow_repository.counts(&author_id)?,
+      models::FollowCounts { followers: 2, following: 0 }
+    );
+
+    like_repository.create(&reader_id, &post_ids[2])?;
+
+    let feed = post_repository.feed(&reader_id, 0)?;
+
+    assert_eq!(feed.iter().map(|post| post.title.as_str()).collect::<Vec<_>>(), vec!["4", "3"]);
+    assert!(!feed[0].liked);
+    assert!(feed[1].liked);
+    assert_eq!(feed[0].author.as_ref().unwrap().id, author_id);
+
+    let feed = post_repository.feed(&reader_id, 1)?;
+
+    assert_eq!(feed.iter().map(|post| post.title.as_str()).collect::<Vec<_>>(), vec!["1"]);
+
+    follow_repository.unfollow(&reader_id, &author_id)?;
+
+    assert!(post_repository.feed(&reader_id, 0)?.is_empty());
+    assert_eq!(follow_repository.counts(&reader_id)?, models::FollowCounts::default());
+
+    Ok(())
+  }
+}
@@ -0,0 +1,69 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError};
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Like {
+  store: Store
+}
+
+impl repository::Like for Like {
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    data.user(user_id)?;
+
+    let author_id = data.post(post_id)?.user_id;
+
+    if data.liked(user_id, post_id) {
+      return Err(Box::new(ConflictError::new("Post is already liked by this user")));
+    }
+
+    data.likes.push(
+      repository::LikeRecord {
+        user_id: *user_id,
+        post_id: *post_id
+      }
+    );
+
+    data.append(models::Event::PostLiked { user_id: *user_id, post_id: *post_id });
+
+    if let Some(author_id) = author_id.filter(|author_id| author_id != user_id) {
+      let mut notification = models::Notification::post_liked(author_id, *user_id, *post_id);
+
+      notification.id = models::NotificationId::from(data.next_id());
+
+      data.notifications.push(notification);
+    }
+
+    Ok(())
+  }
+
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    if !data.liked(user_id, post_id) {
+      return Err(Box::new(NotFoundError::new("Post is not liked by this user")));
+    }
+
+    data.likes.retain(|like| like.user_id != *user_id || like.post_id != *post_id);
+    data.append(models::Event::PostUnliked { user_id: *user_id, post_id: *post_id });
+
+    Ok(())
+  }
+}
+
+impl Like {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
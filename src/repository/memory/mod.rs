@@ -0,0 +1,157 @@
+use std::{collections::HashMap, sync::{Arc, Mutex, MutexGuard}, time::SystemTime};
+
+use crate::models;
+use crate::utils::error::{ConflictError, NotFoundError};
+
+use super::{Entity, UserRecord, SessionRecord, PostRecord, LikeRecord};
+
+mod user;
+mod session;
+mod post;
+mod like;
+mod outbox;
+mod webhook;
+mod notification;
+mod follow;
+mod transfer;
+
+pub use user::User;
+pub use session::Session;
+pub use post::Post;
+pub use like::Like;
+pub use outbox::Outbox;
+pub use webhook::Webhook;
+pub use notification::Notification;
+pub use follow::Follow;
+pub use transfer::Transfer;
+
+// Rows are kept as the transfer records, which hold the same data
+// the other backends store, password hashes included
+#[derive(Default)]
+struct Data {
+  users: Vec<UserRecord>,
+  sessions: Vec<SessionRecord>,
+  posts: Vec<PostRecord>,
+  likes: Vec<LikeRecord>,
+  // Events with the consumers that acknowledged them
+  events: Vec<(models::OutboxEvent, Vec<String>)>,
+  // How many events from the start each consumer acknowledged, polling starts there
+  cursors: HashMap<String, usize>,
+  webhooks: Vec<models::Webhook>,
+  deliveries: Vec<models::Delivery>,
+  notifications: Vec<models::Notification>,
+  // Follower and author, in the order they were followed
+  follows: Vec<(models::UserId, models::UserId)>,
+  // Ids transfers copied source records to, by entity and source id
+  copied: HashMap<(Entity, String), i32>,
+  next_id: i32
+}
+
+impl Data {
+  fn next_id(&mut self) -> i32 {
+    self.next_id += 1;
+
+    self.next_id
+  }
+
+  fn append(&mut self, event: models::Event) {
+    let id = models::EventId::from(self.next_id());
+
+    self.events.push(
+      (
+        models::OutboxEvent {
+          id,
+          event,
+          created_at: SystemTime::now()
+        },
+        Vec::new()
+      )
+    );
+  }
+
+  fn user(&self, id: &models::UserId) -> Result<&UserRecord, NotFoundError> {
+    self.users.iter()
+      .find(|user| user.id == *id)
+      .ok_or(NotFoundError::new("User with this id doesn't exist"))
+  }
+
+  fn user_mut(&mut self, id: &models::UserId) -> Result<&mut UserRecord, NotFoundError> {
+    self.users.iter_mut()
+      .find(|user| user.id == *id)
+      .ok_or(NotFoundError::new("User with this id doesn't exist"))
+  }
+
+  // Emails are compared ignoring case, like the other backends' indexes do
+  fn check_email(&self, email: &str, except: Option<&models::UserId>) -> Result<(), ConflictError> {
+    let email = email.to_lowercase();
+
+    let taken = self.users.iter()
+      .filter(|user| Some(&user.id) != except)
+      .any(|user| user.email.as_ref().is_some_and(|other| other.to_lowercase() == email));
+
+    if taken {
+      Err(ConflictError::new("User with this email already exists"))
+    } else {
+      Ok(())
+    }
+  }
+
+  fn post(&self, id: &models::PostId) -> Result<&PostRecord, NotFoundError> {
+    self.posts.iter()
+      .find(|post| post.id == *id)
+      .ok_or(NotFoundError::new("Post with this id not found"))
+  }
+
+  fn webhook(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<&models::Webhook, NotFoundError> {
+    self.webhooks.iter()
+      .find(|webhook| webhook.id == *id && webhook.user_id == *user_id)
+      .ok_or(NotFoundError::new("Webhook with this id not found"))
+  }
+
+  fn liked(&self, user_id: &models::UserId, post_id: &models::PostId) -> bool {
+    self.likes.iter().any(|like| like.user_id == *user_id && like.post_id == *post_id)
+  }
+
+  // The post with its author, as the other backends read it
+  fn read_post(&self, post: &PostRecord, user_id: Option<&models::UserId>) -> models::Post {
+    let author = post.user_id.as_ref()
+      .and_then(|user_id| self.user(user_id).ok())
+      .map(|user| models::User {
+        id: user.id,
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone(),
+        email: user.email.clone().filter(|_| user.display_email),
+        password: None,
+
+        settings: models::Settings::new()
+      });
+
+    models::Post {
+      id: post.id,
+      title: post.title.clone(),
+      text: post.text.clone(),
+      description: post.description.clone(),
+      liked: user_id.is_some_and(|user_id| self.liked(user_id, &post.id)),
+      author
+    }
+  }
+}
+
+// Repositories built on clones of one store see each other's writes,
+// nothing survives the process
+#[derive(Clone, Default)]
+pub struct Store(Arc<Mutex<Data>>);
+
+impl Store {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn lock(&self) -> MutexGuard<'_, Data> {
+    self.0.lock().unwrap_or_else(|err| err.into_inner())
+  }
+}
+
+fn hash(password: &str) -> String {
+  format!("{:x}", md5::compute(password))
+}
@@ -0,0 +1,117 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Notification {
+  store: Store
+}
+
+impl repository::Notification for Notification {
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().notifications.iter()
+        .rev()
+        .filter(|notification| notification.user_id == *user_id)
+        .cloned()
+        .collect()
+    )
+  }
+
+  fn mark_read(&self, user_id: &models::UserId, ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>> {
+    for notification in self.store.lock().notifications.iter_mut() {
+      if notification.user_id == *user_id && ids.contains(&notification.id) {
+        notification.read = true;
+      }
+    }
+
+    Ok(())
+  }
+
+  fn count_unread(&self, user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().notifications.iter()
+        .filter(|notification| notification.user_id == *user_id && !notification.read)
+        .count() as u64
+    )
+  }
+}
+
+impl Notification {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use crate::{models, repository::{self, User, Post, Like, Notification}};
+
+  use super::Store;
+
+  #[test]
+  fn test_notification() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store.clone());
+    let notification_repository = repository::memory::Notification::with_store(store);
+
+    let mut author = models::User::new();
+    let mut reader = models::User::new();
+
+    author.password = Some("secret".to_owned());
+    reader.password = Some("secret".to_owned());
+
+    let author_id = user_repository.create(&mut author)?;
+    let reader_id = user_repository.create(&mut reader)?;
+
+    let post_id = post_repository.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      }
+    )?;
+
+    like_repository.create(&reader_id, &post_id)?;
+    // Liking one's own post notifies nobody
+    like_repository.create(&author_id, &post_id)?;
+
+    let notifications = notification_repository.list(&author_id)?;
+
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].kind, models::NotificationKind::PostLiked);
+    assert_eq!(notifications[0].actor_id, reader_id);
+    assert_eq!(notifications[0].post_id, post_id);
+    assert!(!notifications[0].read);
+
+    assert_eq!(notification_repository.count_unread(&author_id)?, 1);
+    assert!(notification_repository.list(&reader_id)?.is_empty());
+
+    // Someone else's ids are ignored
+    notification_repository.mark_read(&reader_id, &[notifications[0].id])?;
+
+    assert_eq!(notification_repository.count_unread(&author_id)?, 1);
+
+    notification_repository.mark_read(&author_id, &[notifications[0].id])?;
+
+    assert_eq!(notification_repository.count_unread(&author_id)?, 0);
+    assert!(notification_repository.list(&author_id)?[0].read);
+
+    Ok(())
+  }
+}
@@ -0,0 +1,136 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Outbox {
+  store: Store
+}
+
+impl repository::Outbox for Outbox {
+  fn poll(&self, consumer: &str, limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let cursor = data.cursors.get(consumer).copied().unwrap_or_default();
+
+    Ok(
+      data.events[cursor..].iter()
+        .filter(|(_, consumers)| !consumers.iter().any(|acknowledged| acknowledged == consumer))
+        .take(limit)
+        .map(|(event, _)| event.clone())
+        .collect()
+    )
+  }
+
+  fn acknowledge(&self, consumer: &str, ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    for (event, consumers) in data.events.iter_mut() {
+      if ids.contains(&event.id) && !consumers.iter().any(|acknowledged| acknowledged == consumer) {
+        consumers.push(consumer.to_owned());
+      }
+    }
+
+    let mut cursor = data.cursors.get(consumer).copied().unwrap_or_default();
+
+    while data.events.get(cursor).is_some_and(|(_, consumers)| consumers.iter().any(|acknowledged| acknowledged == consumer)) {
+      cursor += 1;
+    }
+
+    data.cursors.insert(consumer.to_owned(), cursor);
+
+    Ok(())
+  }
+}
+
+impl Outbox {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use crate::{models, repository::{self, User, Post, Like, Outbox}};
+  use crate::utils::error::NotFoundError;
+
+  use super::Store;
+
+  #[test]
+  fn test_outbox() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store.clone());
+    let outbox = repository::memory::Outbox::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    user_repository.edit(&user.settings)?;
+
+    let post_id = post_repository.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(user)
+      }
+    )?;
+
+    like_repository.create(&user_id, &post_id)?;
+    like_repository.delete(&user_id, &post_id)?;
+    // Nothing to unlike, so no event
+    assert!(like_repository.delete(&user_id, &post_id).unwrap_err().is::<NotFoundError>());
+
+    let events = outbox.poll("test", 10)?;
+
+    assert_eq!(
+      events.iter().map(|event| event.event).collect::<Vec<_>>(),
+      vec![
+        models::Event::UserRegistered { user_id },
+        models::Event::SettingsChanged { user_id },
+        models::Event::PostPublished { post_id, user_id: Some(user_id) },
+        models::Event::PostLiked { user_id, post_id },
+        models::Event::PostUnliked { user_id, post_id }
+      ]
+    );
+
+    assert_eq!(outbox.poll("test", 2)?.len(), 2);
+
+    // Out of order, so the cursor waits for the first two
+    outbox.acknowledge("test", &[events[2].id])?;
+
+    assert_eq!(outbox.store.lock().cursors["test"], 0);
+
+    outbox.acknowledge("test", &[events[0].id, events[1].id])?;
+
+    // Acknowledged events at the start aren't scanned again
+    assert_eq!(outbox.store.lock().cursors["test"], 3);
+
+    let pending = outbox.poll("test", 10)?;
+
+    assert_eq!(pending.len(), 2);
+    assert_eq!(pending[0].id, events[3].id);
+
+    // Another consumer still gets everything
+    assert_eq!(outbox.poll("other", 10)?.len(), 5);
+
+    Ok(())
+  }
+}
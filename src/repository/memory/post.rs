@@ -0,0 +1,154 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Post {
+  store: Store
+}
+
+impl repository::Post for Post {
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    let user_id = post.author.as_ref().map(|author| author.id);
+
+    if let Some(user_id) = user_id.as_ref() {
+      data.user(user_id)?;
+    }
+
+    let post_id = models::PostId::from(data.next_id());
+
+    data.posts.push(
+      repository::PostRecord {
+        id: post_id,
+        user_id,
+        title: post.title.clone(),
+        text: post.text.clone(),
+        description: post.description.clone()
+      }
+    );
+
+    data.append(models::Event::PostPublished { post_id, user_id });
+
+    Ok(post_id)
+  }
+
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let post = data.post(id)?;
+
+    Ok(data.read_post(post, user_id))
+  }
+
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    Ok(
+      data.posts.iter()
+        .map(|post| data.read_post(post, user_id))
+        .collect()
+    )
+  }
+
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    Ok(
+      data.posts.iter()
+        .filter(|post| data.liked(user_id, &post.id))
+        .map(|post| data.read_post(post, Some(user_id)))
+        .collect()
+    )
+  }
+
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let per_page = data.user(user_id)?.posts_per_page.max(1) as usize;
+
+    Ok(
+      data.posts.iter()
+        .rev()
+        .filter(|post| {
+          post.user_id.is_some_and(|author_id| data.follows.contains(&(*user_id, author_id)))
+        })
+        .skip(page as usize * per_page)
+        .take(per_page)
+        .map(|post| data.read_post(post, Some(user_id)))
+        .collect()
+    )
+  }
+}
+
+impl Post {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use crate::{models, repository::{self, User, Post, Like}};
+  use crate::utils::error::{ConflictError, NotFoundError};
+
+  use super::Store;
+
+  #[test]
+  fn test_post() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    let mut post = models::Post {
+      id: models::PostId::new(),
+      title: "title".to_owned(),
+      text: None,
+      description: None,
+      liked: false,
+      author: Some(user)
+    };
+
+    let post_id = post_repository.create(&post)?;
+
+    post.author = None;
+
+    post_repository.create(&post)?;
+
+    assert_eq!(post_repository.list(None)?.len(), 2);
+    assert_eq!(post_repository.get(&post_id, None)?.author.unwrap().id, user_id);
+    assert!(post_repository.liked_list(&user_id)?.is_empty());
+
+    like_repository.create(&user_id, &post_id)?;
+
+    assert!(like_repository.create(&user_id, &post_id).unwrap_err().is::<ConflictError>());
+    assert!(post_repository.get(&post_id, Some(&user_id))?.liked);
+    assert_eq!(post_repository.liked_list(&user_id)?.len(), 1);
+
+    like_repository.delete(&user_id, &post_id)?;
+
+    assert!(like_repository.delete(&user_id, &post_id).unwrap_err().is::<NotFoundError>());
+    assert!(post_repository.liked_list(&user_id)?.is_empty());
+    assert!(post_repository.get(&models::PostId::from(100), None).is_err());
+
+    Ok(())
+  }
+}
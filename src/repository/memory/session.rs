@@ -0,0 +1,70 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{NotFoundError, StringError};
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Session {
+  store: Store
+}
+
+impl repository::Session for Session {
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let session = data.sessions.iter()
+      .find(|session| session.code == *code)
+      .ok_or(NotFoundError::new("User with this session code doesn't exist"))?;
+
+    Ok(session.user_id)
+  }
+
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    self.create_with_metadata(user_id, code, &models::SessionMetadata::new())
+  }
+
+  fn create_with_metadata(
+    &self, 
+    user_id: &models::UserId, code: &models::SessionCode, 
+    metadata: &models::SessionMetadata
+  ) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    data.user(user_id)?;
+
+    if data.sessions.iter().any(|session| session.code == *code) {
+      return Err(Box::new(StringError::new("Session with this code already exists")));
+    }
+
+    data.sessions.push(
+      repository::SessionRecord {
+        user_id: *user_id,
+        code: code.clone(),
+        user_agent: metadata.user_agent.clone(),
+        ip: metadata.ip.clone()
+      }
+    );
+
+    Ok(())
+  }
+
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    self.store.lock().sessions.retain(|session| session.code != *code);
+
+    Ok(())
+  }
+}
+
+impl Session {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
@@ -0,0 +1,148 @@
+use std::error;
+
+use crate::repository::{self, Entity};
+use crate::models;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Transfer {
+  store: Store
+}
+
+impl repository::Transfer for Transfer {
+  fn count(&self, entity: Entity) -> Result<u64, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let count = match entity {
+      Entity::Users | Entity::Settings => data.users.len(),
+      Entity::Sessions => data.sessions.len(),
+      Entity::Posts => data.posts.len(),
+      Entity::Likes => data.likes.len(),
+      Entity::Follows => data.follows.len()
+    };
+
+    Ok(count as u64)
+  }
+
+  fn users(&self) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    Ok(self.store.lock().users.clone())
+  }
+
+  fn sessions(&self) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    Ok(self.store.lock().sessions.clone())
+  }
+
+  fn posts(&self) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    Ok(self.store.lock().posts.clone())
+  }
+
+  fn likes(&self) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    Ok(self.store.lock().likes.clone())
+  }
+
+  fn follows(&self) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().follows.iter()
+        .map(|(follower_id, author_id)| repository::FollowRecord {
+          follower_id: *follower_id,
+          author_id: *author_id
+        })
+        .collect()
+    )
+  }
+
+  fn insert_users(&self, users: &[repository::UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    Ok(
+      users.iter()
+        .map(|user| {
+          let key = (Entity::Users, user.id.to_string());
+
+          if let Some(id) = data.copied.get(&key) {
+            return models::UserId::from(*id);
+          }
+
+          let id = data.next_id();
+
+          data.copied.insert(key, id);
+          data.users.push(repository::UserRecord { id: models::UserId::from(id), ..user.clone() });
+
+          models::UserId::from(id)
+        })
+        .collect()
+    )
+  }
+
+  fn insert_sessions(&self, sessions: &[repository::SessionRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    for session in sessions {
+      if !data.sessions.iter().any(|present| present.code == session.code) {
+        data.sessions.push(session.clone());
+      }
+    }
+
+    Ok(())
+  }
+
+  fn insert_posts(&self, posts: &[repository::PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    Ok(
+      posts.iter()
+        .map(|post| {
+          let key = (Entity::Posts, post.id.to_string());
+
+          if let Some(id) = data.copied.get(&key) {
+            return models::PostId::from(*id);
+          }
+
+          let id = data.next_id();
+
+          data.copied.insert(key, id);
+          data.posts.push(repository::PostRecord { id: models::PostId::from(id), ..post.clone() });
+
+          models::PostId::from(id)
+        })
+        .collect()
+    )
+  }
+
+  fn insert_likes(&self, likes: &[repository::LikeRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    for like in likes {
+      if !data.liked(&like.user_id, &like.post_id) {
+        data.likes.push(like.clone());
+      }
+    }
+
+    Ok(())
+  }
+
+  fn insert_follows(&self, follows: &[repository::FollowRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    for follow in follows {
+      let pair = (follow.follower_id, follow.author_id);
+
+      if !data.follows.contains(&pair) {
+        data.follows.push(pair);
+      }
+    }
+
+    Ok(())
+  }
+}
+
+impl Transfer {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
@@ -0,0 +1,421 @@
+use std::error;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{NotFoundError, ValidationError};
+
+use super::Store;
+
+#[derive(Default)]
+pub struct User {
+  store: Store
+}
+
+impl repository::User for User {
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+    let password = user.password.as_ref()
+      .ok_or(ValidationError::new("Password should be non-empty"))?;
+
+    let mut data = self.store.lock();
+
+    if let Some(email) = user.email.as_deref() {
+      data.check_email(email, None)?;
+    }
+
+    let user_id = models::UserId::from(data.next_id());
+
+    data.users.push(
+      repository::UserRecord {
+        id: user_id,
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone(),
+        email: user.email.clone(),
+        password_hash: Some(super::hash(password)),
+        posts_per_page: user.settings.posts_per_page,
+        display_email: user.settings.display_email
+      }
+    );
+
+    data.append(models::Event::UserRegistered { user_id });
+
+    user.id = user_id;
+    user.settings.id = user_id.to_string();
+    user.settings.user_id = user_id;
+
+    Ok(user_id)
+  }
+
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+    let email = email.to_lowercase();
+    let password = super::hash(password);
+
+    let data = self.store.lock();
+
+    let user = data.users.iter()
+      .find(|user| {
+        user.email.as_ref().is_some_and(|other| other.to_lowercase() == email)
+          && user.password_hash.as_ref() == Some(&password)
+      })
+      .ok_or(NotFoundError::new("User with this email and password doesn't exist"))?;
+
+    Ok(user.id)
+  }
+
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let user = data.user(id)?;
+
+    Ok(
+      models::User {
+        id: user.id,
+        first_name: user.first_name.clone(),
+        last_name: user.last_name.clone(),
+        email: user.email.clone().filter(|_| user.display_email),
+        password: None,
+
+        settings: models::Settings {
+          id: user.id.to_string(),
+          user_id: user.id,
+          posts_per_page: user.posts_per_page,
+          display_email: user.display_email
+        }
+      }
+    )
+  }
+
+  fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    let user = data.user_mut(&settings.user_id)?;
+
+    user.posts_per_page = settings.posts_per_page;
+    user.display_email = settings.display_email;
+
+    data.append(models::Event::SettingsChanged { user_id: settings.user_id });
+
+    Ok(())
+  }
+
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    let user = data.user_mut(id)?;
+
+    user.first_name = first_name.to_owned();
+    user.last_name = last_name.to_owned();
+
+    Ok(())
+  }
+
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+    if email.trim().is_empty() {
+      return Err(Box::new(ValidationError::new("Email should be non-empty")));
+    }
+
+    let mut data = self.store.lock();
+
+    data.check_email(email, Some(id))?;
+    data.user_mut(id)?.email = Some(email.to_owned());
+
+    Ok(())
+  }
+
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>> {
+    if new_password.is_empty() {
+      return Err(Box::new(ValidationError::new("Password should be non-empty")));
+    }
+
+    let mut data = self.store.lock();
+
+    let user = data.user_mut(id)?;
+
+    if user.password_hash.as_deref() != Some(super::hash(old_password).as_str()) {
+      return Err(Box::new(NotFoundError::new("User with this id and password doesn't exist")));
+    }
+
+    user.password_hash = Some(super::hash(new_password));
+
+    data.sessions.retain(|session| session.user_id != *id);
+
+    Ok(())
+  }
+
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    data.user(id)?;
+
+    data.users.retain(|user| user.id != *id);
+    data.sessions.retain(|session| session.user_id != *id);
+    data.likes.retain(|like| like.user_id != *id);
+    data.follows.retain(|(follower_id, author_id)| follower_id != id && author_id != id);
+    data.notifications.retain(|notification| notification.user_id != *id && notification.actor_id != *id);
+    data.webhooks.retain(|webhook| webhook.user_id != *id && webhook.topic.author_id() != Some(*id));
+
+    let webhook_ids: Vec<_> = data.webhooks.iter().map(|webhook| webhook.id).collect();
+
+    data.deliveries.retain(|delivery| webhook_ids.contains(&delivery.webhook_id));
+
+    for post in data.posts.iter_mut().filter(|post| post.user_id == Some(*id)) {
+      post.user_id = None;
+    }
+
+    data.append(models::Event::UserDeleted { user_id: *id });
+
+    Ok(())
+  }
+
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let user = data.user(id)?;
+
+    Ok(
+      models::PersonalData {
+        user: models::User {
+          id: user.id,
+          first_name: user.first_name.clone(),
+          last_name: user.last_name.clone(),
+          email: user.email.clone(),
+          password: None,
+
+          settings: models::Settings {
+            id: user.id.to_string(),
+            user_id: user.id,
+            posts_per_page: user.posts_per_page,
+            display_email: user.display_email
+          }
+        },
+        sessions: data.sessions.iter()
+          .filter(|session| session.user_id == *id)
+          .map(|session| {
+            models::SessionExport::new(
+              &session.code,
+              models::SessionMetadata {
+                user_agent: session.user_agent.clone(),
+                ip: session.ip.clone()
+              }
+            )
+          })
+          .collect(),
+        posts: data.posts.iter()
+          .filter(|post| post.user_id == Some(*id))
+          .map(|post| data.read_post(post, Some(id)))
+          .collect(),
+        liked: data.posts.iter()
+          .filter(|post| data.liked(id, &post.id))
+          .map(|post| data.read_post(post, Some(id)))
+          .collect()
+      }
+    )
+  }
+}
+
+impl User {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use crate::{models, repository::{self, User, Session, Post, Like, Follow}};
+  use crate::utils::error::{ConflictError, NotFoundError};
+
+  use super::Store;
+
+  #[test]
+  fn test_user() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let session_repository = repository::memory::Session::with_store(store);
+
+    let mut user = models::User::new();
+
+    user.first_name = "first".to_owned();
+    user.email = Some("first@last.test".to_owned());
+
+    assert!(user_repository.create(&mut user).is_err());
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = user_repository.create(&mut user)?;
+
+    assert_eq!(user.id, user_id);
+    assert_eq!(user_repository.get_id("first@last.test", "secret")?, user_id);
+    assert_eq!(user_repository.get_id("First@Last.TEST", "secret")?, user_id);
+
+    let mut other = models::User::new();
+
+    other.email = Some("FIRST@last.test".to_owned());
+    other.password = Some("other".to_owned());
+
+    assert!(user_repository.create(&mut other).unwrap_err().is::<ConflictError>());
+
+    other.email = Some("other@last.test".to_owned());
+
+    let other_id = user_repository.create(&mut other)?;
+
+    assert!(user_repository.change_email(&other_id, "First@last.test").unwrap_err().is::<ConflictError>());
+    assert!(user_repository.get_id("first@last.test", "wrong").unwrap_err().is::<NotFoundError>());
+
+    let mut settings = user_repository.get_user_settings(&user_id)?.settings;
+
+    settings.display_email = true;
+    settings.posts_per_page = 5;
+
+    user_repository.edit(&settings)?;
+
+    let read = user_repository.get_user_settings(&user_id)?;
+
+    assert_eq!(read.email, user.email);
+    assert_eq!(read.settings.posts_per_page, 5);
+    assert!(read.password.is_none());
+
+    let code = models::SessionCode::new("code");
+
+    session_repository.create(&user_id, &code)?;
+
+    assert_eq!(session_repository.get_user_id(&code)?, user_id);
+    assert!(session_repository.create(&models::UserId::from(100), &models::SessionCode::new("other")).is_err());
+
+    user_repository.edit_profile(&user_id, "renamed", "last")?;
+    user_repository.change_email(&user_id, "renamed@last.test")?;
+
+    let read = user_repository.get_user_settings(&user_id)?;
+
+    assert_eq!((read.first_name.as_str(), read.last_name.as_str()), ("renamed", "last"));
+    assert_eq!(read.email.as_deref(), Some("renamed@last.test"));
+    assert!(user_repository.get_id("first@last.test", "secret").is_err());
+
+    assert!(user_repository.change_password(&user_id, "wrong", "new").unwrap_err().is::<NotFoundError>());
+    assert_eq!(session_repository.get_user_id(&code)?, user_id);
+
+    user_repository.change_password(&user_id, "secret", "new")?;
+
+    assert_eq!(user_repository.get_id("renamed@last.test", "new")?, user_id);
+    assert!(session_repository.get_user_id(&code).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_delete() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let session_repository = repository::memory::Session::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store.clone());
+    let follow_repository = repository::memory::Follow::with_store(store);
+
+    let mut author = models::User::new();
+    let mut reader = models::User::new();
+
+    author.password = Some("secret".to_owned());
+    reader.password = Some("secret".to_owned());
+
+    let author_id = user_repository.create(&mut author)?;
+    let reader_id = user_repository.create(&mut reader)?;
+
+    let post_id = post_repository.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      }
+    )?;
+
+    let code = models::SessionCode::new("code");
+
+    session_repository.create(&author_id, &code)?;
+    like_repository.create(&reader_id, &post_id)?;
+    like_repository.create(&author_id, &post_id)?;
+    follow_repository.follow(&reader_id, &author_id)?;
+
+    user_repository.delete(&author_id)?;
+
+    assert!(user_repository.get_user_settings(&author_id).unwrap_err().is::<NotFoundError>());
+    assert!(session_repository.get_user_id(&code).is_err());
+    assert!(follow_repository.following(&reader_id)?.is_empty());
+    assert!(user_repository.delete(&author_id).is_err());
+
+    // The post stays, without its author and the author's like
+    let post = post_repository.get(&post_id, Some(&reader_id))?;
+
+    assert!(post.author.is_none());
+    assert!(post.liked);
+    assert!(!post_repository.get(&post_id, Some(&author_id))?.liked);
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_export_personal_data() -> Result<(), Box<dyn error::Error>> {
+    let store = Store::new();
+
+    let user_repository = repository::memory::User::with_store(store.clone());
+    let session_repository = repository::memory::Session::with_store(store.clone());
+    let post_repository = repository::memory::Post::with_store(store.clone());
+    let like_repository = repository::memory::Like::with_store(store);
+
+    let mut author = models::User::new();
+    let mut reader = models::User::new();
+
+    author.email = Some("author@test.test".to_owned());
+    author.password = Some("secret".to_owned());
+    reader.password = Some("secret".to_owned());
+
+    let author_id = user_repository.create(&mut author)?;
+    let reader_id = user_repository.create(&mut reader)?;
+
+    let post_id = post_repository.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author.clone())
+      }
+    )?;
+
+    let code = models::SessionCode::new("code");
+
+    session_repository.create(&author_id, &code)?;
+    session_repository.create(&reader_id, &models::SessionCode::new("other"))?;
+    like_repository.create(&author_id, &post_id)?;
+
+    let personal_data = user_repository.export_personal_data(&author_id)?;
+
+    // Hidden from readers, but it's the author's own data
+    assert_eq!(personal_data.user.email, author.email);
+    assert_eq!(personal_data.sessions, vec![models::SessionExport::new(&code, models::SessionMetadata::new())]);
+    assert_ne!(personal_data.sessions[0].code_hash, code.as_str());
+    assert_eq!(personal_data.posts.len(), 1);
+    assert_eq!(personal_data.liked[0].id, post_id);
+    assert!(personal_data.liked[0].liked);
+
+    let personal_data = user_repository.export_personal_data(&reader_id)?;
+
+    assert_eq!(personal_data.sessions.len(), 1);
+    assert!(personal_data.posts.is_empty());
+    assert!(personal_data.liked.is_empty());
+
+    assert!(user_repository.export_personal_data(&models::UserId::from(100)).unwrap_err().is::<NotFoundError>());
+
+    Ok(())
+  }
+}
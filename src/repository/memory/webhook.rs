@@ -0,0 +1,145 @@
+use std::{error, time::SystemTime};
+
+use crate::repository;
+use crate::models;
+
+use super::Store;
+
+#[derive(Default)]
+pub struct Webhook {
+  store: Store
+}
+
+impl repository::Webhook for Webhook {
+  fn create(&self, webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    data.user(&webhook.user_id)?;
+
+    let webhook_id = models::WebhookId::from(data.next_id());
+
+    data.webhooks.push(
+      models::Webhook {
+        id: webhook_id,
+        ..webhook.clone()
+      }
+    );
+
+    Ok(webhook_id)
+  }
+
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().webhooks.iter()
+        .filter(|webhook| webhook.user_id == *user_id)
+        .cloned()
+        .collect()
+    )
+  }
+
+  fn delete(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    data.webhook(id, user_id)?;
+
+    data.webhooks.retain(|webhook| webhook.id != *id);
+    data.deliveries.retain(|delivery| delivery.webhook_id != *id);
+
+    Ok(())
+  }
+
+  fn subscribers(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    Ok(
+      self.store.lock().webhooks.iter()
+        .filter(|webhook| webhook.topic == *topic)
+        .filter(|webhook| owner.is_none_or(|owner| webhook.user_id == *owner))
+        .cloned()
+        .collect()
+    )
+  }
+
+  fn record(&self, delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    let mut data = self.store.lock();
+
+    let delivery_id = models::DeliveryId::from(data.next_id());
+
+    for earlier in data.deliveries.iter_mut() {
+      if earlier.webhook_id == delivery.webhook_id && earlier.event_id == delivery.event_id {
+        earlier.next_attempt_at = None;
+      }
+    }
+
+    data.deliveries.push(
+      models::Delivery {
+        id: delivery_id,
+        ..delivery.clone()
+      }
+    );
+
+    Ok(delivery_id)
+  }
+
+  fn due(
+    &self,
+    now: SystemTime, limit: usize
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    let mut due: Vec<_> = data.deliveries.iter()
+      .filter(|delivery| delivery.next_attempt_at.is_some_and(|next_attempt_at| next_attempt_at <= now))
+      .filter_map(|delivery| {
+        data.webhooks.iter()
+          .find(|webhook| webhook.id == delivery.webhook_id)
+          .map(|webhook| (webhook.clone(), delivery.clone()))
+      })
+      .collect();
+
+    due.sort_by_key(|(_, delivery)| delivery.next_attempt_at);
+    due.truncate(limit);
+
+    Ok(due)
+  }
+
+  fn deliveries(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    data.webhook(id, user_id)?;
+
+    Ok(
+      data.deliveries.iter()
+        .rev()
+        .filter(|delivery| delivery.webhook_id == *id)
+        .cloned()
+        .collect()
+    )
+  }
+
+  fn dead_letters(&self, user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let data = self.store.lock();
+
+    Ok(
+      data.deliveries.iter()
+        .rev()
+        .filter(|delivery| delivery.status == models::DeliveryStatus::Dead)
+        .filter(|delivery| data.webhook(&delivery.webhook_id, user_id).is_ok())
+        .cloned()
+        .collect()
+    )
+  }
+}
+
+impl Webhook {
+  pub fn new() -> Self {
+    Self::with_store(Store::new())
+  }
+
+  pub fn with_store(store: Store) -> Self {
+    Self { store }
+  }
+}
@@ -1,12 +1,55 @@
+//! The repository traits, and a module with their implementations per backend.
+//! [`Repositories`] picks all of them for a backend at runtime.
+
+#[cfg(feature = "sync")]
 mod user;
+#[cfg(feature = "sync")]
 mod session;
+#[cfg(feature = "sync")]
 mod post;
+#[cfg(feature = "sync")]
 mod like;
+#[cfg(feature = "sync")]
+mod outbox;
+#[cfg(feature = "sync")]
+mod webhook;
+#[cfg(feature = "sync")]
+mod notification;
+#[cfg(feature = "sync")]
+mod follow;
+#[cfg(feature = "sync")]
+mod transfer;
+#[cfg(feature = "sync")]
+mod repositories;
 
+#[cfg(feature = "sync")]
+pub mod cache;
+#[cfg(all(feature = "sync", feature = "postgres"))]
 pub mod postgresql;
+#[cfg(feature = "sync")]
+pub mod memory;
+#[cfg(feature = "mongodb")]
 pub mod mongodb;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 
+#[cfg(feature = "sync")]
 pub use user::User;
+#[cfg(feature = "sync")]
 pub use session::Session;
+#[cfg(feature = "sync")]
 pub use post::Post;
+#[cfg(feature = "sync")]
 pub use like::Like;
+#[cfg(feature = "sync")]
+pub use outbox::Outbox;
+#[cfg(feature = "sync")]
+pub use webhook::Webhook;
+#[cfg(feature = "sync")]
+pub use notification::Notification;
+#[cfg(feature = "sync")]
+pub use follow::Follow;
+#[cfg(feature = "sync")]
+pub use transfer::{Transfer, Entity, UserRecord, SessionRecord, PostRecord, LikeRecord, FollowRecord};
+#[cfg(feature = "sync")]
+pub use repositories::{Repositories, Backend, Config};
@@ -0,0 +1,719 @@
+use std::error;
+
+use mongodb::bson::{self, doc, oid::ObjectId, Bson, DateTime, Document};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::models;
+
+use crate::utils::error::{DecodeError, InvalidIdError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Decoding {
+  // Malformed documents are reported with a DecodeError
+  #[default]
+  Strict,
+  // Missing or mistyped fields fall back to defaults, for legacy data
+  Lenient
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserDocument {
+  #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub first_name: Option<String>,
+  #[serde(default)]
+  pub last_name: Option<String>,
+  #[serde(default)]
+  pub email: Option<String>,
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub password: Option<String>,
+
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub settings: Option<SettingsDocument>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SettingsDocument {
+  #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub posts_per_page: Option<i32>,
+  #[serde(default)]
+  pub display_email: Option<bool>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PostDocument {
+  #[serde(rename = "_id", default, skip_serializing_if = "Option::is_none")]
+  pub id: Option<ObjectId>,
+  #[serde(default)]
+  pub user_id: Option<ObjectId>,
+  #[serde(default)]
+  pub title: Option<String>,
+  #[serde(default)]
+  pub text: Option<String>,
+  #[serde(default)]
+  pub description: Option<String>,
+
+  // Only present in the output of Post::pipeline
+  #[serde(default, skip_serializing)]
+  pub author: Option<UserDocument>,
+  #[serde(default, skip_serializing)]
+  pub liked: Option<bool>
+}
+
+pub fn from_document<T: DeserializeOwned>(doc: Document) -> Result<T, Box<dyn error::Error>> {
+  Ok(bson::from_document(doc)?)
+}
+
+pub fn to_document<T: Serialize>(value: &T) -> Result<Document, Box<dyn error::Error>> {
+  Ok(bson::to_document(value)?)
+}
+
+pub fn decode_user(doc: Document, decoding: Decoding) -> Result<models::User, DecodeError> {
+  let id = document_id(&doc);
+
+  let user: UserDocument = deserialize("users", id.as_deref(), doc, decoding)?;
+
+  user.into_model(decoding)
+    .map_err(|field| missing("users", id.as_deref(), field))
+}
+
+pub fn decode_post(doc: Document, decoding: Decoding) -> Result<models::Post, DecodeError> {
+  let id = document_id(&doc);
+
+  let post: PostDocument = deserialize("posts", id.as_deref(), doc, decoding)?;
+
+  post.into_model(decoding)
+    .map_err(|field| missing("posts", id.as_deref(), field))
+}
+
+// Joins the author and whether the given user liked the post
+pub fn post_pipeline(user_id: Option<ObjectId>) -> Vec<Document> {
+  vec![
+    doc! {
+      "$lookup": doc! {
+        "from": "users",
+        "localField": "user_id",
+        "foreignField": "_id",
+        "as": "author"
+      }
+    },
+    doc! {
+      "$set": doc! {
+        "author": doc! {
+          "$first": "$author"
+        }
+      }
+    },
+    doc! {
+      "$unset": vec![
+        "user_id",
+        "author.sessions",
+        "author.settings",
+        "author.email",
+        "author.password"              
+      ]
+    },
+    doc! {
+      "$lookup": doc! {
+        "from": "likes",
+        "let": doc! {
+          "post_id": "$_id"
+        },
+        "pipeline": vec![
+          doc! {
+            "$match": doc! {
+              "$expr": doc! {
+                "$and": vec![
+                  doc! {"$eq": vec!["$post_id", "$$post_id"]},
+                  doc! {"$eq": vec![
+                    Bson::String("$user_id".to_owned()), 
+                    user_id.map(Bson::ObjectId).unwrap_or(Bson::Null)
+                  ]}
+                ]
+              }
+            }
+          }
+        ],
+        "as": "like"
+      }
+    },
+    doc! {
+      "$set": doc! {
+        "liked": doc! {
+          "$cond": doc! {
+            "if": doc! {"$eq": vec![
+              Bson::Document(
+                doc! {"$size": "$like"}
+              ),
+              Bson::Int32(0)
+            ]},
+            "then": false,
+            "else": true
+          }
+        }
+      }
+    },
+    doc! {
+      "$unset": vec!["like"]
+    }
+  ]
+}
+
+// Ids are stored as fields of their own, so consumers can filter on them
+pub fn event_document(event: &models::Event) -> Result<Document, InvalidIdError> {
+  let mut doc = doc! {
+    "kind": event.kind(),
+    "created_at": DateTime::now()
+  };
+
+  if let Some(user_id) = event.user_id() {
+    doc.insert("user_id", user_id.as_object_id()?);
+  }
+
+  if let Some(post_id) = event.post_id() {
+    doc.insert("post_id", post_id.as_object_id()?);
+  }
+
+  Ok(doc)
+}
+
+pub fn decode_event(doc: Document) -> Result<models::OutboxEvent, DecodeError> {
+  let id = document_id(&doc);
+
+  let event_id = doc.get_object_id("_id")
+    .map_err(|_| missing("outbox", id.as_deref(), "_id"))?;
+  let kind = doc.get_str("kind")
+    .map_err(|_| missing("outbox", id.as_deref(), "kind"))?;
+  let created_at = doc.get_datetime("created_at")
+    .map_err(|_| missing("outbox", id.as_deref(), "created_at"))?;
+
+  let event = models::Event::from_parts(
+    kind,
+    doc.get_object_id("user_id").ok().map(models::UserId::from),
+    doc.get_object_id("post_id").ok().map(models::PostId::from)
+  )
+  .ok_or(DecodeError::new("outbox", id.as_deref(), "kind", &format!("{:?} doesn't match the ids", kind)))?;
+
+  Ok(
+    models::OutboxEvent {
+      id: models::EventId::from(event_id),
+      event,
+      created_at: created_at.to_system_time()
+    }
+  )
+}
+
+pub fn webhook_document(webhook: &models::Webhook) -> Result<Document, InvalidIdError> {
+  let mut doc = doc! {
+    "user_id": webhook.user_id.as_object_id()?,
+    "url": &webhook.url,
+    "secret": &webhook.secret,
+    "kind": webhook.topic.kind()
+  };
+
+  if let Some(author_id) = webhook.topic.author_id() {
+    doc.insert("author_id", author_id.as_object_id()?);
+  }
+
+  Ok(doc)
+}
+
+pub fn decode_webhook(doc: Document) -> Result<models::Webhook, DecodeError> {
+  let id = document_id(&doc);
+
+  let webhook_id = doc.get_object_id("_id")
+    .map_err(|_| missing("webhooks", id.as_deref(), "_id"))?;
+  let user_id = doc.get_object_id("user_id")
+    .map_err(|_| missing("webhooks", id.as_deref(), "user_id"))?;
+  let url = doc.get_str("url")
+    .map_err(|_| missing("webhooks", id.as_deref(), "url"))?;
+  let secret = doc.get_str("secret")
+    .map_err(|_| missing("webhooks", id.as_deref(), "secret"))?;
+  let kind = doc.get_str("kind")
+    .map_err(|_| missing("webhooks", id.as_deref(), "kind"))?;
+
+  let topic = models::Topic::from_parts(
+    kind,
+    doc.get_object_id("author_id").ok().map(models::UserId::from)
+  )
+  .ok_or(DecodeError::new("webhooks", id.as_deref(), "kind", &format!("{:?} isn't a topic", kind)))?;
+
+  Ok(
+    models::Webhook {
+      id: models::WebhookId::from(webhook_id),
+      user_id: models::UserId::from(user_id),
+      url: url.to_owned(),
+      secret: secret.to_owned(),
+      topic
+    }
+  )
+}
+
+pub fn delivery_document(delivery: &models::Delivery) -> Result<Document, InvalidIdError> {
+  Ok(
+    doc! {
+      "webhook_id": delivery.webhook_id.as_object_id()?,
+      "event_id": delivery.event_id.as_object_id()?,
+      "attempt": delivery.attempt,
+      "status": delivery.status.as_str(),
+      "response_status": delivery.response_status,
+      "error": &delivery.error,
+      "payload": &delivery.payload,
+      "created_at": DateTime::from_system_time(delivery.created_at),
+      "next_attempt_at": delivery.next_attempt_at.map(DateTime::from_system_time)
+    }
+  )
+}
+
+pub fn decode_delivery(doc: Document) -> Result<models::Delivery, DecodeError> {
+  let id = document_id(&doc);
+
+  let delivery_id = doc.get_object_id("_id")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "_id"))?;
+  let webhook_id = doc.get_object_id("webhook_id")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "webhook_id"))?;
+  let event_id = doc.get_object_id("event_id")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "event_id"))?;
+  let attempt = doc.get_i32("attempt")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "attempt"))?;
+  let status = doc.get_str("status")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "status"))?;
+  let payload = doc.get_str("payload")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "payload"))?;
+  let created_at = doc.get_datetime("created_at")
+    .map_err(|_| missing("webhook_deliveries", id.as_deref(), "created_at"))?;
+
+  let status = models::DeliveryStatus::parse(status)
+    .ok_or(DecodeError::new("webhook_deliveries", id.as_deref(), "status", &format!("{:?} isn't a status", status)))?;
+
+  Ok(
+    models::Delivery {
+      id: models::DeliveryId::from(delivery_id),
+      webhook_id: models::WebhookId::from(webhook_id),
+      event_id: models::EventId::from(event_id),
+      attempt,
+      status,
+      response_status: doc.get_i32("response_status").ok(),
+      error: doc.get_str("error").ok().map(str::to_owned),
+      payload: payload.to_owned(),
+      created_at: created_at.to_system_time(),
+      next_attempt_at: doc.get_datetime("next_attempt_at").ok().map(|next_attempt_at| next_attempt_at.to_system_time())
+    }
+  )
+}
+
+pub fn notification_document(notification: &models::Notification) -> Result<Document, InvalidIdError> {
+  Ok(
+    doc! {
+      "user_id": notification.user_id.as_object_id()?,
+      "kind": notification.kind.as_str(),
+      "actor_id": notification.actor_id.as_object_id()?,
+      "post_id": notification.post_id.as_object_id()?,
+      "read": notification.read,
+      "created_at": DateTime::from_system_time(notification.created_at)
+    }
+  )
+}
+
+pub fn decode_notification(doc: Document) -> Result<models::Notification, DecodeError> {
+  let id = document_id(&doc);
+
+  let notification_id = doc.get_object_id("_id")
+    .map_err(|_| missing("notifications", id.as_deref(), "_id"))?;
+  let user_id = doc.get_object_id("user_id")
+    .map_err(|_| missing("notifications", id.as_deref(), "user_id"))?;
+  let kind = doc.get_str("kind")
+    .map_err(|_| missing("notifications", id.as_deref(), "kind"))?;
+  let actor_id = doc.get_object_id("actor_id")
+    .map_err(|_| missing("notifications", id.as_deref(), "actor_id"))?;
+  let post_id = doc.get_object_id("post_id")
+    .map_err(|_| missing("notifications", id.as_deref(), "post_id"))?;
+  let read = doc.get_bool("read")
+    .map_err(|_| missing("notifications", id.as_deref(), "read"))?;
+  let created_at = doc.get_datetime("created_at")
+    .map_err(|_| missing("notifications", id.as_deref(), "created_at"))?;
+
+  let kind = models::NotificationKind::parse(kind)
+    .ok_or(DecodeError::new("notifications", id.as_deref(), "kind", &format!("{:?} isn't a kind", kind)))?;
+
+  Ok(
+    models::Notification {
+      id: models::NotificationId::from(notification_id),
+      user_id: models::UserId::from(user_id),
+      kind,
+      actor_id: models::UserId::from(actor_id),
+      post_id: models::PostId::from(post_id),
+      read,
+      created_at: created_at.to_system_time()
+    }
+  )
+}
+
+fn document_id(doc: &Document) -> Option<String> {
+  doc.get("_id")
+    .map(|id| match id {
+      Bson::ObjectId(id) => id.to_string(),
+      id => id.to_string()
+    })
+}
+
+fn missing(collection: &str, id: Option<&str>, field: &str) -> DecodeError {
+  DecodeError::new(collection, id, field, "is missing")
+}
+
+// In lenient mode a field that fails to deserialize is dropped and
+// decoding is retried, so it falls back to its default further on
+fn deserialize<T: DeserializeOwned>(
+  collection: &str, id: Option<&str>,
+  mut doc: Document, decoding: Decoding
+) -> Result<T, DecodeError> {
+  loop {
+    let deserializer = bson::Deserializer::new(Bson::Document(doc.clone()));
+
+    match serde_path_to_error::deserialize(deserializer) {
+      Ok(value) => return Ok(value),
+      Err(err) => {
+        let field = err.path().to_string();
+
+        if decoding == Decoding::Lenient && remove_field(&mut doc, &field) {
+          continue;
+        }
+
+        return Err(
+          DecodeError::new(collection, id, &field, &err.inner().to_string())
+        );
+      }
+    }
+  }
+}
+
+fn remove_field(doc: &mut Document, path: &str) -> bool {
+  match path.split_once('.') {
+    Some((key, rest)) => doc.get_document_mut(key)
+      .map(|doc| remove_field(doc, rest))
+      .unwrap_or(false),
+    None => doc.remove(path).is_some()
+  }
+}
+
+fn checked<T>(value: Option<T>, field: &'static str, decoding: Decoding) -> Result<Option<T>, &'static str> {
+  match (value, decoding) {
+    (None, Decoding::Strict) => Err(field),
+    (value, _) => Ok(value)
+  }
+}
+
+fn required<T: Default>(value: Option<T>, field: &'static str, decoding: Decoding) -> Result<T, &'static str> {
+  Ok(
+    checked(value, field, decoding)?
+      .unwrap_or_default()
+  )
+}
+
+impl TryFrom<&models::User> for UserDocument {
+  type Error = InvalidIdError;
+
+  // The password is left out, it has to be hashed by the caller
+  fn try_from(user: &models::User) -> Result<Self, Self::Error> {
+    Ok(
+      Self {
+        id: if user.id.is_empty() { None } else { Some(user.id.as_object_id()?) },
+        first_name: Some(user.first_name.clone()),
+        last_name: Some(user.last_name.clone()),
+        email: user.email.clone(),
+        password: None,
+
+        settings: Some(SettingsDocument::try_from(&user.settings)?)
+      }
+    )
+  }
+}
+
+impl UserDocument {
+  pub fn into_model(mut self, decoding: Decoding) -> Result<models::User, &'static str> {
+    let id = checked(self.id, "_id", decoding)?
+      .map(models::UserId::from)
+      .unwrap_or_default();
+
+    let settings = match (self.settings.take(), decoding) {
+      (Some(settings), _) => settings.into_model(id, decoding)?,
+      (None, Decoding::Lenient) => models::Settings {
+        user_id: id,
+        ..models::Settings::new()
+      },
+      (None, Decoding::Strict) => return Err("settings")
+    };
+
+    Ok(
+      models::User {
+        settings,
+        ..self.into_author(decoding)?
+      }
+    )
+  }
+
+  // Authors are projected without their settings, email and password
+  pub fn into_author(self, decoding: Decoding) -> Result<models::User, &'static str> {
+    Ok(
+      models::User {
+        id: checked(self.id, "_id", decoding)?
+          .map(models::UserId::from)
+          .unwrap_or_default(),
+        first_name: required(self.first_name, "first_name", decoding)?,
+        last_name: required(self.last_name, "last_name", decoding)?,
+        email: self.email,
+        password: None,
+
+        settings: models::Settings::new()
+      }
+    )
+  }
+}
+
+impl TryFrom<&models::Settings> for SettingsDocument {
+  type Error = InvalidIdError;
+
+  fn try_from(settings: &models::Settings) -> Result<Self, Self::Error> {
+    Ok(
+      Self {
+        id: if settings.id.is_empty() { None } else { Some(ObjectId::parse_str(&settings.id).map_err(|_| InvalidIdError::new(&settings.id))?) },
+        posts_per_page: Some(settings.posts_per_page),
+        display_email: Some(settings.display_email)
+      }
+    )
+  }
+}
+
+impl SettingsDocument {
+  pub fn into_model(
+    self,
+    user_id: models::UserId, decoding: Decoding
+  ) -> Result<models::Settings, &'static str> {
+    let defaults = models::Settings::new();
+
+    Ok(
+      models::Settings {
+        id: checked(self.id, "settings._id", decoding)?
+          .map(|id| id.to_string())
+          .unwrap_or_default(),
+        user_id,
+        posts_per_page: match (self.posts_per_page, decoding) {
+          (Some(posts_per_page), _) => posts_per_page,
+          (None, Decoding::Lenient) => defaults.posts_per_page,
+          (None, Decoding::Strict) => return Err("settings.posts_per_page")
+        },
+        display_email: required(self.display_email, "settings.display_email", decoding)?
+      }
+    )
+  }
+}
+
+impl TryFrom<&models::Post> for PostDocument {
+  type Error = InvalidIdError;
+
+  fn try_from(post: &models::Post) -> Result<Self, Self::Error> {
+    Ok(
+      Self {
+        id: if post.id.is_empty() { None } else { Some(post.id.as_object_id()?) },
+        user_id: match post.author.as_ref() {
+          Some(author) => Some(author.id.as_object_id()?),
+          None => None
+        },
+        title: Some(post.title.clone()),
+        text: post.text.clone(),
+        description: post.description.clone(),
+
+        author: None,
+        liked: None
+      }
+    )
+  }
+}
+
+impl PostDocument {
+  pub fn into_model(self, decoding: Decoding) -> Result<models::Post, &'static str> {
+    Ok(
+      models::Post {
+        id: checked(self.id, "_id", decoding)?
+          .map(models::PostId::from)
+          .unwrap_or_default(),
+        title: required(self.title, "title", decoding)?,
+        text: self.text,
+        description: self.description,
+        liked: self.liked.unwrap_or(false),
+
+        author: match self.author {
+          Some(author) => Some(
+            author.into_author(decoding)
+              .map_err(|field| match field {
+                "_id" => "author._id",
+                "first_name" => "author.first_name",
+                "last_name" => "author.last_name",
+                field => field
+              })?
+          ),
+          None => None
+        }
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use mongodb::bson::{doc, oid::ObjectId};
+
+  use super::{decode_post, decode_user, to_document, Decoding, PostDocument, UserDocument};
+  use crate::models;
+
+  #[test]
+  fn test_user_round_trip() {
+    let mut user = models::User::new();
+
+    user.id = models::UserId::from(ObjectId::new());
+    user.first_name = "first".to_owned();
+    user.last_name = "last".to_owned();
+    user.email = Some("first@last.test".to_owned());
+    user.password = Some("secret".to_owned());
+    user.settings.id = ObjectId::new().to_string();
+    user.settings.posts_per_page = 25;
+    user.settings.display_email = true;
+
+    let doc = to_document(&UserDocument::try_from(&user).unwrap()).unwrap();
+
+    assert!(doc.get("password").is_none());
+
+    let read = decode_user(doc, Decoding::Strict).unwrap();
+
+    assert_eq!(read.id, user.id);
+    assert_eq!(read.first_name, user.first_name);
+    assert_eq!(read.last_name, user.last_name);
+    assert_eq!(read.email, user.email);
+    assert!(read.password.is_none());
+    assert_eq!(read.settings.id, user.settings.id);
+    assert_eq!(read.settings.user_id, user.id);
+    assert_eq!(read.settings.posts_per_page, user.settings.posts_per_page);
+    assert_eq!(read.settings.display_email, user.settings.display_email);
+  }
+
+  #[test]
+  fn test_post_round_trip() {
+    let mut author = models::User::new();
+
+    author.id = models::UserId::from(ObjectId::new());
+
+    let post = models::Post {
+      id: models::PostId::from(ObjectId::new()),
+      title: "title".to_owned(),
+      text: Some("text".to_owned()),
+      description: None,
+      liked: false,
+      author: Some(author)
+    };
+
+    let author_id = post.author.as_ref().unwrap().id.as_object_id().unwrap();
+
+    let mut doc = to_document(&PostDocument::try_from(&post).unwrap()).unwrap();
+
+    assert_eq!(doc.get_object_id("user_id").unwrap(), author_id);
+
+    doc.insert(
+      "author",
+      doc! {
+        "_id": author_id,
+        "first_name": "first",
+        "last_name": "last"
+      }
+    );
+    doc.insert("liked", true);
+
+    let read = decode_post(doc, Decoding::Strict).unwrap();
+
+    assert_eq!(read.id, post.id);
+    assert_eq!(read.title, post.title);
+    assert_eq!(read.text, post.text);
+    assert_eq!(read.description, post.description);
+    assert!(read.liked);
+    assert_eq!(read.author.as_ref().unwrap().id, post.author.as_ref().unwrap().id);
+    assert_eq!(read.author.as_ref().unwrap().first_name, "first");
+  }
+
+  #[test]
+  fn test_invalid_ids() {
+    let mut user = models::User::new();
+
+    user.id = models::UserId::from(1);
+
+    assert!(UserDocument::try_from(&user).is_err());
+  }
+
+  #[test]
+  fn test_strict() {
+    let id = ObjectId::new();
+
+    let err = decode_post(doc! { "_id": id }, Decoding::Strict)
+      .unwrap_err();
+
+    assert_eq!(err.collection, "posts");
+    assert_eq!(err.id, Some(id.to_string()));
+    assert_eq!(err.field, "title");
+
+    let err = decode_post(doc! { "_id": id, "title": 5 }, Decoding::Strict)
+      .unwrap_err();
+
+    assert_eq!(err.field, "title");
+
+    let err = decode_user(
+      doc! {
+        "_id": id,
+        "first_name": "first",
+        "last_name": "last",
+        "settings": doc! {
+          "_id": ObjectId::new(),
+          "posts_per_page": "ten",
+          "display_email": false
+        }
+      },
+      Decoding::Strict
+    )
+    .unwrap_err();
+
+    assert_eq!(err.collection, "users");
+    assert_eq!(err.field, "settings.posts_per_page");
+    assert!(err.to_string().contains(&id.to_string()));
+  }
+
+  #[test]
+  fn test_lenient() {
+    let id = ObjectId::new();
+
+    let post = decode_post(doc! { "_id": id, "title": 5 }, Decoding::Lenient)
+      .unwrap();
+
+    assert!(decode_post(doc! { "title": "title" }, Decoding::Lenient).unwrap().id.is_empty());
+
+    assert_eq!(post.id, models::PostId::from(id));
+    assert_eq!(post.title, "");
+
+    let user = decode_user(
+      doc! {
+        "_id": id,
+        "first_name": "first",
+        "settings": doc! {
+          "posts_per_page": "ten"
+        }
+      },
+      Decoding::Lenient
+    )
+    .unwrap();
+
+    assert_eq!(user.first_name, "first");
+    assert_eq!(user.last_name, "");
+    assert_eq!(user.settings.posts_per_page, models::Settings::new().posts_per_page);
+    assert!(!user.settings.display_email);
+    assert!(user.settings.id.is_empty());
+  }
+}
@@ -0,0 +1,329 @@
+use std::error;
+
+use mongodb::{
+  bson::{doc, DateTime, Document},
+  options::{FindOptions, UpdateOptions},
+  sync::ClientSession
+};
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{NotFoundError, ValidationError};
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Follow {
+  pool: utils::Pool
+}
+
+impl repository::Follow for Follow {
+  fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.follow_ws(follower_id, author_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn unfollow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.unfollow_ws(follower_id, author_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn followers(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.followers_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn following(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.following_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn counts(&self, user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.counts_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+}
+
+impl Follow {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn follow_ws(
+    &self,
+    follower_id: &models::UserId, author_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if follower_id == author_id {
+      return Err(Box::new(ValidationError::new("Users can't follow themselves")));
+    }
+
+    let follower_id = follower_id.as_object_id()?;
+    let author_id = author_id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    // Nothing references users in MongoDB, so check both exist
+    let users = db.collection::<Document>("users")
+      .count_documents_with_session(
+        doc! {
+          "_id": doc! { "$in": [follower_id, author_id] }
+        },
+        None,
+        session
+      )?;
+
+    if users < 2 {
+      return Err(Box::new(NotFoundError::new("User with this id not found")));
+    }
+
+    db.collection::<Document>("follows")
+      .update_one_with_session(
+        doc! {
+          "follower_id": follower_id,
+          "author_id": author_id
+        },
+        doc! {
+          "$setOnInsert": doc! {
+            "created_at": DateTime::now()
+          }
+        },
+        UpdateOptions::builder()
+          .upsert(true)
+          .build(),
+        session
+      )?;
+
+    Ok(())
+  }
+
+  pub fn unfollow_ws(
+    &self,
+    follower_id: &models::UserId, author_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    session.client().default_database().unwrap()
+      .collection::<Document>("follows")
+      .delete_one_with_session(
+        doc! {
+          "follower_id": follower_id.as_object_id()?,
+          "author_id": author_id.as_object_id()?
+        },
+        None,
+        session
+      )?;
+
+    Ok(())
+  }
+
+  pub fn followers_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    self.find_ws(doc! { "author_id": user_id.as_object_id()? }, "follower_id", session)
+  }
+
+  pub fn following_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    self.find_ws(doc! { "follower_id": user_id.as_object_id()? }, "author_id", session)
+  }
+
+  pub fn counts_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    let user_id = user_id.as_object_id()?;
+
+    let follows = session.client().default_database().unwrap()
+      .collection::<Document>("follows");
+
+    Ok(
+      models::FollowCounts {
+        followers: follows.count_documents_with_session(doc! { "author_id": user_id }, None, session)?,
+        following: follows.count_documents_with_session(doc! { "follower_id": user_id }, None, session)?
+      }
+    )
+  }
+
+  fn find_ws(
+    &self,
+    filter: Document, field: &str,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("follows")
+      .find_with_session(
+        filter,
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "created_at": -1
+            }
+          )
+          .build(),
+        session
+      )?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(models::UserId::from(doc?.get_object_id(field)?));
+    }
+
+    Ok(data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_follow() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_7__".to_owned(),
+      last_name: "__test_7__".to_owned(),
+      email: Some("__test_7__@7.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_8__".to_owned(),
+      last_name: "__test_8__".to_owned(),
+      email: Some("__test_8__@8.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    reader.settings.posts_per_page = 1;
+
+    let user_repository = repository::mongodb::User::new();
+    let post_repository = repository::mongodb::Post::new();
+    let follow_repository = repository::mongodb::Follow::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let author_id = user_repository.create_ws(&mut author, &mut session)?;
+    let reader_id = user_repository.create_ws(&mut reader, &mut session)?;
+
+    let mut post_ids = Vec::new();
+
+    for title in ["__test_7_1__", "__test_7_2__"] {
+      post_ids.push(
+        post_repository.create_ws(
+          &models::Post {
+            id: models::PostId::new(),
+            title: title.to_owned(),
+            text: None,
+            description: None,
+            liked: false,
+            author: Some(author.clone())
+          },
+          &mut session
+        )?
+      );
+    }
+
+    assert!(post_repository.feed_ws(&reader_id, 0, &mut session)?.is_empty());
+
+    follow_repository.follow_ws(&reader_id, &author_id, &mut session)?;
+    follow_repository.follow_ws(&reader_id, &author_id, &mut session)?;
+
+    assert!(follow_repository.follow_ws(&reader_id, &reader_id, &mut session).is_err());
+
+    assert_eq!(follow_repository.followers_ws(&author_id, &mut session)?, vec![reader_id]);
+    assert_eq!(follow_repository.following_ws(&reader_id, &mut session)?, vec![author_id]);
+    assert_eq!(
+      follow_repository.counts_ws(&author_id, &mut session)?,
+      models::FollowCounts { followers: 1, following: 0 }
+    );
+
+    let feed = post_repository.feed_ws(&reader_id, 0, &mut session)?;
+
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, post_ids[1]);
+    assert_eq!(feed[0].author.as_ref().unwrap().id, author_id);
+
+    let feed = post_repository.feed_ws(&reader_id, 1, &mut session)?;
+
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, post_ids[0]);
+
+    follow_repository.unfollow_ws(&reader_id, &author_id, &mut session)?;
+
+    assert!(post_repository.feed_ws(&reader_id, 0, &mut session)?.is_empty());
+
+    session.abort_transaction()?;
+
+    Ok(())
+  }
+}
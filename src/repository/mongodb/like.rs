@@ -1,84 +1,126 @@
 use std::error;
 
 use mongodb::{
-  bson::{doc, oid::ObjectId, Document}, 
+  bson::{doc, Document}, 
   sync::ClientSession
 };
 
 use crate::repository;
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError};
 
 use super::utils;
 
-pub struct Like {}
+#[derive(Default)]
+pub struct Like {
+  pool: utils::Pool
+}
 
 impl repository::Like for Like {
-  fn create(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
 
     let res = self.create_ws(user_id, post_id, &mut session);
 
-    session.commit_transaction()?;
-
-    res
+    utils::commit_or_abort(&mut session, res)
   }
 
-  fn delete(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
 
     let res = self.delete_ws(user_id, post_id, &mut session);
 
-    session.commit_transaction()?;
-
-    res
+    utils::commit_or_abort(&mut session, res)
   }
 }
 
 impl Like {
   pub fn new() -> Self {
-    Self {  }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn create_ws(
     &self, 
-    user_id: &str, post_id: &str,
+    user_id: &models::UserId, post_id: &models::PostId,
     session: &mut ClientSession
   ) -> Result<(), Box<dyn error::Error>> {
-    session.client().default_database().unwrap()
-      .collection("likes")
-      .insert_one_with_session(
-        doc! {
-          "user_id": ObjectId::parse_str(user_id)?,
-          "post_id": ObjectId::parse_str(post_id)?
-        },
-        None,
+    // Looked up first, so a missing post fails before anything is written
+    let author_id = super::Post::new().get_ws(post_id, None, session)?
+      .author
+      .map(|author| author.id)
+      .filter(|author_id| author_id != user_id);
+
+    let like = doc! {
+      "user_id": user_id.as_object_id()?,
+      "post_id": post_id.as_object_id()?
+    };
+
+    let likes = session.client().default_database().unwrap()
+      .collection::<Document>("likes");
+
+    // A failed write would abort the whole transaction, so a repeated like is caught first
+    if likes.find_one_with_session(like.clone(), None, session)?.is_some() {
+      return Err(Box::new(ConflictError::new("Post is already liked by this user")));
+    }
+
+    likes.insert_one_with_session(like, None, session)
+      .map_err(|err| utils::conflict(err.into(), "Post is already liked by this user"))?;
+
+    if let Some(author_id) = author_id {
+      super::Notification::new().create_ws(
+        &models::Notification::post_liked(author_id, *user_id, *post_id),
         session
       )?;
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostLiked { user_id: *user_id, post_id: *post_id },
+      session
+    )?;
 
     Ok(())
   }
 
   pub fn delete_ws(
     &self, 
-    user_id: &str, post_id: &str,
+    user_id: &models::UserId, post_id: &models::PostId,
     session: &mut ClientSession
   ) -> Result<(), Box<dyn error::Error>> {
-    session.client().default_database().unwrap()
+    let res = session.client().default_database().unwrap()
       .collection::<Document>("likes")
       .delete_one_with_session(
         doc! {
-          "user_id": ObjectId::parse_str(user_id)?,
-          "post_id": ObjectId::parse_str(post_id)?
+          "user_id": user_id.as_object_id()?,
+          "post_id": post_id.as_object_id()?
         }, 
         None, 
         session
       )?;
 
+    if res.deleted_count == 0 {
+      return Err(Box::new(NotFoundError::new("Post is not liked by this user")));
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostUnliked { user_id: *user_id, post_id: *post_id },
+      session
+    )?;
+
     Ok(())
   }
 }
@@ -0,0 +1,776 @@
+use std::error;
+
+use mongodb::{
+  bson::{doc, oid::ObjectId, Bson, DateTime, Document},
+  options::{FindOneOptions, FindOptions, IndexOptions, UpdateOptions},
+  sync::Database, IndexModel
+};
+
+use crate::utils::error::ConflictError;
+
+use super::SESSION_TTL;
+use super::utils;
+
+pub struct Migration {
+  pub version: i32,
+  pub description: &'static str,
+  pub apply: fn(&Database) -> Result<(), Box<dyn error::Error>>
+}
+
+#[derive(Default)]
+pub struct Migrator {
+  pool: utils::Pool
+}
+
+impl Migrator {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn migrations(&self) -> Vec<Migration> {
+    vec![
+      Migration {
+        version: 1,
+        description: "Create users, posts and likes with validators and indexes",
+        apply: create_collections
+      },
+      Migration {
+        version: 2,
+        description: "Move embedded user sessions into the sessions collection",
+        apply: move_sessions
+      },
+      Migration {
+        version: 3,
+        description: "Give embedded user settings a stable _id",
+        apply: identify_settings
+      },
+      Migration {
+        version: 4,
+        description: "Create the outbox for domain events",
+        apply: create_outbox
+      },
+      Migration {
+        version: 5,
+        description: "Create webhooks and their deliveries",
+        apply: create_webhooks
+      },
+      Migration {
+        version: 6,
+        description: "Create notifications",
+        apply: create_notifications
+      },
+      Migration {
+        version: 7,
+        description: "Create follows",
+        apply: create_follows
+      },
+      Migration {
+        version: 8,
+        description: "Make emails unique ignoring case",
+        apply: ignore_email_case
+      },
+      Migration {
+        version: 9,
+        description: "Schedule webhook retries",
+        apply: schedule_retries
+      },
+      Migration {
+        version: 10,
+        description: "Remember the records transfers copied",
+        apply: create_transfer_ids
+      }
+    ]
+  }
+
+  pub fn migrate(&self) -> Result<i32, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+
+    self.migrate_db(&client.default_database().unwrap())
+  }
+
+  pub fn version(&self) -> Result<i32, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+
+    self.version_db(&client.default_database().unwrap())
+  }
+
+  // Schema commands (create, collMod, createIndexes) can't run inside
+  // a multi-document transaction, so migrations work on the database directly
+  pub fn migrate_db(&self, db: &Database) -> Result<i32, Box<dyn error::Error>> {
+    let mut version = self.version_db(db)?;
+
+    for migration in self.migrations() {
+      if migration.version <= version {
+        continue;
+      }
+
+      (migration.apply)(db)?;
+
+      db.collection::<Document>("migrations")
+        .insert_one(
+          doc! {
+            "_id": migration.version,
+            "description": migration.description,
+            "applied_at": DateTime::now()
+          },
+          None
+        )?;
+
+      version = migration.version;
+    }
+
+    Ok(version)
+  }
+
+  pub fn version_db(&self, db: &Database) -> Result<i32, Box<dyn error::Error>> {
+    let res = db.collection::<Document>("migrations")
+      .find_one(
+        None,
+        FindOneOptions::builder()
+          .sort(
+            doc! {
+              "_id": -1
+            }
+          )
+          .build()
+      )?;
+
+    Ok(
+      res.and_then(|doc| doc.get_i32("_id").ok())
+        .unwrap_or(0)
+    )
+  }
+}
+
+pub fn ensure_collection(
+  db: &Database,
+  name: &str, validator: Document
+) -> Result<(), Box<dyn error::Error>> {
+  let exists = !db.list_collection_names(
+    doc! {
+      "name": name
+    }
+  )?
+  .is_empty();
+
+  let command = if exists {
+    doc! {
+      "collMod": name,
+      "validator": validator,
+      "validationLevel": "moderate"
+    }
+  } else {
+    doc! {
+      "create": name,
+      "validator": validator,
+      "validationLevel": "moderate"
+    }
+  };
+
+  db.run_command(command, None)?;
+
+  Ok(())
+}
+
+pub fn ensure_index(
+  db: &Database,
+  collection: &str, name: &str,
+  keys: Document, options: IndexOptions
+) -> Result<(), Box<dyn error::Error>> {
+  let mut options = options;
+
+  options.name = Some(name.to_owned());
+
+  db.collection::<Document>(collection)
+    .create_index(
+      IndexModel::builder()
+        .keys(keys)
+        .options(options)
+        .build(),
+      None
+    )?;
+
+  Ok(())
+}
+
+pub fn users_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["first_name", "last_name", "password", "settings"],
+      "properties": doc! {
+        "first_name": doc! { "bsonType": "string" },
+        "last_name": doc! { "bsonType": "string" },
+        "email": doc! { "bsonType": ["string", "null"] },
+        "password": doc! { "bsonType": "string" },
+        "settings": doc! {
+          "bsonType": "object",
+          "required": ["_id", "posts_per_page", "display_email"],
+          "properties": doc! {
+            "_id": doc! { "bsonType": "objectId" },
+            "posts_per_page": doc! { "bsonType": "int", "minimum": 1 },
+            "display_email": doc! { "bsonType": "bool" }
+          }
+        }
+      }
+    }
+  }
+}
+
+pub fn posts_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["title"],
+      "properties": doc! {
+        "user_id": doc! { "bsonType": ["objectId", "null"] },
+        "title": doc! { "bsonType": "string" },
+        "text": doc! { "bsonType": ["string", "null"] },
+        "description": doc! { "bsonType": ["string", "null"] }
+      }
+    }
+  }
+}
+
+pub fn likes_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["user_id", "post_id"],
+      "properties": doc! {
+        "user_id": doc! { "bsonType": "objectId" },
+        "post_id": doc! { "bsonType": "objectId" }
+      }
+    }
+  }
+}
+
+pub fn sessions_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["code", "user_id", "created_at"],
+      "properties": doc! {
+        "code": doc! { "bsonType": "string" },
+        "user_id": doc! { "bsonType": "objectId" },
+        "created_at": doc! { "bsonType": "date" },
+        "user_agent": doc! { "bsonType": ["string", "null"] },
+        "ip": doc! { "bsonType": ["string", "null"] }
+      }
+    }
+  }
+}
+
+pub fn outbox_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["kind", "created_at"],
+      "properties": doc! {
+        "kind": doc! { "bsonType": "string" },
+        "user_id": doc! { "bsonType": "objectId" },
+        "post_id": doc! { "bsonType": "objectId" },
+        "created_at": doc! { "bsonType": "date" },
+        "acknowledged_by": doc! {
+          "bsonType": "array",
+          "items": doc! { "bsonType": "string" }
+        }
+      }
+    }
+  }
+}
+
+pub fn outbox_consumers_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["_id", "acknowledged_through"],
+      "properties": doc! {
+        "_id": doc! { "bsonType": "string" },
+        "acknowledged_through": doc! { "bsonType": "objectId" }
+      }
+    }
+  }
+}
+
+pub fn transfer_ids_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["_id", "target_id"],
+      "properties": doc! {
+        "_id": doc! {
+          "bsonType": "object",
+          "required": ["entity", "source_id"],
+          "properties": doc! {
+            "entity": doc! { "bsonType": "string" },
+            "source_id": doc! { "bsonType": "string" }
+          }
+        },
+        "target_id": doc! { "bsonType": "objectId" }
+      }
+    }
+  }
+}
+
+pub fn webhooks_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["user_id", "url", "secret", "kind"],
+      "properties": doc! {
+        "user_id": doc! { "bsonType": "objectId" },
+        "url": doc! { "bsonType": "string" },
+        "secret": doc! { "bsonType": "string" },
+        "kind": doc! { "bsonType": "string" },
+        "author_id": doc! { "bsonType": "objectId" }
+      }
+    }
+  }
+}
+
+pub fn webhook_deliveries_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["webhook_id", "event_id", "attempt", "status", "payload", "created_at"],
+      "properties": doc! {
+        "webhook_id": doc! { "bsonType": "objectId" },
+        "event_id": doc! { "bsonType": "objectId" },
+        "attempt": doc! { "bsonType": "int", "minimum": 1 },
+        "status": doc! { "enum": ["delivered", "failed", "dead"] },
+        "response_status": doc! { "bsonType": ["int", "null"] },
+        "error": doc! { "bsonType": ["string", "null"] },
+        "payload": doc! { "bsonType": "string" },
+        "created_at": doc! { "bsonType": "date" },
+        "next_attempt_at": doc! { "bsonType": ["date", "null"] }
+      }
+    }
+  }
+}
+
+pub fn notifications_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["user_id", "kind", "actor_id", "post_id", "read", "created_at"],
+      "properties": doc! {
+        "user_id": doc! { "bsonType": "objectId" },
+        "kind": doc! { "bsonType": "string" },
+        "actor_id": doc! { "bsonType": "objectId" },
+        "post_id": doc! { "bsonType": "objectId" },
+        "read": doc! { "bsonType": "bool" },
+        "created_at": doc! { "bsonType": "date" }
+      }
+    }
+  }
+}
+
+pub fn follows_validator() -> Document {
+  doc! {
+    "$jsonSchema": doc! {
+      "bsonType": "object",
+      "required": ["follower_id", "author_id", "created_at"],
+      "properties": doc! {
+        "follower_id": doc! { "bsonType": "objectId" },
+        "author_id": doc! { "bsonType": "objectId" },
+        "created_at": doc! { "bsonType": "date" }
+      }
+    }
+  }
+}
+
+fn create_collections(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "users", users_validator())?;
+  ensure_collection(db, "posts", posts_validator())?;
+  ensure_collection(db, "likes", likes_validator())?;
+
+  ensure_index(
+    db, "users", "email_unique",
+    doc! { "email": 1 },
+    IndexOptions::builder()
+      .unique(true)
+      .partial_filter_expression(
+        doc! {
+          "email": doc! { "$type": "string" }
+        }
+      )
+      .build()
+  )?;
+
+  ensure_index(
+    db, "users", "sessions",
+    doc! { "sessions": 1 },
+    IndexOptions::default()
+  )?;
+
+  ensure_index(
+    db, "posts", "user_id",
+    doc! { "user_id": 1 },
+    IndexOptions::default()
+  )?;
+
+  // Also serves lookups by user_id alone, as its prefix
+  ensure_index(
+    db, "likes", "user_id_post_id_unique",
+    doc! { "user_id": 1, "post_id": 1 },
+    IndexOptions::builder()
+      .unique(true)
+      .build()
+  )?;
+
+  ensure_index(
+    db, "likes", "post_id",
+    doc! { "post_id": 1 },
+    IndexOptions::default()
+  )?;
+
+  Ok(())
+}
+
+fn move_sessions(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "sessions", sessions_validator())?;
+
+  ensure_index(
+    db, "sessions", "code_unique",
+    doc! { "code": 1 },
+    IndexOptions::builder()
+      .unique(true)
+      .build()
+  )?;
+
+  ensure_index(
+    db, "sessions", "user_id",
+    doc! { "user_id": 1 },
+    IndexOptions::default()
+  )?;
+
+  ensure_index(
+    db, "sessions", "created_at_ttl",
+    doc! { "created_at": 1 },
+    IndexOptions::builder()
+      .expire_after(SESSION_TTL)
+      .build()
+  )?;
+
+  let users = db.collection::<Document>("users");
+  let sessions = db.collection::<Document>("sessions");
+
+  let cursor = users.find(
+    doc! {
+      "sessions": doc! { "$exists": true }
+    },
+    FindOptions::builder()
+      .projection(
+        doc! {
+          "sessions": 1
+        }
+      )
+      .build()
+  )?;
+
+  // Embedded codes never had a creation time, so they
+  // start their TTL from the moment they are moved
+  let created_at = DateTime::now();
+
+  for user in cursor {
+    let user = user?;
+
+    let user_id = user.get_object_id("_id")?;
+
+    let codes = user.get_array("sessions")
+      .cloned()
+      .unwrap_or_default();
+
+    for code in codes.iter().filter_map(|code| code.as_str()) {
+      sessions.update_one(
+        doc! {
+          "code": code
+        },
+        doc! {
+          "$setOnInsert": doc! {
+            "code": code,
+            "user_id": user_id,
+            "created_at": created_at,
+            "user_agent": Bson::Null,
+            "ip": Bson::Null
+          }
+        },
+        UpdateOptions::builder()
+          .upsert(true)
+          .build()
+      )?;
+    }
+  }
+
+  users.update_many(
+    doc! {
+      "sessions": doc! { "$exists": true }
+    },
+    doc! {
+      "$unset": doc! {
+        "sessions": ""
+      }
+    },
+    None
+  )?;
+
+  if users.list_index_names()?.contains(&"sessions".to_owned()) {
+    users.drop_index("sessions", None)?;
+  }
+
+  ensure_collection(db, "users", users_validator())
+}
+
+fn identify_settings(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  let users = db.collection::<Document>("users");
+
+  let cursor = users.find(
+    doc! {
+      "settings": doc! { "$exists": true },
+      "settings._id": doc! { "$exists": false }
+    },
+    FindOptions::builder()
+      .projection(
+        doc! {
+          "_id": 1
+        }
+      )
+      .build()
+  )?;
+
+  for user in cursor {
+    let user = user?;
+
+    users.update_one(
+      doc! {
+        "_id": user.get_object_id("_id")?
+      },
+      doc! {
+        "$set": doc! {
+          "settings._id": ObjectId::new()
+        }
+      },
+      None
+    )?;
+  }
+
+  ensure_collection(db, "users", users_validator())
+}
+
+// Polling walks _id from the consumer's cursor, so the outbox needs no index of its own
+fn create_outbox(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "outbox", outbox_validator())?;
+  ensure_collection(db, "outbox_consumers", outbox_consumers_validator())
+}
+
+fn create_webhooks(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "webhooks", webhooks_validator())?;
+  ensure_collection(db, "webhook_deliveries", webhook_deliveries_validator())?;
+
+  ensure_index(
+    db, "webhooks", "user_id",
+    doc! { "user_id": 1 },
+    IndexOptions::default()
+  )?;
+
+  ensure_index(
+    db, "webhooks", "kind_author_id",
+    doc! { "kind": 1, "author_id": 1 },
+    IndexOptions::default()
+  )?;
+
+  // Newest first is how deliveries are always listed
+  ensure_index(
+    db, "webhook_deliveries", "webhook_id",
+    doc! { "webhook_id": 1, "_id": -1 },
+    IndexOptions::default()
+  )
+}
+
+fn create_notifications(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "notifications", notifications_validator())?;
+
+  ensure_index(
+    db, "notifications", "user_id",
+    doc! { "user_id": 1, "_id": -1 },
+    IndexOptions::default()
+  )?;
+
+  ensure_index(
+    db, "notifications", "user_id_unread",
+    doc! { "user_id": 1 },
+    IndexOptions::builder()
+      .partial_filter_expression(
+        doc! {
+          "read": false
+        }
+      )
+      .build()
+  )
+}
+
+fn create_follows(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "follows", follows_validator())?;
+
+  ensure_index(
+    db, "follows", "follower_id_author_id",
+    doc! { "follower_id": 1, "author_id": 1 },
+    IndexOptions::builder()
+      .unique(true)
+      .build()
+  )?;
+
+  ensure_index(
+    db, "follows", "author_id",
+    doc! { "author_id": 1, "created_at": -1 },
+    IndexOptions::default()
+  )
+}
+
+// Fails, naming them, if emails differ only in case. Merge or rename
+// those accounts by hand and run the migration again
+fn ignore_email_case(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  let users = db.collection::<Document>("users");
+
+  let cursor = users.aggregate(
+    [
+      doc! {
+        "$match": doc! {
+          "email": doc! { "$type": "string" }
+        }
+      },
+      doc! {
+        "$group": doc! {
+          "_id": doc! { "$toLower": "$email" },
+          "emails": doc! { "$push": "$email" },
+          "count": doc! { "$sum": 1 }
+        }
+      },
+      doc! {
+        "$match": doc! {
+          "count": doc! { "$gt": 1 }
+        }
+      }
+    ],
+    None
+  )?;
+
+  let mut duplicates = Vec::new();
+
+  for group in cursor {
+    let emails = group?.get_array("emails")?
+      .iter()
+      .filter_map(|email| email.as_str())
+      .collect::<Vec<_>>()
+      .join(", ");
+
+    duplicates.push(emails);
+  }
+
+  if !duplicates.is_empty() {
+    return Err(
+      Box::new(
+        ConflictError::new(&format!("Emails that differ only in case: {}", duplicates.join("; ")))
+      )
+    );
+  }
+
+  // Built before the old index goes, so emails are never left unchecked
+  ensure_index(
+    db, "users", "email_unique_ci",
+    doc! { "email": 1 },
+    IndexOptions::builder()
+      .unique(true)
+      .collation(super::email_collation())
+      .partial_filter_expression(
+        doc! {
+          "email": doc! { "$type": "string" }
+        }
+      )
+      .build()
+  )?;
+
+  if users.list_index_names()?.contains(&"email_unique".to_owned()) {
+    users.drop_index("email_unique", None)?;
+  }
+
+  Ok(())
+}
+
+fn schedule_retries(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "webhook_deliveries", webhook_deliveries_validator())?;
+
+  ensure_index(
+    db, "webhook_deliveries", "due",
+    doc! { "next_attempt_at": 1 },
+    IndexOptions::builder()
+      .partial_filter_expression(
+        doc! {
+          "next_attempt_at": doc! { "$type": "date" }
+        }
+      )
+      .build()
+  )
+}
+
+// Written in the same transaction as the copies, so a resumed
+// transfer finds what an interrupted one already wrote
+fn create_transfer_ids(db: &Database) -> Result<(), Box<dyn error::Error>> {
+  ensure_collection(db, "transfer_ids", transfer_ids_validator())
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+  use mongodb::bson::Document;
+
+  use super::{utils, Migrator};
+
+  #[test]
+  fn test_migrate() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let migrator = Migrator::new();
+
+    let client = utils::connect()?;
+    let db = client.default_database().unwrap();
+
+    let latest = migrator.migrations()
+      .iter()
+      .map(|migration| migration.version)
+      .max()
+      .unwrap_or(0);
+
+    assert_eq!(migrator.migrate_db(&db)?, latest);
+    assert_eq!(migrator.migrate_db(&db)?, latest);
+    assert_eq!(migrator.version_db(&db)?, latest);
+
+    let users_indexes = db.collection::<Document>("users").list_index_names()?;
+
+    assert!(users_indexes.contains(&"email_unique_ci".to_owned()));
+    assert!(!users_indexes.contains(&"email_unique".to_owned()));
+
+    let likes_indexes = db.collection::<Document>("likes").list_index_names()?;
+
+    assert!(likes_indexes.contains(&"user_id_post_id_unique".to_owned()));
+    assert!(likes_indexes.contains(&"post_id".to_owned()));
+
+    let sessions_indexes = db.collection::<Document>("sessions").list_index_names()?;
+
+    assert!(sessions_indexes.contains(&"code_unique".to_owned()));
+    assert!(sessions_indexes.contains(&"created_at_ttl".to_owned()));
+
+    Ok(())
+  }
+}
@@ -1,10 +1,75 @@
+use std::time::Duration;
+
+use mongodb::{
+  error::{ErrorKind, WriteFailure},
+  options::{Collation, CollationStrength}
+};
+
+#[cfg(feature = "sync")]
 mod user;
+#[cfg(feature = "sync")]
 mod session;
+#[cfg(feature = "sync")]
 mod like;
+#[cfg(feature = "sync")]
 mod post;
+#[cfg(feature = "sync")]
+mod outbox;
+#[cfg(feature = "sync")]
+mod webhook;
+#[cfg(feature = "sync")]
+mod notification;
+#[cfg(feature = "sync")]
+mod follow;
+#[cfg(feature = "sync")]
+mod transfer;
+#[cfg(feature = "sync")]
 pub mod utils;
+#[cfg(feature = "sync")]
+pub mod migration;
+pub mod document;
 
+#[cfg(feature = "sync")]
 pub use user::User;
+#[cfg(feature = "sync")]
 pub use session::Session;
+#[cfg(feature = "sync")]
 pub use like::Like;
+#[cfg(feature = "sync")]
 pub use post::Post;
+#[cfg(feature = "sync")]
+pub use outbox::Outbox;
+#[cfg(feature = "sync")]
+pub use webhook::Webhook;
+#[cfg(feature = "sync")]
+pub use notification::Notification;
+#[cfg(feature = "sync")]
+pub use follow::Follow;
+#[cfg(feature = "sync")]
+pub use transfer::Transfer;
+#[cfg(feature = "sync")]
+pub use migration::Migrator;
+pub use document::Decoding;
+
+pub const SESSION_TTL: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+// E11000, what a unique index answers a write that would break it with
+pub fn is_duplicate_key(err: &mongodb::error::Error) -> bool {
+  const DUPLICATE_KEY: i32 = 11000;
+
+  match err.kind.as_ref() {
+    ErrorKind::Write(WriteFailure::WriteError(err)) => err.code == DUPLICATE_KEY,
+    ErrorKind::BulkWrite(err) => err.write_errors.iter().flatten().any(|err| err.code == DUPLICATE_KEY),
+    ErrorKind::Command(err) => err.code == DUPLICATE_KEY,
+    _ => false
+  }
+}
+
+// Emails compare ignoring case, queries by email need it to use the
+// email_unique_ci index
+pub fn email_collation() -> Collation {
+  Collation::builder()
+    .locale("en")
+    .strength(CollationStrength::Secondary)
+    .build()
+}
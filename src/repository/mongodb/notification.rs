@@ -0,0 +1,244 @@
+use std::error;
+
+use mongodb::{bson::{doc, Document}, options::FindOptions, sync::ClientSession};
+
+use crate::repository;
+use crate::models;
+
+use super::document;
+use super::utils;
+
+#[derive(Default)]
+pub struct Notification {
+  pool: utils::Pool
+}
+
+impl repository::Notification for Notification {
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.list_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn mark_read(&self, user_id: &models::UserId, ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.mark_read_ws(user_id, ids, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn count_unread(&self, user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.count_unread_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+}
+
+impl Notification {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn create_ws(
+    &self,
+    notification: &models::Notification,
+    session: &mut ClientSession
+  ) -> Result<models::NotificationId, Box<dyn error::Error>> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("notifications")
+      .insert_one_with_session(
+        document::notification_document(notification)?,
+        None,
+        session
+      )?;
+
+    Ok(models::NotificationId::from(res.inserted_id.as_object_id().unwrap()))
+  }
+
+  pub fn list_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("notifications")
+      .find_with_session(
+        doc! {
+          "user_id": user_id.as_object_id()?
+        },
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "_id": -1
+            }
+          )
+          .build(),
+        session
+      )?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(document::decode_notification(doc?)?);
+    }
+
+    Ok(data)
+  }
+
+  pub fn mark_read_ws(
+    &self,
+    user_id: &models::UserId, ids: &[models::NotificationId],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let ids = ids.iter()
+      .map(|id| id.as_object_id())
+      .collect::<Result<Vec<_>, _>>()?;
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("notifications")
+      .update_many_with_session(
+        doc! {
+          "_id": doc! { "$in": ids },
+          "user_id": user_id.as_object_id()?
+        },
+        doc! {
+          "$set": doc! {
+            "read": true
+          }
+        },
+        None,
+        session
+      )?;
+
+    Ok(())
+  }
+
+  pub fn count_unread_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<u64, Box<dyn error::Error>> {
+    Ok(
+      session.client().default_database().unwrap()
+        .collection::<Document>("notifications")
+        .count_documents_with_session(
+          doc! {
+            "user_id": user_id.as_object_id()?,
+            "read": false
+          },
+          None,
+          session
+        )?
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_notification() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_5__".to_owned(),
+      last_name: "__test_5__".to_owned(),
+      email: Some("__test_5__@5.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_6__".to_owned(),
+      last_name: "__test_6__".to_owned(),
+      email: Some("__test_6__@6.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::mongodb::User::new();
+    let post_repository = repository::mongodb::Post::new();
+    let like_repository = repository::mongodb::Like::new();
+    let notification_repository = repository::mongodb::Notification::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let author_id = user_repository.create_ws(&mut author, &mut session)?;
+    let reader_id = user_repository.create_ws(&mut reader, &mut session)?;
+
+    let post_id = post_repository.create_ws(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "__test_5__".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      },
+      &mut session
+    )?;
+
+    like_repository.create_ws(&reader_id, &post_id, &mut session)?;
+    like_repository.create_ws(&author_id, &post_id, &mut session)?;
+
+    let notifications = notification_repository.list_ws(&author_id, &mut session)?;
+
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].actor_id, reader_id);
+    assert_eq!(notifications[0].post_id, post_id);
+    assert_eq!(notification_repository.count_unread_ws(&author_id, &mut session)?, 1);
+
+    notification_repository.mark_read_ws(&reader_id, &[notifications[0].id], &mut session)?;
+
+    assert_eq!(notification_repository.count_unread_ws(&author_id, &mut session)?, 1);
+
+    notification_repository.mark_read_ws(&author_id, &[notifications[0].id], &mut session)?;
+
+    assert_eq!(notification_repository.count_unread_ws(&author_id, &mut session)?, 0);
+
+    session.abort_transaction()?;
+
+    Ok(())
+  }
+}
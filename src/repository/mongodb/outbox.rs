@@ -0,0 +1,346 @@
+use std::{
+  error,
+  time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use mongodb::{
+  bson::{doc, oid::ObjectId, Document},
+  options::{FindOneOptions, FindOptions, UpdateOptions},
+  sync::ClientSession
+};
+
+use crate::repository;
+use crate::models;
+
+use super::document;
+use super::utils;
+
+// Transactions stay open for up to transactionLifetimeLimitSeconds, 60 by
+// default, and ObjectIds are made before the commit, so a consumer's cursor
+// only passes events older than this. A lower id can't turn up after that
+const SETTLED: Duration = Duration::from_secs(120);
+
+#[derive(Default)]
+pub struct Outbox {
+  pool: utils::Pool
+}
+
+impl repository::Outbox for Outbox {
+  fn poll(&self, consumer: &str, limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.poll_ws(consumer, limit, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn acknowledge(&self, consumer: &str, ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.acknowledge_ws(consumer, ids, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+}
+
+impl Outbox {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn append_ws(
+    &self,
+    event: &models::Event,
+    session: &mut ClientSession
+  ) -> Result<models::EventId, Box<dyn error::Error>> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("outbox")
+      .insert_one_with_session(
+        document::event_document(event)?,
+        None,
+        session
+      )?;
+
+    Ok(models::EventId::from(res.inserted_id.as_object_id().unwrap()))
+  }
+
+  pub fn poll_ws(
+    &self,
+    consumer: &str, limit: usize,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    let mut filter = doc! {
+      "acknowledged_by": doc! { "$ne": consumer }
+    };
+
+    if let Some(acknowledged_through) = self.cursor_ws(consumer, session)? {
+      filter.insert("_id", doc! { "$gt": acknowledged_through });
+    }
+
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("outbox")
+      .find_with_session(
+        filter,
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "_id": 1
+            }
+          )
+          .limit(limit as i64)
+          .build(),
+        session
+      )?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(document::decode_event(doc?)?);
+    }
+
+    Ok(data)
+  }
+
+  pub fn acknowledge_ws(
+    &self,
+    consumer: &str, ids: &[models::EventId],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let ids = ids.iter()
+      .map(|id| id.as_object_id())
+      .collect::<Result<Vec<_>, _>>()?;
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("outbox")
+      .update_many_with_session(
+        doc! {
+          "_id": doc! { "$in": ids }
+        },
+        doc! {
+          "$addToSet": doc! {
+            "acknowledged_by": consumer
+          }
+        },
+        None,
+        session
+      )?;
+
+    let settled = SystemTime::now()
+      .checked_sub(SETTLED)
+      .and_then(|settled| settled.duration_since(UNIX_EPOCH).ok())
+      .map(|settled| settled.as_secs() as u32)
+      .unwrap_or_default();
+
+    let mut before = [0; 12];
+
+    before[..4].copy_from_slice(&settled.to_be_bytes());
+
+    self.advance_ws(consumer, ObjectId::from_bytes(before), session)?;
+
+    Ok(())
+  }
+
+  /// Moves the consumer's cursor past the events below `before` it
+  /// acknowledged without a gap, returns the new cursor
+  pub fn advance_ws(
+    &self,
+    consumer: &str, before: ObjectId,
+    session: &mut ClientSession
+  ) -> Result<Option<ObjectId>, Box<dyn error::Error>> {
+    let acknowledged_through = self.cursor_ws(consumer, session)?;
+
+    let outbox = session.client().default_database().unwrap()
+      .collection::<Document>("outbox");
+
+    let mut filter = doc! {
+      "acknowledged_by": doc! { "$ne": consumer }
+    };
+
+    if let Some(acknowledged_through) = acknowledged_through {
+      filter.insert("_id", doc! { "$gt": acknowledged_through });
+    }
+
+    let pending = outbox.find_one_with_session(
+      filter,
+      FindOneOptions::builder()
+        .sort(
+          doc! {
+            "_id": 1
+          }
+        )
+        .projection(
+          doc! {
+            "_id": 1
+          }
+        )
+        .build(),
+      session
+    )?
+    .map(|doc| doc.get_object_id("_id"))
+    .transpose()?;
+
+    let mut range = doc! {
+      "$lt": pending.map_or(before, |pending| pending.min(before))
+    };
+
+    if let Some(acknowledged_through) = acknowledged_through {
+      range.insert("$gt", acknowledged_through);
+    }
+
+    let last = outbox.find_one_with_session(
+      doc! {
+        "_id": range
+      },
+      FindOneOptions::builder()
+        .sort(
+          doc! {
+            "_id": -1
+          }
+        )
+        .projection(
+          doc! {
+            "_id": 1
+          }
+        )
+        .build(),
+      session
+    )?
+    .map(|doc| doc.get_object_id("_id"))
+    .transpose()?;
+
+    let Some(last) = last else {
+      return Ok(acknowledged_through);
+    };
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("outbox_consumers")
+      .update_one_with_session(
+        doc! {
+          "_id": consumer
+        },
+        doc! {
+          "$set": doc! {
+            "acknowledged_through": last
+          }
+        },
+        UpdateOptions::builder()
+          .upsert(true)
+          .build(),
+        session
+      )?;
+
+    Ok(Some(last))
+  }
+
+  fn cursor_ws(
+    &self,
+    consumer: &str,
+    session: &mut ClientSession
+  ) -> Result<Option<ObjectId>, Box<dyn error::Error>> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("outbox_consumers")
+      .find_one_with_session(
+        doc! {
+          "_id": consumer
+        },
+        None,
+        session
+      )?;
+
+    Ok(res.and_then(|doc| doc.get_object_id("acknowledged_through").ok()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+  use mongodb::bson::oid::ObjectId;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_outbox() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_3__".to_owned(),
+      last_name: "__test_3__".to_owned(),
+      email: Some("__test_3__@3.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::mongodb::User::new();
+    let outbox = repository::mongodb::Outbox::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let user_id = user_repository.create_ws(&mut user, &mut session)?;
+
+    let events = outbox.poll_ws("test", 1000, &mut session)?;
+
+    let registered = events.iter()
+      .find(|event| event.event == models::Event::UserRegistered { user_id })
+      .unwrap();
+
+    outbox.acknowledge_ws("test", &[registered.id], &mut session)?;
+
+    let events = outbox.poll_ws("test", 1000, &mut session)?;
+
+    assert!(events.iter().all(|event| event.id != registered.id));
+
+    // Another consumer still gets it
+    let events = outbox.poll_ws("other", 1000, &mut session)?;
+
+    assert!(events.iter().any(|event| event.id == registered.id));
+
+    loop {
+      let ids = outbox.poll_ws("test", 1000, &mut session)?
+        .iter()
+        .map(|event| event.id)
+        .collect::<Vec<_>>();
+
+      if ids.is_empty() {
+        break;
+      }
+
+      outbox.acknowledge_ws("test", &ids, &mut session)?;
+    }
+
+    // With everything acknowledged the cursor passes it all, so none of it is scanned again
+    let acknowledged_through = outbox.advance_ws("test", ObjectId::new(), &mut session)?;
+
+    assert!(acknowledged_through.is_some_and(|acknowledged_through| acknowledged_through >= registered.id.as_object_id().unwrap()));
+    assert!(outbox.poll_ws("test", 1000, &mut session)?.is_empty());
+
+    session.abort_transaction()?;
+
+    Ok(())
+  }
+}
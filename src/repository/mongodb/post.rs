@@ -1,32 +1,35 @@
 use std::error;
 
-use mongodb::{bson::{doc, oid::ObjectId, Bson, Document}, sync::ClientSession};
+use mongodb::{bson::{doc, oid::ObjectId, Document}, sync::ClientSession};
 
 use crate::repository;
 use crate::models;
 
-use crate::utils::error::StringError;
+use crate::utils::error::NotFoundError;
 
+use super::document::{self, Decoding, PostDocument};
 use super::utils;
 
-pub struct Post {}
+#[derive(Default)]
+pub struct Post {
+  decoding: Decoding,
+  pool: utils::Pool
+}
 
 impl repository::Post for Post {
-  fn create(&self, post: &models::Post) -> Result<String, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
 
     let res = self.create_ws(post, &mut session);
 
-    session.commit_transaction()?;
-
-    res
+    utils::commit_or_abort(&mut session, res)
   }
 
-  fn get(&self, id: &str, user_id: Option<&str>) -> Result<models::Post, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
@@ -38,8 +41,8 @@ impl repository::Post for Post {
     res
   }
 
-  fn list(&self, user_id: Option<&str>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
@@ -51,8 +54,8 @@ impl repository::Post for Post {
     res
   }
 
-  fn liked_list(&self, user_id: &str) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
@@ -63,50 +66,84 @@ impl repository::Post for Post {
 
     res
   }
+
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.feed_ws(user_id, page, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
 }
 
 impl Post {
   pub fn new() -> Self {
-    Self {  }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self {
+      decoding: Decoding::Strict,
+      pool
+    }
+  }
+
+  pub fn with_decoding(decoding: Decoding) -> Self {
+    Self { decoding, pool: utils::Pool::new() }
   }
 
   pub fn create_ws(
     &self, 
     post: &models::Post,
     session: &mut ClientSession
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::PostId, Box<dyn error::Error>> {
     let res = session.client().default_database().unwrap()
-      .collection("posts")
+      .collection::<PostDocument>("posts")
       .insert_one_with_session(
-        doc! {
-          "user_id": post.author.as_ref().and_then(|user| 
-            ObjectId::parse_str(&user.id).ok()
-          ),
-          "title": &post.title,
-          "text": &post.text,
-          "description": &post.description
-        }, 
+        PostDocument::try_from(post)?, 
         None, 
         session
       )?;
 
-    Ok(res.inserted_id.as_object_id().unwrap().to_string())
+    let post_id = models::PostId::from(res.inserted_id.as_object_id().unwrap());
+
+    super::Outbox::new().append_ws(
+      &models::Event::PostPublished {
+        post_id,
+        user_id: post.author.as_ref().map(|author| author.id)
+      },
+      session
+    )?;
+
+    Ok(post_id)
   }
 
   pub fn get_ws(
     &self, 
-    id: &str, user_id: Option<&str>,
+    id: &models::PostId, user_id: Option<&models::UserId>,
     session: &mut ClientSession
   ) -> Result<models::Post, Box<dyn error::Error>> {
+    let id = id.as_object_id()?;
+    let user_id = user_id.map(|user_id| user_id.as_object_id()).transpose()?;
+
     let mut pipeline = vec![
       doc! {
         "$match": doc! {
-          "_id": ObjectId::parse_str(id)?
+          "_id": id
         }
       }
     ];
 
-    pipeline.extend(self.pipeline(&user_id));
+    pipeline.extend(self.pipeline(user_id));
 
     let mut cursor = session.client().default_database().unwrap()
       .collection::<Document>("posts")
@@ -119,40 +156,38 @@ impl Post {
     let mut post = None;
 
     while let Some(doc) = cursor.next(session) {
-      post = Some(self.read(&doc?))
+      post = Some(self.read(doc?)?)
     }
 
-    if post.is_some() {
-      Ok(post.unwrap())
-    } else {
-      Err(
-        Box::new(
-          StringError::new(
-            "Post with this id not found"
-          )
+    post.ok_or(
+      Box::new(
+        NotFoundError::new(
+          "Post with this id not found"
         )
       )
-    }
+    )
   }
 
   pub fn list_ws(
     &self, 
-    user_id: Option<&str>,
+    user_id: Option<&models::UserId>,
     session: &mut ClientSession
   ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let user_id = user_id.map(|user_id| user_id.as_object_id()).transpose()?;
+
     let mut data = Vec::new();
 
     let mut cursor = session.client().default_database().unwrap()
       .collection::<Document>("posts")
       .aggregate_with_session(
-        self.pipeline(&user_id), 
+        self.pipeline(user_id), 
         None,
         session  
       )?;
 
     while let Some(doc) = cursor.next(session) {
       data.push(
-        self.read(&doc?)
+        self.read(doc?)?
       );
     }
 
@@ -161,12 +196,14 @@ impl Post {
 
   pub fn liked_list_ws(
     &self, 
-    user_id: &str,
+    user_id: &models::UserId,
     session: &mut ClientSession
   ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let user_id = user_id.as_object_id()?;
+
     let mut data = Vec::new();
 
-    let mut pipeline = self.pipeline(&Some(user_id));
+    let mut pipeline = self.pipeline(Some(user_id));
 
     pipeline.push(
       doc! {
@@ -186,153 +223,153 @@ impl Post {
 
     while let Some(doc) = cursor.next(session) {
       data.push(
-        self.read(&doc?)
+        self.read(doc?)?
       );
     }
 
     Ok(data)
   }
 
-  pub fn read(&self, doc: &Document) -> models::Post {
-    models::Post {
-      id: doc.get("_id")
-        .unwrap_or(&Bson::ObjectId(ObjectId::new()))
-        .as_object_id()
-        .unwrap_or(ObjectId::new())
-        .to_string(),
-      title: doc.get("title")
-        .unwrap_or(&Bson::String(String::new()))
-        .as_str()
-        .unwrap_or("")
-        .to_owned(),
-      text: doc.get("text")
-        .and_then(|text| text.as_str()
-          .and_then(|text| Some(text.to_owned()))),
-      description: doc.get("description")
-        .and_then(|description: &Bson| description.as_str()
-          .and_then(|description| Some(description.to_owned()))),
-      author: doc.get("author")
-        .and_then(
-          |doc| doc.as_document()
-                        .and_then(|doc| Some(models::User {
-                          id: doc.get("_id")
-                            .unwrap_or(&Bson::ObjectId(ObjectId::new()))
-                            .as_object_id()
-                            .unwrap_or(ObjectId::new())
-                            .to_string(),
-                          first_name: doc.get("first_name")
-                            .unwrap_or(&Bson::String(String::new()))
-                            .as_str()
-                            .unwrap_or("")
-                            .to_owned(),
-                          last_name: doc.get("last_name")
-                            .unwrap_or(&Bson::String(String::new()))
-                            .as_str()
-                            .unwrap_or("")
-                            .to_owned(),
-                          email: None,
-                          password: None,
-                          
-                          settings: models::Settings::new()
-                        }))
-        ),
-      liked: doc.get("liked")
-        .and_then(|doc| doc.as_bool())
-        .unwrap_or(false)        
-    }
-  }
+  // The author's posts, liked as the author sees them
+  pub fn authored_list_ws(
+    &self,
+    author_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let author_id = author_id.as_object_id()?;
 
-  pub fn pipeline(&self, user_id: &Option<&str>) -> Vec<Document> {
-    vec![
+    let mut pipeline = vec![
       doc! {
-        "$lookup": doc! {
-          "from": "users",
-          "localField": "user_id",
-          "foreignField": "_id",
-          "as": "author"
+        "$match": doc! {
+          "user_id": author_id
         }
-      },
+      }
+    ];
+
+    pipeline.extend(self.pipeline(Some(author_id)));
+
+    let mut data = Vec::new();
+
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("posts")
+      .aggregate_with_session(
+        pipeline,
+        None,
+        session
+      )?;
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(
+        self.read(doc?)?
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn feed_ws(
+    &self,
+    user_id: &models::UserId, page: u32,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let user_id = user_id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    let per_page = db.collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "_id": user_id
+        },
+        None,
+        session
+      )?
+      .and_then(|user| {
+        user.get_document("settings").ok()
+          .and_then(|settings| settings.get_i32("posts_per_page").ok())
+      })
+      .unwrap_or(models::Settings::new().posts_per_page)
+      .max(1) as i64;
+
+    let mut author_ids = Vec::new();
+
+    let mut cursor = db.collection::<Document>("follows")
+      .find_with_session(
+        doc! {
+          "follower_id": user_id
+        },
+        None,
+        session
+      )?;
+
+    while let Some(doc) = cursor.next(session) {
+      author_ids.push(doc?.get_object_id("author_id")?);
+    }
+
+    let mut pipeline = vec![
       doc! {
-        "$set": doc! {
-          "author": doc! {
-            "$first": "$author"
-          }
+        "$match": doc! {
+          "user_id": doc! { "$in": author_ids }
         }
       },
       doc! {
-        "$unset": vec![
-          "user_id",
-          "author.sessions",
-          "author.settings",
-          "author.email",
-          "author.password"              
-        ]
-      },
-      doc! {
-        "$lookup": doc! {
-          "from": "likes",
-          "let": doc! {
-            "post_id": "$_id"
-          },
-          "pipeline": vec![
-            doc! {
-              "$match": doc! {
-                "$expr": doc! {
-                  "$and": vec![
-                    doc! {"$eq": vec!["$post_id", "$$post_id"]},
-                    doc! {"$eq": vec![
-                      Bson::String("$user_id".to_owned()), 
-                      Bson::ObjectId(
-                        user_id.and_then(|user_id| ObjectId::parse_str(user_id).ok())
-                          .unwrap_or(ObjectId::new())
-                      )
-                    ]}
-                  ]
-                }
-              }
-            }
-          ],
-          "as": "like"
+        "$sort": doc! {
+          "_id": -1
         }
       },
       doc! {
-        "$set": doc! {
-          "liked": doc! {
-            "$cond": doc! {
-              "if": doc! {"$eq": vec![
-                Bson::Document(
-                  doc! {"$size": "$like"}
-                ),
-                Bson::Int32(0)
-              ]},
-              "then": false,
-              "else": true
-            }
-          }
-        }
+        "$skip": page as i64 * per_page
       },
       doc! {
-        "$unset": vec!["like"]
+        "$limit": per_page
       }
-    ]
+    ];
+
+    pipeline.extend(self.pipeline(Some(user_id)));
+
+    let mut data = Vec::new();
+
+    let mut cursor = db.collection::<Document>("posts")
+      .aggregate_with_session(
+        pipeline,
+        None,
+        session
+      )?;
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(
+        self.read(doc?)?
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn read(&self, doc: Document) -> Result<models::Post, Box<dyn error::Error>> {
+    Ok(document::decode_post(doc, self.decoding)?)
+  }
+
+  pub fn pipeline(&self, user_id: Option<ObjectId>) -> Vec<Document> {
+    document::post_pipeline(user_id)
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use std::error;
+  use std::{error, thread};
 
   use dotenv::dotenv;
 
   use super::utils;
   use crate::{models, repository};
+  use crate::utils::error::{ConflictError, NotFoundError};
 
   #[test]
   fn test_post() -> Result<(), Box<dyn error::Error>> {
     dotenv().ok();
 
     let mut user = models::User {
-      id: String::new(),
+      id: models::UserId::new(),
       first_name: "__test_2__".to_owned(),
       last_name: "__test_2__".to_owned(),
       email: Some("__test_2__@2.again".to_owned()),
@@ -355,7 +392,7 @@ mod tests {
     assert!(!user.id.is_empty());
 
     let post = models::Post {
-      id: String::new(),
+      id: models::PostId::new(),
       title: "__title_1".to_owned(),
       text: Some("__text_1".to_owned()),
       description: Some("__abstract_1".to_owned()),
@@ -392,7 +429,7 @@ mod tests {
 
     let posts = post_repository.list_ws(Some(&user.id), &mut session)?;
 
-    assert!(posts.len() > 0);
+    assert!(!posts.is_empty());
     assert!(posts.iter().find(|p| p.id == post_id).is_some());
 
     let liked_posts = post_repository.liked_list_ws(&user.id, &mut session)?;
@@ -401,13 +438,19 @@ mod tests {
 
     like_repository.create_ws(&user.id, &post_id, &mut session)?;
 
+    // Liking twice is a conflict, not a second like
+    assert!(like_repository.create_ws(&user.id, &post_id, &mut session).unwrap_err().is::<ConflictError>());
+
     let liked_posts_2 = post_repository.liked_list_ws(&user.id, &mut session)?;
 
-    assert!(liked_posts_2.len() > 0);
+    assert!(!liked_posts_2.is_empty());
     assert!(liked_posts_2.iter().find(|p| p.id == post_id && p.liked).is_some());
 
     like_repository.delete_ws(&user.id, &post_id, &mut session)?;
 
+    // So is taking back a like that isn't there
+    assert!(like_repository.delete_ws(&user.id, &post_id, &mut session).unwrap_err().is::<NotFoundError>());
+
     let liked_posts_3 = post_repository.liked_list_ws(
       &user.id, 
       &mut session
@@ -419,4 +462,28 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn test_threads() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    // One pool of connections for all threads
+    let post_repository: Box<dyn repository::Post> = Box::new(
+      repository::mongodb::Post::new()
+    );
+
+    let count = post_repository.list(None)?.len();
+
+    thread::scope(|scope| {
+      for _ in 0..8 {
+        scope.spawn(|| {
+          for _ in 0..20 {
+            assert_eq!(post_repository.list(None).unwrap().len(), count);
+          }
+        });
+      }
+    });
+
+    Ok(())
+  }
 }
@@ -1,23 +1,27 @@
 use std::error;
 
 use mongodb::{
-  bson::{doc, oid::ObjectId, Document}, 
+  bson::{doc, DateTime, Document},
   options::FindOneOptions, sync::ClientSession
 };
 
 use crate::repository;
+use crate::models;
 
-use crate::utils::error::StringError;
+use crate::utils::error::NotFoundError;
 
-use super::utils;
+use super::{utils, SESSION_TTL};
 
-pub struct Session {}
+#[derive(Default)]
+pub struct Session {
+  pool: utils::Pool
+}
 
 impl repository::Session for Session {
-  fn get_user_id(&self, code: &str) -> Result<String, Box<dyn error::Error>> {
-    let client = utils::connect()?;
-    let mut session = client.start_session(None)?;      
-    
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
     session.start_transaction(None)?;
 
     let res = self.get_user_id_ws(code, &mut session);
@@ -27,13 +31,34 @@ impl repository::Session for Session {
     res
   }
 
-  fn create(&self, user_id: &str, code: &str) -> Result<(), Box<dyn error::Error>> {
-    let client = utils::connect()?;
-    let mut session = client.start_session(None)?;      
-    
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    self.create_with_metadata(user_id, code, &models::SessionMetadata::new())
+  }
+
+  fn create_with_metadata(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    metadata: &models::SessionMetadata
+  ) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.create_with_metadata_ws(user_id, code, metadata, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
     session.start_transaction(None)?;
 
-    let res = self.create_ws(user_id, code, &mut session);
+    let res = self.delete_ws(code, &mut session);
 
     session.commit_transaction()?;
 
@@ -43,59 +68,108 @@ impl repository::Session for Session {
 
 impl Session {
   pub fn new() -> Self {
-    Self {  }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn get_user_id_ws(
-    &self, 
-    code: &str, 
+    &self,
+    code: &models::SessionCode,
     session: &mut ClientSession
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
+    // The TTL monitor only runs once a minute, so expired
+    // sessions can still be around for a while
+    let expired_at = DateTime::from_millis(
+      DateTime::now().timestamp_millis() - SESSION_TTL.as_millis() as i64
+    );
+
     let res = session.client().default_database().unwrap()
-      .collection::<Document>("users")
+      .collection::<Document>("sessions")
       .find_one_with_session(
         doc! {
-          "sessions": code
+          "code": code.as_str(),
+          "created_at": doc! {
+            "$gt": expired_at
+          }
         },
         FindOneOptions::builder()
           .projection(
             doc! {
-              "_id": 1
+              "user_id": 1
             }
           )
-          .build(), 
+          .build(),
         session
       )?;
 
     res.and_then(
-      |doc| doc.get("_id")
+      |doc| doc.get("user_id")
                         .and_then(|id| id.as_object_id()
-                          .and_then(|id| Some(id.to_string())))
+                          .map(models::UserId::from))
     )
     .ok_or(
       Box::new(
-        StringError::new("User with this session code doesn't exist")
+        NotFoundError::new("User with this session code doesn't exist")
       )
     )
   }
 
   pub fn create_ws(
     &self,
-    user_id: &str, code: &str,
+    user_id: &models::UserId, code: &models::SessionCode,
     session: &mut ClientSession
   ) -> Result<(), Box<dyn error::Error>> {
+    self.create_with_metadata_ws(
+      user_id, code,
+      &models::SessionMetadata::new(),
+      session
+    )
+  }
+
+  pub fn create_with_metadata_ws(
+    &self,
+    user_id: &models::UserId, code: &models::SessionCode,
+    metadata: &models::SessionMetadata,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let user_id = user_id.as_object_id()?;
+
     session.client().default_database().unwrap()
-      .collection::<Document>("users")
-      .update_one_with_session(
+      .collection::<Document>("sessions")
+      .insert_one_with_session(
         doc! {
-          "_id": ObjectId::parse_str(user_id).unwrap_or(ObjectId::new()) 
-        }, 
+          "code": code.as_str(),
+          "user_id": user_id,
+          "created_at": DateTime::now(),
+          "user_agent": &metadata.user_agent,
+          "ip": &metadata.ip
+        },
+        None,
+        session
+      )?;
+
+    Ok(())
+  }
+
+  pub fn delete_ws(
+    &self,
+    code: &models::SessionCode,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    session.client().default_database().unwrap()
+      .collection::<Document>("sessions")
+      .delete_one_with_session(
         doc! {
-          "$addToSet": doc! {
-            "sessions": code
-          }
-        }, 
-        None, 
+          "code": code.as_str()
+        },
+        None,
         session
       )?;
 
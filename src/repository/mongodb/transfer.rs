@@ -0,0 +1,698 @@
+use std::{collections::{HashMap, HashSet}, error};
+
+use mongodb::{
+  bson::{doc, oid::ObjectId, DateTime, Document},
+  options::FindOptions, sync::ClientSession
+};
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{DecodeError, InvalidIdError};
+
+use super::document::{PostDocument, SettingsDocument, UserDocument};
+use super::utils;
+
+#[derive(Default)]
+pub struct Transfer {
+  pool: utils::Pool
+}
+
+impl repository::Transfer for Transfer {
+  fn count(&self, entity: repository::Entity) -> Result<u64, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.count_ws(entity, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn users(&self) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.users_ws(&mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn sessions(&self) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.sessions_ws(&mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn posts(&self) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.posts_ws(&mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn likes(&self) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.likes_ws(&mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn follows(&self) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.follows_ws(&mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn insert_users(&self, users: &[repository::UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.insert_users_ws(users, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn insert_sessions(&self, sessions: &[repository::SessionRecord]) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.insert_sessions_ws(sessions, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn insert_posts(&self, posts: &[repository::PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.insert_posts_ws(posts, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn insert_likes(&self, likes: &[repository::LikeRecord]) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.insert_likes_ws(likes, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn insert_follows(&self, follows: &[repository::FollowRecord]) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.insert_follows_ws(follows, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+}
+
+impl Transfer {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn count_ws(
+    &self,
+    entity: repository::Entity,
+    session: &mut ClientSession
+  ) -> Result<u64, Box<dyn error::Error>> {
+    // Settings are embedded in the users
+    let (collection, filter) = match entity {
+      repository::Entity::Settings => (
+        "users",
+        doc! {
+          "settings": doc! {
+            "$exists": true
+          }
+        }
+      ),
+      entity => (entity.name(), doc! {})
+    };
+
+    let count = session.client().default_database().unwrap()
+      .collection::<Document>(collection)
+      .count_documents_with_session(filter, None, session)?;
+
+    Ok(count)
+  }
+
+  pub fn users_ws(
+    &self,
+    session: &mut ClientSession
+  ) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<UserDocument>("users")
+      .find_with_session(None, sorted(), session)?;
+
+    let defaults = models::Settings::new();
+
+    let mut data = Vec::new();
+
+    while let Some(user) = cursor.next(session) {
+      let user = user?;
+
+      let id = user.id.ok_or(missing("users", None, "_id"))?;
+
+      let settings = user.settings.unwrap_or(
+        SettingsDocument {
+          id: None,
+          posts_per_page: None,
+          display_email: None
+        }
+      );
+
+      data.push(
+        repository::UserRecord {
+          id: models::UserId::from(id),
+          first_name: user.first_name.ok_or(missing("users", Some(id), "first_name"))?,
+          last_name: user.last_name.ok_or(missing("users", Some(id), "last_name"))?,
+          email: user.email,
+          password_hash: user.password,
+          posts_per_page: settings.posts_per_page.unwrap_or(defaults.posts_per_page),
+          display_email: settings.display_email.unwrap_or(defaults.display_email)
+        }
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn sessions_ws(
+    &self,
+    session: &mut ClientSession
+  ) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("sessions")
+      .find_with_session(None, sorted(), session)?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      let doc = doc?;
+
+      let id = doc.get_object_id("_id").ok();
+
+      let code = doc.get_str("code")
+        .map_err(|_| missing("sessions", id, "code"))?;
+
+      let user_id = doc.get_object_id("user_id")
+        .map_err(|_| missing("sessions", id, "user_id"))?;
+
+      data.push(
+        repository::SessionRecord {
+          user_id: models::UserId::from(user_id),
+          code: models::SessionCode::new(code),
+          user_agent: doc.get_str("user_agent").ok().map(str::to_owned),
+          ip: doc.get_str("ip").ok().map(str::to_owned)
+        }
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn posts_ws(
+    &self,
+    session: &mut ClientSession
+  ) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<PostDocument>("posts")
+      .find_with_session(None, sorted(), session)?;
+
+    let mut data = Vec::new();
+
+    while let Some(post) = cursor.next(session) {
+      let post = post?;
+
+      let id = post.id.ok_or(missing("posts", None, "_id"))?;
+
+      data.push(
+        repository::PostRecord {
+          id: models::PostId::from(id),
+          user_id: post.user_id.map(models::UserId::from),
+          title: post.title.ok_or(missing("posts", Some(id), "title"))?,
+          text: post.text,
+          description: post.description
+        }
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn likes_ws(
+    &self,
+    session: &mut ClientSession
+  ) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("likes")
+      .find_with_session(None, sorted(), session)?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      let doc = doc?;
+
+      let id = doc.get_object_id("_id").ok();
+
+      let user_id = doc.get_object_id("user_id")
+        .map_err(|_| missing("likes", id, "user_id"))?;
+
+      let post_id = doc.get_object_id("post_id")
+        .map_err(|_| missing("likes", id, "post_id"))?;
+
+      data.push(
+        repository::LikeRecord {
+          user_id: models::UserId::from(user_id),
+          post_id: models::PostId::from(post_id)
+        }
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn follows_ws(
+    &self,
+    session: &mut ClientSession
+  ) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("follows")
+      .find_with_session(None, sorted(), session)?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      let doc = doc?;
+
+      let id = doc.get_object_id("_id").ok();
+
+      let follower_id = doc.get_object_id("follower_id")
+        .map_err(|_| missing("follows", id, "follower_id"))?;
+
+      let author_id = doc.get_object_id("author_id")
+        .map_err(|_| missing("follows", id, "author_id"))?;
+
+      data.push(
+        repository::FollowRecord {
+          follower_id: models::UserId::from(follower_id),
+          author_id: models::UserId::from(author_id)
+        }
+      );
+    }
+
+    Ok(data)
+  }
+
+  pub fn insert_users_ws(
+    &self,
+    users: &[repository::UserRecord],
+    session: &mut ClientSession
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let source_ids: Vec<String> = users.iter().map(|user| user.id.to_string()).collect();
+
+    let mut copied = self.copied_ws(repository::Entity::Users, &source_ids, session)?;
+
+    let documents: Vec<UserDocument> = users.iter()
+      .zip(&source_ids)
+      .filter(|(_, source_id)| !copied.contains_key(*source_id))
+      .map(|(user, _)| UserDocument {
+        id: Some(ObjectId::new()),
+        first_name: Some(user.first_name.clone()),
+        last_name: Some(user.last_name.clone()),
+        email: user.email.clone(),
+        // The hash is copied as it is, hashing it again would lock the user out
+        password: user.password_hash.clone(),
+
+        settings: Some(
+          SettingsDocument {
+            id: Some(ObjectId::new()),
+            posts_per_page: Some(user.posts_per_page),
+            display_email: Some(user.display_email)
+          }
+        )
+      })
+      .collect();
+
+    if !documents.is_empty() {
+      session.client().default_database().unwrap()
+        .collection::<UserDocument>("users")
+        .insert_many_with_session(&documents, None, session)?;
+    }
+
+    let inserted: Vec<_> = source_ids.iter()
+      .filter(|source_id| !copied.contains_key(*source_id))
+      .zip(documents.iter().filter_map(|document| document.id))
+      .map(|(source_id, id)| (source_id.clone(), id))
+      .collect();
+
+    self.remember_ws(repository::Entity::Users, &inserted, session)?;
+
+    copied.extend(inserted);
+
+    Ok(
+      source_ids.iter()
+        .map(|source_id| models::UserId::from(copied[source_id]))
+        .collect()
+    )
+  }
+
+  pub fn insert_sessions_ws(
+    &self,
+    sessions: &[repository::SessionRecord],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let session_repository = super::Session::new();
+
+    let codes: Vec<&str> = sessions.iter().map(|record| record.code.as_str()).collect();
+
+    let present = self.present_ws(
+      "sessions",
+      doc! {
+        "code": doc! { "$in": codes }
+      },
+      |doc| Ok(doc.get_str("code")?.to_owned()),
+      session
+    )?;
+
+    // Copied sessions start a new TTL period, Postgres keeps no creation time
+    for record in sessions.iter().filter(|record| !present.contains(record.code.as_str())) {
+      session_repository.create_with_metadata_ws(
+        &record.user_id, &record.code,
+        &models::SessionMetadata {
+          user_agent: record.user_agent.clone(),
+          ip: record.ip.clone()
+        },
+        session
+      )?;
+    }
+
+    Ok(())
+  }
+
+  pub fn insert_posts_ws(
+    &self,
+    posts: &[repository::PostRecord],
+    session: &mut ClientSession
+  ) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    let source_ids: Vec<String> = posts.iter().map(|post| post.id.to_string()).collect();
+
+    let mut copied = self.copied_ws(repository::Entity::Posts, &source_ids, session)?;
+
+    let mut documents = Vec::with_capacity(posts.len());
+
+    for (post, _) in posts.iter().zip(&source_ids).filter(|(_, source_id)| !copied.contains_key(*source_id)) {
+      documents.push(
+        PostDocument {
+          id: Some(ObjectId::new()),
+          user_id: post.user_id.map(|user_id| user_id.as_object_id()).transpose()?,
+          title: Some(post.title.clone()),
+          text: post.text.clone(),
+          description: post.description.clone(),
+
+          author: None,
+          liked: None
+        }
+      );
+    }
+
+    if !documents.is_empty() {
+      session.client().default_database().unwrap()
+        .collection::<PostDocument>("posts")
+        .insert_many_with_session(&documents, None, session)?;
+    }
+
+    let inserted: Vec<_> = source_ids.iter()
+      .filter(|source_id| !copied.contains_key(*source_id))
+      .zip(documents.iter().filter_map(|document| document.id))
+      .map(|(source_id, id)| (source_id.clone(), id))
+      .collect();
+
+    self.remember_ws(repository::Entity::Posts, &inserted, session)?;
+
+    copied.extend(inserted);
+
+    Ok(
+      source_ids.iter()
+        .map(|source_id| models::PostId::from(copied[source_id]))
+        .collect()
+    )
+  }
+
+  pub fn insert_likes_ws(
+    &self,
+    likes: &[repository::LikeRecord],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if likes.is_empty() {
+      return Ok(());
+    }
+
+    let pairs = likes.iter()
+      .map(|like| Ok((like.user_id.as_object_id()?, like.post_id.as_object_id()?)))
+      .collect::<Result<Vec<_>, InvalidIdError>>()?;
+
+    let present = self.present_ws(
+      "likes",
+      doc! {
+        "user_id": doc! { "$in": pairs.iter().map(|(user_id, _)| *user_id).collect::<Vec<_>>() },
+        "post_id": doc! { "$in": pairs.iter().map(|(_, post_id)| *post_id).collect::<Vec<_>>() }
+      },
+      |doc| Ok((doc.get_object_id("user_id")?, doc.get_object_id("post_id")?)),
+      session
+    )?;
+
+    // Copies aren't new likes, so no events are written for them
+    let documents: Vec<Document> = pairs.iter()
+      .filter(|pair| !present.contains(*pair))
+      .map(|(user_id, post_id)| doc! {
+        "user_id": user_id,
+        "post_id": post_id
+      })
+      .collect();
+
+    if documents.is_empty() {
+      return Ok(());
+    }
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("likes")
+      .insert_many_with_session(&documents, None, session)?;
+
+    Ok(())
+  }
+
+  pub fn insert_follows_ws(
+    &self,
+    follows: &[repository::FollowRecord],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if follows.is_empty() {
+      return Ok(());
+    }
+
+    let pairs = follows.iter()
+      .map(|follow| Ok((follow.follower_id.as_object_id()?, follow.author_id.as_object_id()?)))
+      .collect::<Result<Vec<_>, InvalidIdError>>()?;
+
+    let present = self.present_ws(
+      "follows",
+      doc! {
+        "follower_id": doc! { "$in": pairs.iter().map(|(follower_id, _)| *follower_id).collect::<Vec<_>>() },
+        "author_id": doc! { "$in": pairs.iter().map(|(_, author_id)| *author_id).collect::<Vec<_>>() }
+      },
+      |doc| Ok((doc.get_object_id("follower_id")?, doc.get_object_id("author_id")?)),
+      session
+    )?;
+
+    let documents: Vec<Document> = pairs.iter()
+      .filter(|pair| !present.contains(*pair))
+      .map(|(follower_id, author_id)| doc! {
+        "follower_id": follower_id,
+        "author_id": author_id,
+        "created_at": DateTime::now()
+      })
+      .collect();
+
+    if documents.is_empty() {
+      return Ok(());
+    }
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("follows")
+      .insert_many_with_session(&documents, None, session)?;
+
+    Ok(())
+  }
+}
+
+impl Transfer {
+  // The documents earlier transfers copied the source records to
+  fn copied_ws(
+    &self,
+    entity: repository::Entity, source_ids: &[String],
+    session: &mut ClientSession
+  ) -> Result<HashMap<String, ObjectId>, Box<dyn error::Error>> {
+    let keys: Vec<Document> = source_ids.iter()
+      .map(|source_id| doc! {
+        "entity": entity.name(),
+        "source_id": source_id
+      })
+      .collect();
+
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("transfer_ids")
+      .find_with_session(
+        doc! {
+          "_id": doc! { "$in": keys }
+        },
+        None,
+        session
+      )?;
+
+    let mut copied = HashMap::new();
+
+    while let Some(doc) = cursor.next(session) {
+      let doc = doc?;
+
+      copied.insert(
+        doc.get_document("_id")?.get_str("source_id")?.to_owned(),
+        doc.get_object_id("target_id")?
+      );
+    }
+
+    Ok(copied)
+  }
+
+  fn remember_ws(
+    &self,
+    entity: repository::Entity, copied: &[(String, ObjectId)],
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if copied.is_empty() {
+      return Ok(());
+    }
+
+    let documents: Vec<Document> = copied.iter()
+      .map(|(source_id, target_id)| doc! {
+        "_id": doc! {
+          "entity": entity.name(),
+          "source_id": source_id
+        },
+        "target_id": target_id
+      })
+      .collect();
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("transfer_ids")
+      .insert_many_with_session(&documents, None, session)?;
+
+    Ok(())
+  }
+
+  // Keys of the documents matching the filter, to skip what is already there
+  fn present_ws<K, F>(
+    &self,
+    collection: &str, filter: Document, key: F,
+    session: &mut ClientSession
+  ) -> Result<HashSet<K>, Box<dyn error::Error>>
+  where
+    K: Eq + std::hash::Hash,
+    F: Fn(&Document) -> Result<K, Box<dyn error::Error>>
+  {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>(collection)
+      .find_with_session(filter, None, session)?;
+
+    let mut present = HashSet::new();
+
+    while let Some(doc) = cursor.next(session) {
+      present.insert(key(&doc?)?);
+    }
+
+    Ok(present)
+  }
+}
+
+fn sorted() -> FindOptions {
+  FindOptions::builder()
+    .sort(
+      doc! {
+        "_id": 1
+      }
+    )
+    .build()
+}
+
+fn missing(collection: &str, id: Option<ObjectId>, field: &str) -> Box<dyn error::Error> {
+  let id = id.map(|id| id.to_string());
+
+  Box::new(
+    DecodeError::new(collection, id.as_deref(), field, "is missing")
+  )
+}
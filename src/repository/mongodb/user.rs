@@ -1,35 +1,38 @@
 use std::error;
 
 use mongodb::{
-  bson::{doc, oid::ObjectId, Bson, Document}, 
+  bson::{doc, oid::ObjectId, DateTime, Document}, 
   options::FindOneOptions, sync::ClientSession
 };
 
 use crate::repository;
 use crate::models;
 
-use crate::utils::error::StringError;
+use crate::utils::error::{ConflictError, NotFoundError, ValidationError};
 
-use super::utils;
+use super::document::{self, Decoding, UserDocument};
+use super::{utils, SESSION_TTL};
 
-pub struct User {}
+#[derive(Default)]
+pub struct User {
+  decoding: Decoding,
+  pool: utils::Pool
+}
 
 impl repository::User for User {
-  fn create(&self, user: &mut models::User) -> Result<String, Box<dyn error::Error>> {
-      let client = utils::connect()?;
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+      let client = self.pool.get()?;
       let mut session = client.start_session(None)?;      
       
       session.start_transaction(None)?;
 
       let res = self.create_ws(user, &mut session);
 
-      session.commit_transaction()?;
-
-      res
+      utils::commit_or_abort(&mut session, res)
   }
 
-  fn get_id(&self, email: &str, password: &str) -> Result<String, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
@@ -41,8 +44,8 @@ impl repository::User for User {
     res
   }
 
-  fn get_user_settings(&self, id: &str) -> Result<models::User, Box<dyn error::Error>> {
-    let client = utils::connect()?;
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
@@ -55,13 +58,68 @@ impl repository::User for User {
   }
 
   fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
-    let client = utils::connect()?;
+    let client = self.pool.get()?;
     let mut session = client.start_session(None)?;      
     
     session.start_transaction(None)?;
 
     let res = self.edit_ws(settings, &mut session);
 
+    utils::commit_or_abort(&mut session, res)
+  }
+
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.edit_profile_ws(id, first_name, last_name, &mut session);
+
+    utils::commit_or_abort(&mut session, res)
+  }
+
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.change_email_ws(id, email, &mut session);
+
+    utils::commit_or_abort(&mut session, res)
+  }
+
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.change_password_ws(id, old_password, new_password, &mut session);
+
+    utils::commit_or_abort(&mut session, res)
+  }
+
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.delete_ws(id, &mut session);
+
+    utils::commit_or_abort(&mut session, res)
+  }
+
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.export_personal_data_ws(id, &mut session);
+
     session.commit_transaction()?;
 
     res
@@ -70,55 +128,119 @@ impl repository::User for User {
 
 impl User {
   pub fn new() -> Self {
-    Self {  }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self {
+      decoding: Decoding::Strict,
+      pool
+    }
+  }
+
+  pub fn with_decoding(decoding: Decoding) -> Self {
+    Self { decoding, pool: utils::Pool::new() }
   }
 
   pub fn create_ws(
     &self, 
     user: &mut models::User, 
     session: &mut ClientSession
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
     if let Some(password) = user.password.as_ref() {
+      if let Some(email) = user.email.as_deref() {
+        self.check_email_ws(email, None, session)?;
+      }
+
       let password = format!("{:x}", md5::compute(password));
 
+      let settings_id = ObjectId::new();
+
+      let mut document = UserDocument::try_from(&*user)?;
+
+      document.password = Some(password);
+
+      if let Some(settings) = document.settings.as_mut() {
+        settings.id = Some(settings_id);
+      }
+
       let res = session.client().default_database().unwrap()
-        .collection("users")
+        .collection::<UserDocument>("users")
         .insert_one_with_session(
-          doc! {
-            "first_name": &user.first_name,
-            "last_name": &user.last_name,
-            "email": &user.email,
-            "password": &password,
-
-            "settings": doc! {
-              "posts_per_page": user.settings.posts_per_page,
-              "display_email": user.settings.display_email
-            },
-
-            "sessions": Vec::<String>::new()
-          },
+          &document,
           None,
           session
-        )?;
+        )
+        .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
 
-      user.id = res.inserted_id.as_object_id().unwrap().to_string();
-      user.settings.user_id = user.id.clone();
+      user.id = models::UserId::from(res.inserted_id.as_object_id().unwrap());
+      user.settings.id = settings_id.to_string();
+      user.settings.user_id = user.id;
+
+      super::Outbox::new().append_ws(
+        &models::Event::UserRegistered { user_id: user.id },
+        session
+      )?;
         
-      Ok(user.id.clone())
+      Ok(user.id)
     } else {
       Err(
         Box::new(
-          StringError::new("Password should be non-empty")
+          ValidationError::new("Password should be non-empty")
         )
       )
     }
   }
 
+  // The email_unique_ci index enforces this too, and the writes turn its
+  // error into the same ConflictError when a concurrent one got there first
+  pub fn check_email_ws(
+    &self,
+    email: &str, except: Option<&models::UserId>,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let except = except.map(|id| id.as_object_id()).transpose()?;
+
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "email": email
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "_id": 1
+            }
+          )
+          .collation(super::email_collation())
+          .build(),
+        session
+      )?;
+
+    let taken = res.and_then(|doc| doc.get_object_id("_id").ok())
+      .is_some_and(|id| Some(id) != except);
+
+    if taken {
+      return Err(
+        Box::new(
+          ConflictError::new("User with this email already exists")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
   pub fn get_id_ws(
     &self, 
     email: &str, password: &str,
     session: &mut ClientSession
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
     let password = format!("{:x}", md5::compute(password));
 
     let res = session.client().default_database().unwrap()
@@ -134,6 +256,7 @@ impl User {
               "_id": 1
             }
           )
+          .collation(super::email_collation())
           .build(), 
         session
       )?;
@@ -141,25 +264,27 @@ impl User {
     res.and_then(
       |doc| doc.get("_id")
                         .and_then(|id| id.as_object_id()
-                          .and_then(|id| Some(id.to_string())))
+                          .map(models::UserId::from))
     )
     .ok_or(
       Box::new(
-        StringError::new("User with this email and password doesn't exist")
+        NotFoundError::new("User with this email and password doesn't exist")
       )
     )
   }
 
   pub fn get_user_settings_ws(
     &self,
-    id: &str,
+    id: &models::UserId,
     session: &mut ClientSession
   ) -> Result<models::User, Box<dyn error::Error>> {
+    let id = id.as_object_id()?;
+
     let res = session.client().default_database().unwrap()
       .collection::<Document>("users")
       .find_one_with_session(
         doc! {
-          "_id": ObjectId::parse_str(id).unwrap_or(ObjectId::new()) 
+          "_id": id
         },
         FindOneOptions::builder()
           .projection(
@@ -180,12 +305,14 @@ impl User {
         session
       )?;
 
-    res.and_then(|doc| Some(self.read(&doc)))
-      .ok_or(
+    match res {
+      Some(doc) => self.read(doc),
+      None => Err(
         Box::new(
-          StringError::new("User with this id doesn't exist")
+          NotFoundError::new("User with this id doesn't exist")
         )
       )
+    }
   }
 
   pub fn edit_ws(
@@ -193,70 +320,318 @@ impl User {
     settings: &models::Settings,
     session: &mut ClientSession
   ) -> Result<(), Box<dyn error::Error>> {
-    session.client().default_database().unwrap()
+    let user_id = settings.user_id.as_object_id()?;
+
+    let res = session.client().default_database().unwrap()
       .collection::<Document>("users")
       .update_one_with_session(
         doc! {
-          "_id": ObjectId::parse_str(&settings.user_id).unwrap_or(ObjectId::new()) 
+          "_id": user_id
         },         
         doc! {
           "$set": doc! {
-            "settings": doc! {
-              "posts_per_page": settings.posts_per_page,
-              "display_email": settings.display_email
-            }
+            "settings.posts_per_page": settings.posts_per_page,
+            "settings.display_email": settings.display_email
           }
         }, 
         None,
         session
       )?;
 
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    super::Outbox::new().append_ws(
+      &models::Event::SettingsChanged { user_id: settings.user_id },
+      session
+    )?;
+
     Ok(())
   }
 
-  pub fn read(&self, doc: &Document) -> models::User {
-    let user_id = doc.get("_id")
-      .unwrap_or(&Bson::ObjectId(ObjectId::new()))
-      .as_object_id()
-      .unwrap_or(ObjectId::new())
-      .to_string();
-
-    models::User {
-      id: user_id.clone(),
-      first_name: doc.get("first_name")
-        .unwrap_or(&Bson::String(String::new()))
-        .as_str()
-        .unwrap_or("")
-        .to_owned(),
-      last_name: doc.get("last_name")
-        .unwrap_or(&Bson::String(String::new()))
-        .as_str()
-        .unwrap_or("")
-        .to_owned(),
-      email: doc.get("email")
-        .and_then(|email| email.as_str()
-                                  .and_then(|email| Some(email.to_owned()))),
-      password: None,
-
-      settings: doc.get("settings")
-        .and_then(
-          |doc| doc.as_document()
-                        .and_then(|doc| Some(models::Settings {
-                          id: String::new(),
-                          user_id: user_id,
-                          display_email: doc.get("display_email")
-                            .unwrap_or(&Bson::Boolean(false))
-                            .as_bool()
-                            .unwrap_or(false),
-                          posts_per_page: doc.get("posts_per_page")
-                            .unwrap_or(&Bson::Int32(0))
-                            .as_i32()
-                            .unwrap_or(0)
-                        }))
+  pub fn edit_profile_ws(
+    &self,
+    id: &models::UserId, first_name: &str, last_name: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    self.update_ws(
+      id,
+      doc! {
+        "first_name": first_name,
+        "last_name": last_name
+      },
+      session
+    )
+  }
+
+  pub fn change_email_ws(
+    &self,
+    id: &models::UserId, email: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if email.trim().is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Email should be non-empty")
+        )
+      );
+    }
+
+    self.check_email_ws(email, Some(id), session)?;
+
+    self.update_ws(
+      id,
+      doc! {
+        "email": email
+      },
+      session
+    )
+    .map_err(|err| utils::conflict(err, "User with this email already exists"))
+  }
+
+  pub fn change_password_ws(
+    &self,
+    id: &models::UserId, old_password: &str, new_password: &str,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    if new_password.is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
         )
-        .unwrap_or(models::Settings::new())
+      );
+    }
+
+    let user_id = id.as_object_id()?;
+    let old_password = format!("{:x}", md5::compute(old_password));
+    let new_password = format!("{:x}", md5::compute(new_password));
+
+    let db = session.client().default_database().unwrap();
+
+    let res = db.collection::<Document>("users")
+      .update_one_with_session(
+        doc! {
+          "_id": user_id,
+          "password": old_password
+        },
+        doc! {
+          "$set": doc! {
+            "password": new_password
+          }
+        },
+        None,
+        session
+      )?;
 
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id and password doesn't exist")
+        )
+      );
     }
+
+    db.collection::<Document>("sessions")
+      .delete_many_with_session(
+        doc! {
+          "user_id": user_id
+        },
+        None,
+        session
+      )?;
+
+    Ok(())
+  }
+
+  // Nothing cascades in MongoDB, so everything referencing the user goes here
+  pub fn delete_ws(
+    &self,
+    id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let user_id = id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    let res = db.collection::<Document>("users")
+      .delete_one_with_session(
+        doc! {
+          "_id": user_id
+        },
+        None,
+        session
+      )?;
+
+    if res.deleted_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    let mut webhook_ids = Vec::new();
+
+    let mut cursor = db.collection::<Document>("webhooks")
+      .find_with_session(
+        doc! {
+          "$or": [
+            doc! { "user_id": user_id },
+            doc! { "author_id": user_id }
+          ]
+        },
+        None,
+        session
+      )?;
+
+    while let Some(doc) = cursor.next(session) {
+      webhook_ids.push(doc?.get_object_id("_id")?);
+    }
+
+    let deletes = [
+      ("sessions", doc! { "user_id": user_id }),
+      ("likes", doc! { "user_id": user_id }),
+      ("follows", doc! { "$or": [doc! { "follower_id": user_id }, doc! { "author_id": user_id }] }),
+      ("notifications", doc! { "$or": [doc! { "user_id": user_id }, doc! { "actor_id": user_id }] }),
+      ("webhooks", doc! { "_id": doc! { "$in": webhook_ids.clone() } }),
+      ("webhook_deliveries", doc! { "webhook_id": doc! { "$in": webhook_ids } })
+    ];
+
+    for (collection, filter) in deletes {
+      db.collection::<Document>(collection)
+        .delete_many_with_session(filter, None, session)?;
+    }
+
+    db.collection::<Document>("posts")
+      .update_many_with_session(
+        doc! {
+          "user_id": user_id
+        },
+        doc! {
+          "$unset": doc! {
+            "user_id": ""
+          }
+        },
+        None,
+        session
+      )?;
+
+    super::Outbox::new().append_ws(
+      &models::Event::UserDeleted { user_id: *id },
+      session
+    )?;
+
+    Ok(())
+  }
+
+  pub fn export_personal_data_ws(
+    &self,
+    id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    let user_id = id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    let user = db.collection::<Document>("users")
+      .find_one_with_session(
+        doc! {
+          "_id": user_id
+        },
+        FindOneOptions::builder()
+          .projection(
+            doc! {
+              "password": 0
+            }
+          )
+          .build(),
+        session
+      )?
+      .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
+
+    let user = self.read(user)?;
+
+    // Expired sessions wait for the ttl index, they aren't active anymore
+    let expired_at = DateTime::from_millis(
+      DateTime::now().timestamp_millis() - SESSION_TTL.as_millis() as i64
+    );
+
+    let mut sessions = Vec::new();
+
+    let mut cursor = db.collection::<Document>("sessions")
+      .find_with_session(
+        doc! {
+          "user_id": user_id,
+          "created_at": doc! {
+            "$gt": expired_at
+          }
+        },
+        None,
+        session
+      )?;
+
+    while let Some(doc) = cursor.next(session) {
+      let doc = doc?;
+
+      sessions.push(
+        models::SessionExport::new(
+          &models::SessionCode::new(doc.get_str("code")?),
+          models::SessionMetadata {
+            user_agent: doc.get_str("user_agent").ok().map(str::to_owned),
+            ip: doc.get_str("ip").ok().map(str::to_owned)
+          }
+        )
+      );
+    }
+
+    let post_repository = super::Post::with_decoding(self.decoding);
+
+    Ok(
+      models::PersonalData {
+        user,
+        sessions,
+        posts: post_repository.authored_list_ws(id, session)?,
+        liked: post_repository.liked_list_ws(id, session)?
+      }
+    )
+  }
+
+  fn update_ws(
+    &self,
+    id: &models::UserId, set: Document,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("users")
+      .update_one_with_session(
+        doc! {
+          "_id": id.as_object_id()?
+        },
+        doc! {
+          "$set": set
+        },
+        None,
+        session
+      )?;
+
+    if res.matched_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub fn read(&self, doc: Document) -> Result<models::User, Box<dyn error::Error>> {
+    Ok(document::decode_user(doc, self.decoding)?)
   }
 }
 
@@ -265,16 +640,18 @@ mod tests {
   use std::error;
 
   use dotenv::dotenv;
+  use mongodb::bson::{doc, oid::ObjectId, Document};
 
   use super::utils;
-  use crate::{models, repository};
+  use crate::{models, repository::{self, User}};
+  use crate::utils::error::{ConflictError, InvalidIdError, NotFoundError, StringError};
 
   #[test]
   fn test_user() -> Result<(), Box<dyn error::Error>> {
     dotenv().ok();
 
     let mut user = models::User {
-      id: String::new(),
+      id: models::UserId::new(),
       first_name: "__test_1__".to_owned(),
       last_name: "__test_1__".to_owned(),
       email: Some("__test_1__@1.again".to_owned()),
@@ -294,6 +671,7 @@ mod tests {
     user_repository.create_ws(&mut user, &mut session)?;
 
     assert!(!user.id.is_empty());
+    assert!(!user.settings.id.is_empty());
     assert!(!user.settings.user_id.is_empty());
 
     let user_id = user_repository.get_id_ws(
@@ -335,22 +713,206 @@ mod tests {
     assert_eq!(user_settings_2.settings.display_email, user_settings.settings.display_email);
     assert_eq!(user_settings_2.settings.posts_per_page, user_settings.settings.posts_per_page);
 
-    let code = uuid::Uuid::new_v4().to_string();
+    let err = user_repository.get_user_settings_ws(&models::UserId::from(1), &mut session)
+      .unwrap_err();
+
+    assert!(err.is::<InvalidIdError>());
+
+    let mut missing_settings = models::Settings::new();
+
+    missing_settings.user_id = models::UserId::from(ObjectId::new());
+
+    assert!(user_repository.edit_ws(&missing_settings, &mut session).is_err());
+
+    let code = models::SessionCode::generate();
 
     session_repository.create_ws(
-      &user_id, code.as_str(), 
+      &user_id, &code, 
       &mut session
     )?;    
 
     let user_id = session_repository.get_user_id_ws(
-      code.as_str(), 
+      &code, 
       &mut session
     )?;
 
     assert_eq!(user_id, user.id);    
 
+    let code = models::SessionCode::generate();
+
+    session_repository.create_with_metadata_ws(
+      &user_id, &code,
+      &models::SessionMetadata {
+        user_agent: Some("__test_agent__".to_owned()),
+        ip: Some("127.0.0.1".to_owned())
+      },
+      &mut session
+    )?;
+
+    let user_id = session_repository.get_user_id_ws(
+      &code,
+      &mut session
+    )?;
+
+    assert_eq!(user_id, user.id);
+
+    session_repository.delete_ws(&code, &mut session)?;
+
+    assert!(session_repository.get_user_id_ws(&code, &mut session).is_err());
+
     session.abort_transaction()?;
 
     Ok(())
   }
+
+  #[test]
+  fn test_account() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_9__".to_owned(),
+      last_name: "__test_9__".to_owned(),
+      email: Some("__test_9__@9.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_10__".to_owned(),
+      last_name: "__test_10__".to_owned(),
+      email: Some("__test_10__@10.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::mongodb::User::new();
+    let session_repository = repository::mongodb::Session::new();
+    let post_repository = repository::mongodb::Post::new();
+    let like_repository = repository::mongodb::Like::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let author_id = user_repository.create_ws(&mut author, &mut session)?;
+    let reader_id = user_repository.create_ws(&mut reader, &mut session)?;
+
+    user_repository.edit_profile_ws(&author_id, "__test_9_renamed__", "__test_9__", &mut session)?;
+    user_repository.change_email_ws(&author_id, "__test_9_renamed__@9.again", &mut session)?;
+
+    assert_eq!(user_repository.get_user_settings_ws(&author_id, &mut session)?.first_name, "__test_9_renamed__");
+    assert_eq!(user_repository.get_id_ws("__test_9_renamed__@9.again", "test", &mut session)?, author_id);
+    assert_eq!(user_repository.get_id_ws("__TEST_9_RENAMED__@9.again", "test", &mut session)?, author_id);
+    assert!(
+      user_repository.change_email_ws(&reader_id, "__Test_9_Renamed__@9.again", &mut session)
+        .unwrap_err()
+        .is::<ConflictError>()
+    );
+
+    let mut duplicate = reader.clone();
+
+    duplicate.email = Some("__TEST_10__@10.again".to_owned());
+    duplicate.password = Some("test".to_owned());
+
+    assert!(user_repository.create_ws(&mut duplicate, &mut session).unwrap_err().is::<ConflictError>());
+
+    let code = models::SessionCode::generate();
+
+    session_repository.create_ws(&author_id, &code, &mut session)?;
+
+    assert!(user_repository.change_password_ws(&author_id, "wrong", "changed", &mut session).is_err());
+    assert_eq!(session_repository.get_user_id_ws(&code, &mut session)?, author_id);
+
+    user_repository.change_password_ws(&author_id, "test", "changed", &mut session)?;
+
+    assert_eq!(user_repository.get_id_ws("__test_9_renamed__@9.again", "changed", &mut session)?, author_id);
+    assert!(session_repository.get_user_id_ws(&code, &mut session).is_err());
+
+    let post_id = post_repository.create_ws(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "__test_9__".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      },
+      &mut session
+    )?;
+
+    like_repository.create_ws(&reader_id, &post_id, &mut session)?;
+    session_repository.create_ws(&reader_id, &code, &mut session)?;
+
+    let personal_data = user_repository.export_personal_data_ws(&author_id, &mut session)?;
+
+    assert_eq!(personal_data.user.email.as_deref(), Some("__test_9_renamed__@9.again"));
+    assert!(personal_data.sessions.is_empty());
+    assert_eq!(personal_data.posts[0].id, post_id);
+    assert!(personal_data.liked.is_empty());
+
+    let personal_data = user_repository.export_personal_data_ws(&reader_id, &mut session)?;
+
+    assert_eq!(personal_data.sessions.len(), 1);
+    assert_ne!(personal_data.sessions[0].code_hash, code.as_str());
+    assert!(personal_data.posts.is_empty());
+    assert_eq!(personal_data.liked[0].id, post_id);
+
+    user_repository.delete_ws(&author_id, &mut session)?;
+
+    assert!(user_repository.get_user_settings_ws(&author_id, &mut session).is_err());
+    assert!(user_repository.delete_ws(&author_id, &mut session).is_err());
+
+    let post = post_repository.get_ws(&post_id, Some(&reader_id), &mut session)?;
+
+    assert!(post.author.is_none());
+    assert!(post.liked);
+
+    session.abort_transaction()?;
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_rollback() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_11__".to_owned(),
+      last_name: "__test_11__".to_owned(),
+      email: Some("__test_11__@11.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::mongodb::User::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let user_id = user_repository.create_ws(&mut user, &mut session)?;
+
+    // Whatever fails after the insert takes the user and its event with it
+    let res: Result<(), Box<dyn error::Error>> = Err(Box::new(StringError::new("Failed after the insert")));
+
+    assert!(utils::commit_or_abort(&mut session, res).is_err());
+
+    assert!(user_repository.get_id("__test_11__@11.again", "test").unwrap_err().is::<NotFoundError>());
+
+    let events = connection.default_database().unwrap()
+      .collection::<Document>("outbox")
+      .count_documents(doc! { "user_id": user_id.as_object_id()? }, None)?;
+
+    assert_eq!(events, 0);
+
+    Ok(())
+  }
 }
@@ -1,17 +1,97 @@
-use std::{error, env};
+use std::{error, env, sync::{Arc, Mutex}};
 
-use mongodb::{sync::Client, options::ClientOptions};
+use mongodb::{sync::{Client, ClientSession}, options::ClientOptions};
+
+use crate::utils::error::ConflictError;
 
 pub fn connect() -> Result<Client, Box<dyn error::Error>> {
+  connect_with(None)
+}
+
+// A url keeps the database in its path, MONGODB_DB is used when it has none
+pub fn connect_with(url: Option<&str>) -> Result<Client, Box<dyn error::Error>> {
   let mut client_options = ClientOptions::parse(
-    env::var("MONGODB_CS")?
+    match url {
+      Some(url) => url.to_owned(),
+      None => env::var("MONGODB_CS")?
+    }
   )?;
 
-  client_options.default_database = Some(
-    env::var("MONGODB_DB")?
-  );
+  if url.is_none() || client_options.default_database.is_none() {
+    client_options.default_database = Some(
+      env::var("MONGODB_DB")?
+    );
+  }
 
   Ok(
     Client::with_options(client_options)?
   )
 }
+
+#[derive(Default)]
+struct Shared {
+  url: Option<String>,
+  client: Mutex<Option<Client>>
+}
+
+// The driver pools connections inside a client, so clones of a pool
+// share one client, created on first use
+#[derive(Clone, Default)]
+pub struct Pool(Arc<Shared>);
+
+impl Pool {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self(
+      Arc::new(
+        Shared {
+          url: Some(url.to_owned()),
+          client: Mutex::new(None)
+        }
+      )
+    )
+  }
+
+  pub fn get(&self) -> Result<Client, Box<dyn error::Error>> {
+    let mut client = self.0.client.lock().unwrap_or_else(|err| err.into_inner());
+
+    if client.is_none() {
+      *client = Some(connect_with(self.0.url.as_deref())?);
+    }
+
+    Ok(client.as_ref().unwrap().clone())
+  }
+}
+
+// Commits when the call succeeded and aborts otherwise, so a failed
+// call keeps none of its writes, its outbox event included
+pub fn commit_or_abort<T>(
+  session: &mut ClientSession,
+  res: Result<T, Box<dyn error::Error>>
+) -> Result<T, Box<dyn error::Error>> {
+  match res {
+    Ok(value) => {
+      session.commit_transaction()?;
+
+      Ok(value)
+    },
+    Err(err) => {
+      // The call's error says more than a failed abort would
+      session.abort_transaction().ok();
+
+      Err(err)
+    }
+  }
+}
+
+// Turns a duplicate key error into a ConflictError with the message. A check
+// that ran first can miss a concurrent write the index still rejects
+pub fn conflict(err: Box<dyn error::Error>, message: &str) -> Box<dyn error::Error> {
+  match err.downcast_ref::<mongodb::error::Error>() {
+    Some(db_err) if super::is_duplicate_key(db_err) => Box::new(ConflictError::new(message)),
+    _ => err
+  }
+}
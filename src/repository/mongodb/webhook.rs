@@ -0,0 +1,549 @@
+use std::{error, time::SystemTime};
+
+use mongodb::{bson::{doc, Bson, DateTime, Document}, options::FindOptions, sync::ClientSession};
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::NotFoundError;
+
+use super::document;
+use super::utils;
+
+#[derive(Default)]
+pub struct Webhook {
+  pool: utils::Pool
+}
+
+impl repository::Webhook for Webhook {
+  fn create(&self, webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.create_ws(webhook, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.list_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn delete(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.delete_ws(id, user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn subscribers(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.subscribers_ws(topic, owner, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn record(&self, delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.record_ws(delivery, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn due(
+    &self,
+    now: SystemTime, limit: usize
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.due_ws(now, limit, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn deliveries(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.deliveries_ws(id, user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+
+  fn dead_letters(&self, user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let client = self.pool.get()?;
+    let mut session = client.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let res = self.dead_letters_ws(user_id, &mut session);
+
+    session.commit_transaction()?;
+
+    res
+  }
+}
+
+impl Webhook {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn create_ws(
+    &self,
+    webhook: &models::Webhook,
+    session: &mut ClientSession
+  ) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    let res = session.client().default_database().unwrap()
+      .collection::<Document>("webhooks")
+      .insert_one_with_session(
+        document::webhook_document(webhook)?,
+        None,
+        session
+      )?;
+
+    Ok(models::WebhookId::from(res.inserted_id.as_object_id().unwrap()))
+  }
+
+  pub fn list_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    self.find_ws(
+      doc! {
+        "user_id": user_id.as_object_id()?
+      },
+      session
+    )
+  }
+
+  pub fn delete_ws(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<(), Box<dyn error::Error>> {
+    let id = id.as_object_id()?;
+
+    let db = session.client().default_database().unwrap();
+
+    let res = db.collection::<Document>("webhooks")
+      .delete_one_with_session(
+        doc! {
+          "_id": id,
+          "user_id": user_id.as_object_id()?
+        },
+        None,
+        session
+      )?;
+
+    if res.deleted_count == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("Webhook with this id not found")
+        )
+      );
+    }
+
+    db.collection::<Document>("webhook_deliveries")
+      .delete_many_with_session(
+        doc! {
+          "webhook_id": id
+        },
+        None,
+        session
+      )?;
+
+    Ok(())
+  }
+
+  pub fn subscribers_ws(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let mut filter = doc! {
+      "kind": topic.kind()
+    };
+
+    if let Some(author_id) = topic.author_id() {
+      filter.insert("author_id", author_id.as_object_id()?);
+    }
+
+    if let Some(owner) = owner {
+      filter.insert("user_id", owner.as_object_id()?);
+    }
+
+    self.find_ws(filter, session)
+  }
+
+  pub fn record_ws(
+    &self,
+    delivery: &models::Delivery,
+    session: &mut ClientSession
+  ) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    let deliveries = session.client().default_database().unwrap()
+      .collection::<Document>("webhook_deliveries");
+
+    let res = deliveries.insert_one_with_session(
+      document::delivery_document(delivery)?,
+      None,
+      session
+    )?;
+
+    let delivery_id = res.inserted_id.as_object_id().unwrap();
+
+    deliveries.update_many_with_session(
+      doc! {
+        "webhook_id": delivery.webhook_id.as_object_id()?,
+        "event_id": delivery.event_id.as_object_id()?,
+        "_id": doc! { "$ne": delivery_id },
+        "next_attempt_at": doc! { "$type": "date" }
+      },
+      doc! {
+        "$set": doc! {
+          "next_attempt_at": Bson::Null
+        }
+      },
+      None,
+      session
+    )?;
+
+    Ok(models::DeliveryId::from(delivery_id))
+  }
+
+  pub fn due_ws(
+    &self,
+    now: SystemTime, limit: usize,
+    session: &mut ClientSession
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("webhook_deliveries")
+      .find_with_session(
+        doc! {
+          "next_attempt_at": doc! { "$lte": DateTime::from_system_time(now) }
+        },
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "next_attempt_at": 1
+            }
+          )
+          .limit(limit as i64)
+          .build(),
+        session
+      )?;
+
+    let mut deliveries = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      deliveries.push(document::decode_delivery(doc?)?);
+    }
+
+    let webhook_ids = deliveries.iter()
+      .map(|delivery| delivery.webhook_id.as_object_id())
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let webhooks = self.find_ws(
+      doc! {
+        "_id": doc! { "$in": webhook_ids }
+      },
+      session
+    )?;
+
+    Ok(
+      deliveries.into_iter()
+        .filter_map(|delivery| {
+          webhooks.iter()
+            .find(|webhook| webhook.id == delivery.webhook_id)
+            .map(|webhook| (webhook.clone(), delivery))
+        })
+        .collect()
+    )
+  }
+
+  pub fn deliveries_ws(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let id = id.as_object_id()?;
+
+    session.client().default_database().unwrap()
+      .collection::<Document>("webhooks")
+      .find_one_with_session(
+        doc! {
+          "_id": id,
+          "user_id": user_id.as_object_id()?
+        },
+        None,
+        session
+      )?
+      .ok_or(NotFoundError::new("Webhook with this id not found"))?;
+
+    self.find_deliveries_ws(
+      doc! {
+        "webhook_id": id
+      },
+      session
+    )
+  }
+
+  pub fn dead_letters_ws(
+    &self,
+    user_id: &models::UserId,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let webhook_ids = self.list_ws(user_id, session)?
+      .iter()
+      .map(|webhook| webhook.id.as_object_id())
+      .collect::<Result<Vec<_>, _>>()?;
+
+    self.find_deliveries_ws(
+      doc! {
+        "webhook_id": doc! { "$in": webhook_ids },
+        "status": models::DeliveryStatus::Dead.as_str()
+      },
+      session
+    )
+  }
+
+  fn find_ws(
+    &self,
+    filter: Document,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("webhooks")
+      .find_with_session(
+        filter,
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "_id": 1
+            }
+          )
+          .build(),
+        session
+      )?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(document::decode_webhook(doc?)?);
+    }
+
+    Ok(data)
+  }
+
+  fn find_deliveries_ws(
+    &self,
+    filter: Document,
+    session: &mut ClientSession
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let mut cursor = session.client().default_database().unwrap()
+      .collection::<Document>("webhook_deliveries")
+      .find_with_session(
+        filter,
+        FindOptions::builder()
+          .sort(
+            doc! {
+              "_id": -1
+            }
+          )
+          .build(),
+        session
+      )?;
+
+    let mut data = Vec::new();
+
+    while let Some(doc) = cursor.next(session) {
+      data.push(document::decode_delivery(doc?)?);
+    }
+
+    Ok(data)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, time::SystemTime};
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_webhook() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_4__".to_owned(),
+      last_name: "__test_4__".to_owned(),
+      email: Some("__test_4__@4.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::mongodb::User::new();
+    let webhook_repository = repository::mongodb::Webhook::new();
+
+    let connection = utils::connect()?;
+    let mut session = connection.start_session(None)?;
+
+    session.start_transaction(None)?;
+
+    let user_id = user_repository.create_ws(&mut user, &mut session)?;
+
+    let webhook_id = webhook_repository.create_ws(
+      &models::Webhook {
+        id: models::WebhookId::new(),
+        user_id,
+        url: "http://127.0.0.1/hook".to_owned(),
+        secret: "secret".to_owned(),
+        topic: models::Topic::PostPublished { author_id: user_id }
+      },
+      &mut session
+    )?;
+
+    let webhooks = webhook_repository.list_ws(&user_id, &mut session)?;
+
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].id, webhook_id);
+    assert_eq!(webhooks[0].topic, models::Topic::PostPublished { author_id: user_id });
+
+    let subscribers = webhook_repository.subscribers_ws(
+      &models::Topic::PostPublished { author_id: user_id }, None,
+      &mut session
+    )?;
+
+    assert!(subscribers.iter().any(|webhook| webhook.id == webhook_id));
+
+    let event_id = repository::mongodb::Outbox::new().append_ws(
+      &models::Event::UserRegistered { user_id },
+      &mut session
+    )?;
+
+    webhook_repository.record_ws(
+      &models::Delivery {
+        id: models::DeliveryId::new(),
+        webhook_id,
+        event_id,
+        attempt: 1,
+        status: models::DeliveryStatus::Dead,
+        response_status: None,
+        error: Some("connection refused".to_owned()),
+        payload: "{}".to_owned(),
+        created_at: SystemTime::now(),
+        next_attempt_at: None
+      },
+      &mut session
+    )?;
+
+    let deliveries = webhook_repository.deliveries_ws(&webhook_id, &user_id, &mut session)?;
+
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].error.as_deref(), Some("connection refused"));
+
+    assert_eq!(webhook_repository.dead_letters_ws(&user_id, &mut session)?.len(), 1);
+
+    let failed = models::Delivery {
+      id: models::DeliveryId::new(),
+      webhook_id,
+      event_id,
+      attempt: 1,
+      status: models::DeliveryStatus::Failed,
+      response_status: Some(503),
+      error: None,
+      payload: "{}".to_owned(),
+      created_at: SystemTime::now(),
+      next_attempt_at: Some(SystemTime::now())
+    };
+
+    let failed_id = webhook_repository.record_ws(&failed, &mut session)?;
+
+    let due = webhook_repository.due_ws(SystemTime::now(), 1000, &mut session)?;
+
+    assert!(due.iter().any(|(webhook, delivery)| webhook.id == webhook_id && delivery.id == failed_id));
+
+    // Recording the retry settles the failed attempt
+    webhook_repository.record_ws(
+      &models::Delivery {
+        attempt: 2,
+        status: models::DeliveryStatus::Delivered,
+        next_attempt_at: None,
+        ..failed
+      },
+      &mut session
+    )?;
+
+    let due = webhook_repository.due_ws(SystemTime::now(), 1000, &mut session)?;
+
+    assert!(due.iter().all(|(_, delivery)| delivery.id != failed_id));
+
+    webhook_repository.delete_ws(&webhook_id, &user_id, &mut session)?;
+
+    assert!(webhook_repository.list_ws(&user_id, &mut session)?.is_empty());
+
+    session.abort_transaction()?;
+
+    Ok(())
+  }
+}
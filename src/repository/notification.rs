@@ -0,0 +1,29 @@
+use std::error;
+
+use crate::models;
+
+/// What happened to a user's posts, written along with the change
+pub trait Notification: Send + Sync {
+  /// The user's notifications, newest first
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>>;
+
+  /// Marks the user's notifications read, other ids are ignored
+  fn mark_read(&self, user_id: &models::UserId, ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>>;
+
+  /// How many of the user's notifications are unread
+  fn count_unread(&self, user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>>;
+}
+
+impl<T: Notification + ?Sized> Notification for Box<T> {
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    (**self).list(user_id)
+  }
+
+  fn mark_read(&self, user_id: &models::UserId, ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>> {
+    (**self).mark_read(user_id, ids)
+  }
+
+  fn count_unread(&self, user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>> {
+    (**self).count_unread(user_id)
+  }
+}
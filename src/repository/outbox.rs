@@ -0,0 +1,26 @@
+use std::error;
+
+use crate::models;
+
+/// Events written in the same transaction as the changes they describe.
+/// Every consumer, named by a string of its choice, acknowledges events
+/// on its own, and delivery is at least once: whatever a consumer hasn't
+/// acknowledged is polled again by it
+pub trait Outbox: Send + Sync {
+  /// Up to `limit` events the consumer hasn't acknowledged, oldest first
+  fn poll(&self, consumer: &str, limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>>;
+
+  /// Marks the events as handled by the consumer, the others still
+  /// get them. Unknown ids are ignored
+  fn acknowledge(&self, consumer: &str, ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>>;
+}
+
+impl<T: Outbox + ?Sized> Outbox for Box<T> {
+  fn poll(&self, consumer: &str, limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    (**self).poll(consumer, limit)
+  }
+
+  fn acknowledge(&self, consumer: &str, ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>> {
+    (**self).acknowledge(consumer, ids)
+  }
+}
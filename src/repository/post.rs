@@ -2,12 +2,43 @@ use std::error;
 
 use crate::models;
 
-pub trait Post {
-  fn create(&self, post: &models::Post) -> Result<String, Box<dyn error::Error>>;
+/// Posts, read as seen by a user: `liked` says whether they liked it
+pub trait Post: Send + Sync {
+  /// Stores the post by its author and returns the new id
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>>;
 
-  fn get(&self, id: &str, user_id: Option<&str>) -> Result<models::Post, Box<dyn error::Error>>;
+  /// A single post, `NotFoundError` if there is none with this id
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>>;
 
-  fn list(&self, user_id: Option<&str>) -> Result<Vec<models::Post>, Box<dyn error::Error>>;
+  /// All posts
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>>;
 
-  fn liked_list(&self, user_id: &str) -> Result<Vec<models::Post>, Box<dyn error::Error>>;
+  /// The posts the user liked
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>>;
+
+  /// Posts by the authors the user follows, newest first, `posts_per_page`
+  /// of the user's settings at a time. Pages count from 0
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>>;
+}
+
+impl<T: Post + ?Sized> Post for Box<T> {
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+    (**self).create(post)
+  }
+
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+    (**self).get(id, user_id)
+  }
+
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    (**self).list(user_id)
+  }
+
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    (**self).liked_list(user_id)
+  }
+
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    (**self).feed(user_id, page)
+  }
 }
@@ -0,0 +1,282 @@
+use std::error;
+
+use postgres;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::ValidationError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Follow {
+  pool: utils::Pool
+}
+
+impl repository::Follow for Follow {
+  fn follow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.follow_wt(follower_id, author_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn unfollow(&self, follower_id: &models::UserId, author_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.unfollow_wt(follower_id, author_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn followers(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.followers_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn following(&self, user_id: &models::UserId) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.following_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn counts(&self, user_id: &models::UserId) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.counts_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+}
+
+impl Follow {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn follow_wt(
+    &self,
+    follower_id: &models::UserId, author_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    if follower_id == author_id {
+      return Err(Box::new(ValidationError::new("Users can't follow themselves")));
+    }
+
+    transaction.execute(
+      "
+        insert into follows(follower_id, author_id)
+        values ($1, $2)
+        on conflict do nothing;
+      ",
+      &[&follower_id.as_i32()?, &author_id.as_i32()?]
+    )?;
+
+    Ok(())
+  }
+
+  pub fn unfollow_wt(
+    &self,
+    follower_id: &models::UserId, author_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    transaction.execute(
+      "delete from follows where follower_id = $1 and author_id = $2;",
+      &[&follower_id.as_i32()?, &author_id.as_i32()?]
+    )?;
+
+    Ok(())
+  }
+
+  pub fn followers_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select follower_id from follows where author_id = $1 order by created_at desc;",
+      &[&user_id.as_i32()?]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| models::UserId::from(row.get::<_, i32>(0)))
+        .collect()
+    )
+  }
+
+  pub fn following_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select author_id from follows where follower_id = $1 order by created_at desc;",
+      &[&user_id.as_i32()?]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| models::UserId::from(row.get::<_, i32>(0)))
+        .collect()
+    )
+  }
+
+  pub fn counts_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::FollowCounts, Box<dyn error::Error>> {
+    let row = transaction.query_one(
+      "
+        select
+          (select count(*) from follows where author_id = $1) followers,
+          (select count(*) from follows where follower_id = $1) following;
+      ",
+      &[&user_id.as_i32()?]
+    )?;
+
+    let followers: i64 = row.get("followers");
+    let following: i64 = row.get("following");
+
+    Ok(
+      models::FollowCounts {
+        followers: followers as u64,
+        following: following as u64
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_follow() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_7__".to_owned(),
+      last_name: "__test_7__".to_owned(),
+      email: Some("__test_7__@7.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_8__".to_owned(),
+      last_name: "__test_8__".to_owned(),
+      email: Some("__test_8__@8.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    reader.settings.posts_per_page = 1;
+
+    let user_repository = repository::postgresql::User::new();
+    let post_repository = repository::postgresql::Post::new();
+    let follow_repository = repository::postgresql::Follow::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let author_id = user_repository.create_wt(&mut author, &mut transaction)?;
+    let reader_id = user_repository.create_wt(&mut reader, &mut transaction)?;
+
+    let mut post_ids = Vec::new();
+
+    for title in ["__test_7_1__", "__test_7_2__"] {
+      post_ids.push(
+        post_repository.create_wt(
+          &models::Post {
+            id: models::PostId::new(),
+            title: title.to_owned(),
+            text: None,
+            description: None,
+            liked: false,
+            author: Some(author.clone())
+          },
+          &mut transaction
+        )?
+      );
+    }
+
+    assert!(post_repository.feed_wt(&reader_id, 0, &mut transaction)?.is_empty());
+
+    follow_repository.follow_wt(&reader_id, &author_id, &mut transaction)?;
+    follow_repository.follow_wt(&reader_id, &author_id, &mut transaction)?;
+
+    assert!(follow_repository.follow_wt(&reader_id, &reader_id, &mut transaction).is_err());
+
+    assert_eq!(follow_repository.followers_wt(&author_id, &mut transaction)?, vec![reader_id]);
+    assert_eq!(follow_repository.following_wt(&reader_id, &mut transaction)?, vec![author_id]);
+    assert_eq!(
+      follow_repository.counts_wt(&author_id, &mut transaction)?,
+      models::FollowCounts { followers: 1, following: 0 }
+    );
+
+    let feed = post_repository.feed_wt(&reader_id, 0, &mut transaction)?;
+
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, post_ids[1]);
+    assert_eq!(feed[0].author.as_ref().unwrap().id, author_id);
+
+    let feed = post_repository.feed_wt(&reader_id, 1, &mut transaction)?;
+
+    assert_eq!(feed.len(), 1);
+    assert_eq!(feed[0].id, post_ids[0]);
+
+    follow_repository.unfollow_wt(&reader_id, &author_id, &mut transaction)?;
+
+    assert!(post_repository.feed_wt(&reader_id, 0, &mut transaction)?.is_empty());
+
+    transaction.rollback()?;
+
+    Ok(())
+  }
+}
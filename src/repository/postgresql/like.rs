@@ -3,53 +3,92 @@ use std::error;
 use postgres;
 
 use crate::repository;
+use crate::models;
+
+use crate::utils::error::{ConflictError, NotFoundError};
 
 use super::utils;
 
-pub struct Like {}
+#[derive(Default)]
+pub struct Like {
+  pool: utils::Pool
+}
 
 impl repository::Like for Like {
-  fn create(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn create(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
     let res = self.create_wt(user_id, post_id, &mut transaction);
 
-    transaction.commit()?;
-
-    res 
+    utils::commit_or_rollback(transaction, res)
   }
 
-  fn delete(&self, user_id: &str, post_id: &str) -> Result<(), Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn delete(&self, user_id: &models::UserId, post_id: &models::PostId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
     let res = self.delete_wt(user_id, post_id, &mut transaction);
 
-    transaction.commit()?;
-
-    res
+    utils::commit_or_rollback(transaction, res)
   }
 }
 
 impl Like {
   pub fn new() -> Self {
-    Self {}
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn create_wt(
     &self, 
-    user_id: &str, post_id: &str,
+    user_id: &models::UserId, post_id: &models::PostId,
     transaction: &mut postgres::Transaction
   ) -> Result<(), Box<dyn error::Error>> {
+    // Looked up first, so a missing post fails before anything is written
+    let author_id = super::Post::new().get_wt(post_id, None, transaction)?
+      .author
+      .map(|author| author.id)
+      .filter(|author_id| author_id != user_id);
+
+    let liked: bool = transaction.query_one(
+      "select exists (select from likes where user_id = $1 and post_id = $2);",
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    )?
+    .get(0);
+
+    if liked {
+      return Err(Box::new(ConflictError::new("Post is already liked by this user")));
+    }
+
     transaction.execute(
       "
         insert into likes(user_id, post_id) 
         values ($1, $2);
       ", 
-      &[&user_id.parse::<i32>()?, &post_id.parse::<i32>()?]
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    )
+    .map_err(|err| utils::conflict(err.into(), "Post is already liked by this user"))?;
+
+    if let Some(author_id) = author_id {
+      super::Notification::new().create_wt(
+        &models::Notification::post_liked(author_id, *user_id, *post_id),
+        transaction
+      )?;
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostLiked { user_id: *user_id, post_id: *post_id },
+      transaction
     )?;
 
     Ok(())
@@ -57,10 +96,10 @@ impl Like {
 
   pub fn delete_wt(
     &self, 
-    user_id: &str, post_id: &str,
+    user_id: &models::UserId, post_id: &models::PostId,
     transaction: &mut postgres::Transaction
   ) -> Result<(), Box<dyn error::Error>> {
-    transaction.execute(
+    let res = transaction.execute(
       "
         delete from 
           likes 
@@ -68,7 +107,16 @@ impl Like {
           user_id = $1
           and post_id = $2;
       ", 
-      &[&user_id.parse::<i32>()?, &post_id.parse::<i32>()?]
+      &[&user_id.as_i32()?, &post_id.as_i32()?]
+    )?;
+
+    if res == 0 {
+      return Err(Box::new(NotFoundError::new("Post is not liked by this user")));
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostUnliked { user_id: *user_id, post_id: *post_id },
+      transaction
     )?;
 
     Ok(())
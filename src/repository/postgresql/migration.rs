@@ -0,0 +1,278 @@
+use std::error;
+
+use postgres::Client;
+
+use super::utils;
+
+// The base schema (users, settings, sessions, posts, likes) predates
+// these migrations and is still managed outside the crate
+pub struct Migration {
+  pub version: i32,
+  pub description: &'static str,
+  pub sql: &'static str
+}
+
+#[derive(Default)]
+pub struct Migrator {
+  pool: utils::Pool
+}
+
+impl Migrator {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn migrations(&self) -> Vec<Migration> {
+    vec![
+      Migration {
+        version: 1,
+        description: "Create the outbox for domain events",
+        sql: "
+          create table outbox (
+            id serial primary key,
+            kind text not null,
+            user_id integer,
+            post_id integer,
+            created_at timestamptz not null default now()
+          );
+
+          -- Each consumer acknowledges events on its own
+          create table outbox_acknowledgements (
+            consumer text not null,
+            event_id integer not null references outbox(id) on delete cascade,
+            acknowledged_at timestamptz not null default now(),
+            primary key (consumer, event_id)
+          );
+
+          -- Everything up to acknowledged_through is acknowledged by the
+          -- consumer, polling starts after it and the acknowledgements below are dropped
+          create table outbox_consumers (
+            consumer text primary key,
+            acknowledged_through integer not null default 0
+          );
+        "
+      },
+      Migration {
+        version: 2,
+        description: "Create webhooks and their deliveries",
+        sql: "
+          create table webhooks (
+            id serial primary key,
+            user_id integer not null references users(id) on delete cascade,
+            url text not null,
+            secret text not null,
+            kind text not null,
+            author_id integer references users(id) on delete cascade
+          );
+
+          create index webhooks_user_id on webhooks (user_id);
+          create index webhooks_kind_author_id on webhooks (kind, author_id);
+
+          create table webhook_deliveries (
+            id serial primary key,
+            webhook_id integer not null references webhooks(id) on delete cascade,
+            event_id integer not null,
+            attempt integer not null,
+            status text not null,
+            response_status integer,
+            error text,
+            payload text not null,
+            created_at timestamptz not null default now()
+          );
+
+          create index webhook_deliveries_webhook_id on webhook_deliveries (webhook_id, id);
+          create index webhook_deliveries_dead on webhook_deliveries (webhook_id) where status = 'dead';
+        "
+      },
+      Migration {
+        version: 3,
+        description: "Create notifications",
+        sql: "
+          create table notifications (
+            id serial primary key,
+            user_id integer not null references users(id) on delete cascade,
+            kind text not null,
+            actor_id integer not null references users(id) on delete cascade,
+            post_id integer not null references posts(id) on delete cascade,
+            read_at timestamptz,
+            created_at timestamptz not null default now()
+          );
+
+          create index notifications_user_id on notifications (user_id, id);
+          create index notifications_unread on notifications (user_id) where read_at is null;
+        "
+      },
+      Migration {
+        version: 4,
+        description: "Create follows",
+        sql: "
+          create table follows (
+            follower_id integer not null references users(id) on delete cascade,
+            author_id integer not null references users(id) on delete cascade,
+            created_at timestamptz not null default now(),
+            primary key (follower_id, author_id),
+            check (follower_id <> author_id)
+          );
+
+          create index follows_author_id on follows (author_id);
+          create index if not exists posts_user_id_id on posts (user_id, id desc);
+        "
+      },
+      // Fails, naming them, if emails differ only in case. Merge or
+      // rename those accounts by hand and run the migration again
+      Migration {
+        version: 5,
+        description: "Make emails unique ignoring case",
+        sql: "
+          do $$
+          declare
+            duplicates text;
+          begin
+            select
+              string_agg(emails, '; ')
+            into
+              duplicates
+            from (
+              select
+                string_agg(email, ', ' order by id) emails
+              from
+                users
+              where
+                email is not null
+              group by
+                lower(email)
+              having
+                count(*) > 1
+            ) d;
+
+            if duplicates is not null then
+              raise exception 'Emails that differ only in case: %', duplicates;
+            end if;
+          end
+          $$;
+
+          create unique index users_email_lower on users (lower(email));
+        "
+      },
+      Migration {
+        version: 6,
+        description: "Schedule webhook retries",
+        sql: "
+          alter table webhook_deliveries add column next_attempt_at timestamptz;
+
+          create index webhook_deliveries_due on webhook_deliveries (next_attempt_at)
+            where next_attempt_at is not null;
+        "
+      },
+      Migration {
+        version: 7,
+        description: "Remember the records transfers copied",
+        sql: "
+          -- Written in the same transaction as the copies, so a resumed
+          -- transfer finds what an interrupted one already wrote
+          create table transfer_ids (
+            entity text not null,
+            source_id text not null,
+            target_id integer not null,
+            primary key (entity, source_id)
+          );
+        "
+      }
+    ]
+  }
+
+  pub fn migrate(&self) -> Result<i32, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    self.migrate_client(&mut connection)
+  }
+
+  pub fn version(&self) -> Result<i32, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    self.version_client(&mut connection)
+  }
+
+  // Each migration is applied in its own transaction together with its
+  // record, so a failed one leaves the schema at the previous version
+  pub fn migrate_client(&self, client: &mut Client) -> Result<i32, Box<dyn error::Error>> {
+    let mut version = self.version_client(client)?;
+
+    for migration in self.migrations() {
+      if migration.version <= version {
+        continue;
+      }
+
+      let mut transaction = client.transaction()?;
+
+      transaction.batch_execute(migration.sql)?;
+      transaction.execute(
+        "
+          insert into schema_migrations(version, description)
+          values ($1, $2);
+        ",
+        &[&migration.version, &migration.description]
+      )?;
+
+      transaction.commit()?;
+
+      version = migration.version;
+    }
+
+    Ok(version)
+  }
+
+  pub fn version_client(&self, client: &mut Client) -> Result<i32, Box<dyn error::Error>> {
+    client.batch_execute(
+      "
+        create table if not exists schema_migrations (
+          version integer primary key,
+          description text not null,
+          applied_at timestamptz not null default now()
+        );
+      "
+    )?;
+
+    let row = client.query_one("select coalesce(max(version), 0) from schema_migrations;", &[])?;
+
+    Ok(row.get(0))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::{utils, Migrator};
+
+  #[test]
+  fn test_migrate() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let migrator = Migrator::new();
+
+    let mut client = utils::connect()?;
+
+    let latest = migrator.migrations()
+      .iter()
+      .map(|migration| migration.version)
+      .max()
+      .unwrap_or(0);
+
+    assert_eq!(migrator.migrate_client(&mut client)?, latest);
+    assert_eq!(migrator.migrate_client(&mut client)?, latest);
+    assert_eq!(migrator.version_client(&mut client)?, latest);
+
+    Ok(())
+  }
+}
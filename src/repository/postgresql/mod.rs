@@ -2,9 +2,21 @@ mod user;
 mod session;
 mod post;
 mod like;
-mod utils;
+mod outbox;
+mod webhook;
+mod notification;
+mod follow;
+mod transfer;
+pub mod utils;
+pub mod migration;
 
 pub use user::User;
 pub use session::Session;
 pub use post::Post;
 pub use like::Like;
+pub use outbox::Outbox;
+pub use webhook::Webhook;
+pub use notification::Notification;
+pub use follow::Follow;
+pub use transfer::Transfer;
+pub use migration::Migrator;
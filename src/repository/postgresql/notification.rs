@@ -0,0 +1,258 @@
+use std::{error, time::SystemTime};
+
+use postgres;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::DecodeError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Notification {
+  pool: utils::Pool
+}
+
+impl repository::Notification for Notification {
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.list_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn mark_read(&self, user_id: &models::UserId, ids: &[models::NotificationId]) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.mark_read_wt(user_id, ids, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn count_unread(&self, user_id: &models::UserId) -> Result<u64, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.count_unread_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+}
+
+impl Notification {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn create_wt(
+    &self,
+    notification: &models::Notification,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::NotificationId, Box<dyn error::Error>> {
+    let row = transaction.query_one(
+      "
+        insert into notifications(user_id, kind, actor_id, post_id)
+        values ($1, $2, $3, $4)
+        returning id;
+      ",
+      &[
+        &notification.user_id.as_i32()?, &notification.kind.as_str(),
+        &notification.actor_id.as_i32()?, &notification.post_id.as_i32()?
+      ]
+    )?;
+
+    let notification_id: i32 = row.get(0);
+
+    Ok(models::NotificationId::from(notification_id))
+  }
+
+  pub fn list_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Notification>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          id, user_id, kind, actor_id, post_id, read_at, created_at
+        from
+          notifications
+        where
+          user_id = $1
+        order by
+          id desc;
+      ",
+      &[&user_id.as_i32()?]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read(row))
+      .collect()
+  }
+
+  pub fn mark_read_wt(
+    &self,
+    user_id: &models::UserId, ids: &[models::NotificationId],
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let ids = ids.iter()
+      .map(|id| id.as_i32())
+      .collect::<Result<Vec<i32>, _>>()?;
+
+    transaction.execute(
+      "
+        update
+          notifications
+        set
+          read_at = now()
+        where
+          user_id = $1
+          and id = any($2)
+          and read_at is null;
+      ",
+      &[&user_id.as_i32()?, &ids]
+    )?;
+
+    Ok(())
+  }
+
+  pub fn count_unread_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<u64, Box<dyn error::Error>> {
+    let row = transaction.query_one(
+      "select count(*) from notifications where user_id = $1 and read_at is null;",
+      &[&user_id.as_i32()?]
+    )?;
+
+    let count: i64 = row.get(0);
+
+    Ok(count as u64)
+  }
+
+  pub fn read(&self, row: &postgres::Row) -> Result<models::Notification, Box<dyn error::Error>> {
+    let id: i32 = row.get("id");
+    let user_id: i32 = row.get("user_id");
+    let kind: String = row.get("kind");
+    let actor_id: i32 = row.get("actor_id");
+    let post_id: i32 = row.get("post_id");
+    let read_at: Option<SystemTime> = row.get("read_at");
+
+    let kind = models::NotificationKind::parse(&kind)
+      .ok_or(DecodeError::new("notifications", Some(&id.to_string()), "kind", &format!("{:?} isn't a kind", kind)))?;
+
+    Ok(
+      models::Notification {
+        id: models::NotificationId::from(id),
+        user_id: models::UserId::from(user_id),
+        kind,
+        actor_id: models::UserId::from(actor_id),
+        post_id: models::PostId::from(post_id),
+        read: read_at.is_some(),
+        created_at: row.get("created_at")
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_notification() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_5__".to_owned(),
+      last_name: "__test_5__".to_owned(),
+      email: Some("__test_5__@5.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_6__".to_owned(),
+      last_name: "__test_6__".to_owned(),
+      email: Some("__test_6__@6.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::postgresql::User::new();
+    let post_repository = repository::postgresql::Post::new();
+    let like_repository = repository::postgresql::Like::new();
+    let notification_repository = repository::postgresql::Notification::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let author_id = user_repository.create_wt(&mut author, &mut transaction)?;
+    let reader_id = user_repository.create_wt(&mut reader, &mut transaction)?;
+
+    let post_id = post_repository.create_wt(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "__test_5__".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      },
+      &mut transaction
+    )?;
+
+    like_repository.create_wt(&reader_id, &post_id, &mut transaction)?;
+    like_repository.create_wt(&author_id, &post_id, &mut transaction)?;
+
+    let notifications = notification_repository.list_wt(&author_id, &mut transaction)?;
+
+    assert_eq!(notifications.len(), 1);
+    assert_eq!(notifications[0].actor_id, reader_id);
+    assert_eq!(notifications[0].post_id, post_id);
+    assert_eq!(notification_repository.count_unread_wt(&author_id, &mut transaction)?, 1);
+
+    notification_repository.mark_read_wt(&reader_id, &[notifications[0].id], &mut transaction)?;
+
+    assert_eq!(notification_repository.count_unread_wt(&author_id, &mut transaction)?, 1);
+
+    notification_repository.mark_read_wt(&author_id, &[notifications[0].id], &mut transaction)?;
+
+    assert_eq!(notification_repository.count_unread_wt(&author_id, &mut transaction)?, 0);
+
+    transaction.rollback()?;
+
+    Ok(())
+  }
+}
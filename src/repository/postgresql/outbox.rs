@@ -0,0 +1,305 @@
+use std::{error, time::SystemTime};
+
+use postgres;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::DecodeError;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Outbox {
+  pool: utils::Pool
+}
+
+impl repository::Outbox for Outbox {
+  fn poll(&self, consumer: &str, limit: usize) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.poll_wt(consumer, limit, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn acknowledge(&self, consumer: &str, ids: &[models::EventId]) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.acknowledge_wt(consumer, ids, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+}
+
+impl Outbox {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn append_wt(
+    &self,
+    event: &models::Event,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::EventId, Box<dyn error::Error>> {
+    let user_id = event.user_id().map(|user_id| user_id.as_i32()).transpose()?;
+    let post_id = event.post_id().map(|post_id| post_id.as_i32()).transpose()?;
+
+    let row = transaction.query_one(
+      "
+        insert into outbox(kind, user_id, post_id)
+        values ($1, $2, $3)
+        returning id;
+      ",
+      &[&event.kind(), &user_id, &post_id]
+    )?;
+
+    let event_id: i32 = row.get(0);
+
+    Ok(models::EventId::from(event_id))
+  }
+
+  pub fn poll_wt(
+    &self,
+    consumer: &str, limit: usize,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::OutboxEvent>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          id, kind, user_id, post_id, created_at
+        from
+          outbox
+        where
+          id > coalesce(
+            (select acknowledged_through from outbox_consumers where consumer = $1),
+            0
+          )
+          and not exists (
+            select
+            from
+              outbox_acknowledgements
+            where
+              consumer = $1
+              and event_id = outbox.id
+          )
+        order by
+          id
+        limit $2;
+      ",
+      &[&consumer, &(limit as i64)]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read(row))
+      .collect()
+  }
+
+  pub fn acknowledge_wt(
+    &self,
+    consumer: &str, ids: &[models::EventId],
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let ids = ids.iter()
+      .map(|id| id.as_i32())
+      .collect::<Result<Vec<i32>, _>>()?;
+
+    transaction.execute(
+      "
+        insert into outbox_acknowledgements(consumer, event_id)
+        select
+          $1, id
+        from
+          outbox
+        where
+          id = any($2)
+        on conflict do nothing;
+      ",
+      &[&consumer, &ids]
+    )?;
+
+    self.advance_wt(consumer, transaction)?;
+
+    Ok(())
+  }
+
+  /// Moves the consumer's cursor past the events it acknowledged without
+  /// a gap and drops their acknowledgements, returns the new cursor
+  pub fn advance_wt(
+    &self,
+    consumer: &str,
+    transaction: &mut postgres::Transaction
+  ) -> Result<i32, Box<dyn error::Error>> {
+    // Ids are taken before the insert commits, so an event below the
+    // acknowledged ones can still turn up. This waits for the transactions
+    // writing to the outbox right now, after which no lower id can appear
+    transaction.batch_execute("lock table outbox in share mode;")?;
+
+    transaction.execute(
+      "insert into outbox_consumers(consumer) values ($1) on conflict do nothing;",
+      &[&consumer]
+    )?;
+
+    let row = transaction.query_one(
+      "
+        update
+          outbox_consumers c
+        set
+          acknowledged_through = coalesce(
+            (
+              select
+                min(o.id) - 1
+              from
+                outbox o
+              where
+                o.id > c.acknowledged_through
+                and not exists (
+                  select
+                  from
+                    outbox_acknowledgements a
+                  where
+                    a.consumer = c.consumer
+                    and a.event_id = o.id
+                )
+            ),
+            (select max(id) from outbox),
+            c.acknowledged_through
+          )
+        where
+          consumer = $1
+        returning
+          acknowledged_through;
+      ",
+      &[&consumer]
+    )?;
+
+    let acknowledged_through: i32 = row.get(0);
+
+    transaction.execute(
+      "delete from outbox_acknowledgements where consumer = $1 and event_id <= $2;",
+      &[&consumer, &acknowledged_through]
+    )?;
+
+    Ok(acknowledged_through)
+  }
+
+  pub fn read(&self, row: &postgres::Row) -> Result<models::OutboxEvent, Box<dyn error::Error>> {
+    let id: i32 = row.get("id");
+    let kind: String = row.get("kind");
+    let user_id: Option<i32> = row.get("user_id");
+    let post_id: Option<i32> = row.get("post_id");
+    let created_at: SystemTime = row.get("created_at");
+
+    let event = models::Event::from_parts(
+      &kind,
+      user_id.map(models::UserId::from),
+      post_id.map(models::PostId::from)
+    )
+    .ok_or(DecodeError::new("outbox", Some(&id.to_string()), "kind", &format!("{:?} doesn't match the ids", kind)))?;
+
+    Ok(
+      models::OutboxEvent {
+        id: models::EventId::from(id),
+        event,
+        created_at
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::error;
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_outbox() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_3__".to_owned(),
+      last_name: "__test_3__".to_owned(),
+      email: Some("__test_3__@3.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::postgresql::User::new();
+    let outbox = repository::postgresql::Outbox::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let user_id = user_repository.create_wt(&mut user, &mut transaction)?;
+
+    let events = outbox.poll_wt("test", 1000, &mut transaction)?;
+
+    let registered = events.iter()
+      .find(|event| event.event == models::Event::UserRegistered { user_id })
+      .unwrap();
+
+    outbox.acknowledge_wt("test", &[registered.id], &mut transaction)?;
+
+    let events = outbox.poll_wt("test", 1000, &mut transaction)?;
+
+    assert!(events.iter().all(|event| event.id != registered.id));
+
+    // Another consumer still gets it
+    let events = outbox.poll_wt("other", 1000, &mut transaction)?;
+
+    assert!(events.iter().any(|event| event.id == registered.id));
+
+    loop {
+      let ids = outbox.poll_wt("test", 1000, &mut transaction)?
+        .iter()
+        .map(|event| event.id)
+        .collect::<Vec<_>>();
+
+      if ids.is_empty() {
+        break;
+      }
+
+      outbox.acknowledge_wt("test", &ids, &mut transaction)?;
+    }
+
+    // With everything acknowledged the cursor is at the last event and
+    // the acknowledgements below it are gone, so none of it is scanned again
+    let last: i32 = transaction.query_one("select max(id) from outbox;", &[])?.get(0);
+
+    assert_eq!(outbox.advance_wt("test", &mut transaction)?, last);
+
+    let acknowledgements: i64 = transaction.query_one(
+      "select count(*) from outbox_acknowledgements where consumer = 'test';",
+      &[]
+    )?
+    .get(0);
+
+    assert_eq!(acknowledgements, 0);
+
+    transaction.rollback()?;
+
+    Ok(())
+  }
+}
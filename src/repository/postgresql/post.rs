@@ -6,25 +6,28 @@ use postgres::fallible_iterator::FallibleIterator;
 use crate::repository;
 use crate::models;
 
+use crate::utils::error::NotFoundError;
+
 use super::utils;
 
-pub struct Post {}
+#[derive(Default)]
+pub struct Post {
+  pool: utils::Pool
+}
 
 impl repository::Post for Post {
-  fn create(&self, post: &models::Post) -> Result<String, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn create(&self, post: &models::Post) -> Result<models::PostId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
     let res = self.create_wt(post, &mut transaction);
 
-    transaction.commit()?;
-
-    res
+    utils::commit_or_rollback(transaction, res)
   }
 
-  fn get(&self, id: &str, user_id: Option<&str>) -> Result<models::Post, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn get(&self, id: &models::PostId, user_id: Option<&models::UserId>) -> Result<models::Post, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -35,8 +38,8 @@ impl repository::Post for Post {
     res
   }
 
-  fn list(&self, user_id: Option<&str>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn list(&self, user_id: Option<&models::UserId>) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -47,8 +50,8 @@ impl repository::Post for Post {
     res
   }
 
-  fn liked_list(&self, user_id: &str) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn liked_list(&self, user_id: &models::UserId) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -58,22 +61,42 @@ impl repository::Post for Post {
 
     res
   }
+
+  fn feed(&self, user_id: &models::UserId, page: u32) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.feed_wt(user_id, page, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
 }
 
 impl Post {
   pub fn new() -> Self {
-    Self { }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn create_wt(
     &self, 
     post: &models::Post,
     transaction: &mut postgres::Transaction
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::PostId, Box<dyn error::Error>> {
     let mut user_id = None;
 
     if let Some(author) = post.author.as_ref() {
-      user_id = Some(author.id.parse::<i32>()?);
+      user_id = Some(author.id.as_i32()?);
     }
 
     let row = transaction.query_one(
@@ -85,19 +108,27 @@ impl Post {
       &[&user_id, &post.title, &post.text, &post.description]
     )?;
 
-    let post_id: i32 = row.get(0);
+    let post_id = models::PostId::from(row.get::<_, i32>(0));
+
+    super::Outbox::new().append_wt(
+      &models::Event::PostPublished {
+        post_id,
+        user_id: post.author.as_ref().map(|author| author.id)
+      },
+      transaction
+    )?;
     
-    Ok(post_id.to_string())
+    Ok(post_id)
   }
 
   pub fn get_wt(
     &self, 
-    id: &str, user_id: Option<&str>,
+    id: &models::PostId, user_id: Option<&models::UserId>,
     transaction: &mut postgres::Transaction
   ) -> Result<models::Post, Box<dyn error::Error>> {
-    let user_id = user_id.and_then(|s| s.parse::<i32>().ok());
+    let user_id = user_id.map(|user_id| user_id.as_i32()).transpose()?;
 
-    let row = transaction.query_one(
+    let row = transaction.query_opt(
       "
         select 
           u.id user_id, u.first_name, u.last_name,
@@ -125,18 +156,19 @@ impl Post {
         where
           p.id = $1;
       ",
-      &[&id.parse::<i32>()?, &user_id]
-    )?;
+      &[&id.as_i32()?, &user_id]
+    )?
+    .ok_or(NotFoundError::new("Post with this id not found"))?;
 
     Ok(self.read(&row))
   }
 
   pub fn list_wt(
     &self, 
-    user_id: Option<&str>,
+    user_id: Option<&models::UserId>,
     transaction: &mut postgres::Transaction
   ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
-    let user_id = user_id.and_then(|s| s.parse::<i32>().ok());
+    let user_id = user_id.map(|user_id| user_id.as_i32()).transpose()?;
 
     let mut v = Vec::new();
 
@@ -180,7 +212,7 @@ impl Post {
 
   pub fn liked_list_wt(
     &self, 
-    user_id: &str,
+    user_id: &models::UserId,
     transaction: &mut postgres::Transaction
   ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
     let mut v = Vec::new();
@@ -211,7 +243,7 @@ impl Post {
           on p.id = l.post_id
             and l.user_id = $1;
       ",
-      &[&user_id.parse::<i32>()?]
+      &[&user_id.as_i32()?]
     )?;
 
     while let Some(row) = row_iter.next()? {
@@ -223,11 +255,116 @@ impl Post {
     Ok(v)
   }
 
+  // The author's posts, liked as the author sees them
+  pub fn authored_list_wt(
+    &self,
+    author_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email is null or s.display_email = false then null
+            else u.email
+          end email,
+          p.id post_id, p.title, p.text, p.abstract,
+          case
+            when l.id is null then false
+            else true
+          end liked
+        from
+          posts p
+        left join
+          users u
+          on p.user_id = u.id
+        left join
+          settings s
+          on p.user_id = s.user_id
+        left join
+          likes l
+          on p.id = l.post_id
+            and l.user_id = $1
+        where
+          p.user_id = $1
+        order by
+          p.id;
+      ",
+      &[&author_id.as_i32()?]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| self.read(row))
+        .collect()
+    )
+  }
+
+  pub fn feed_wt(
+    &self,
+    user_id: &models::UserId, page: u32,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Post>, Box<dyn error::Error>> {
+    let user_id = user_id.as_i32()?;
+
+    let per_page = transaction.query_opt(
+      "select posts_per_page from settings where user_id = $1;",
+      &[&user_id]
+    )?
+    .map(|row| row.get::<_, i32>(0))
+    .unwrap_or(models::Settings::new().posts_per_page)
+    .max(1) as i64;
+
+    let rows = transaction.query(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name,
+          case
+            when s.display_email is null or s.display_email = false then null
+            else u.email
+          end email,
+          p.id post_id, p.title, p.text, p.abstract,
+          case
+            when l.id is null then false
+            else true
+          end liked
+        from
+          posts p
+        inner join
+          follows f
+          on p.user_id = f.author_id
+            and f.follower_id = $1
+        left join
+          users u
+          on p.user_id = u.id
+        left join
+          settings s
+          on p.user_id = s.user_id
+        left join
+          likes l
+          on p.id = l.post_id
+            and l.user_id = $1
+        order by
+          p.id desc
+        limit $2
+        offset $3;
+      ",
+      &[&user_id, &per_page, &(page as i64 * per_page)]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| self.read(row))
+        .collect()
+    )
+  }
+
   pub fn read(&self, row: &postgres::Row) -> models::Post {
     let user_id: Option<i32> = row.get("user_id");
     let post_id: i32 = row.get("post_id");
     
-    let post_id = post_id.to_string();
+    let post_id = models::PostId::from(post_id);
 
     models::Post {
       id: post_id,
@@ -236,40 +373,35 @@ impl Post {
       description: row.get("abstract"),
       liked: row.get("liked"),
 
-      author: if user_id.is_some() {
-        Some(
-          models::User {
-            id: user_id.unwrap().to_string(),
-            first_name: row.get("first_name"),
-            last_name: row.get("last_name"),
-            email: row.get("email"),
-            password: None,
-            
-            settings: models::Settings::new()
-          }
-        )
-      } else {
-        None
-      }
+      author: user_id.map(|user_id| models::User {
+        id: models::UserId::from(user_id),
+        first_name: row.get("first_name"),
+        last_name: row.get("last_name"),
+        email: row.get("email"),
+        password: None,
+        
+        settings: models::Settings::new()
+      })
     }
   }
 }
 
 #[cfg(test)]
 mod tests {
-  use std::error;
+  use std::{error, thread};
 
   use dotenv::dotenv;
 
   use super::utils;
   use crate::{models, repository};
+  use crate::utils::error::{ConflictError, NotFoundError};
 
   #[test]
   fn test_post() -> Result<(), Box<dyn error::Error>> {
     dotenv().ok();
 
     let mut user = models::User {
-      id: String::new(),
+      id: models::UserId::new(),
       first_name: "__test_2__".to_owned(),
       last_name: "__test_2__".to_owned(),
       email: Some("__test_2__@2.again".to_owned()),
@@ -290,7 +422,7 @@ mod tests {
     assert!(!user.id.is_empty());
 
     let post = models::Post {
-      id: String::new(),
+      id: models::PostId::new(),
       title: "__title_1".to_owned(),
       text: Some("__text_1".to_owned()),
       description: Some("__abstract_1".to_owned()),
@@ -327,7 +459,7 @@ mod tests {
 
     let posts = post_repository.list_wt(Some(&user.id), &mut transaction)?;
 
-    assert!(posts.len() > 0);
+    assert!(!posts.is_empty());
     assert!(posts.iter().find(|p| p.id == post_id).is_some());
 
     let liked_posts = post_repository.liked_list_wt(&user.id, &mut transaction)?;
@@ -336,13 +468,19 @@ mod tests {
 
     like_repository.create_wt(&user.id, &post_id, &mut transaction)?;
 
+    // Liking twice is a conflict, not a second like
+    assert!(like_repository.create_wt(&user.id, &post_id, &mut transaction).unwrap_err().is::<ConflictError>());
+
     let liked_posts_2 = post_repository.liked_list_wt(&user.id, &mut transaction)?;
 
-    assert!(liked_posts_2.len() > 0);
+    assert!(!liked_posts_2.is_empty());
     assert!(liked_posts_2.iter().find(|p| p.id == post_id && p.liked).is_some());
 
     like_repository.delete_wt(&user.id, &post_id, &mut transaction)?;
 
+    // So is taking back a like that isn't there
+    assert!(like_repository.delete_wt(&user.id, &post_id, &mut transaction).unwrap_err().is::<NotFoundError>());
+
     let liked_posts_3 = post_repository.liked_list_wt(
       &user.id, 
       &mut transaction
@@ -354,4 +492,28 @@ mod tests {
 
     Ok(())
   }
+
+  #[test]
+  fn test_threads() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    // One pool of connections for all threads
+    let post_repository: Box<dyn repository::Post> = Box::new(
+      repository::postgresql::Post::new()
+    );
+
+    let count = post_repository.list(None)?.len();
+
+    thread::scope(|scope| {
+      for _ in 0..8 {
+        scope.spawn(|| {
+          for _ in 0..20 {
+            assert_eq!(post_repository.list(None).unwrap().len(), count);
+          }
+        });
+      }
+    });
+
+    Ok(())
+  }
 }
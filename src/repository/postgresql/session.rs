@@ -3,14 +3,20 @@ use std::error;
 use postgres;
 
 use crate::repository;
+use crate::models;
+
+use crate::utils::error::NotFoundError;
 
 use super::utils;
 
-pub struct Session {}
+#[derive(Default)]
+pub struct Session {
+  pool: utils::Pool
+}
 
 impl repository::Session for Session {
-  fn get_user_id(&self, code: &str) -> Result<String, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -21,8 +27,8 @@ impl repository::Session for Session {
     res
   }
 
-  fn create(&self, user_id: &str, code: &str) -> Result<(), Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -32,31 +38,52 @@ impl repository::Session for Session {
 
     res
   }
+
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.delete_wt(code, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
 }
 
 impl Session {
   pub fn new() -> Self {
-    Self { }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn get_user_id_wt(
     &self, 
-    code: &str, 
+    code: &models::SessionCode, 
     transaction: &mut postgres::Transaction
-  ) -> Result<String, Box<dyn error::Error>> {
-    let row = transaction.query_one(
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
+    let row = transaction.query_opt(
       "select user_id from sessions where code = $1;", 
-      &[&code]
-    )?;
+      &[&code.as_str()]
+    )?
+    .ok_or(NotFoundError::new("User with this session code doesn't exist"))?;
 
     let user_id: i32 = row.get("user_id");
 
-    Ok(user_id.to_string())
+    Ok(models::UserId::from(user_id))
   }
 
   pub fn create_wt(
     &self,
-    user_id: &str, code: &str,
+    user_id: &models::UserId, code: &models::SessionCode,
     transaction: &mut postgres::Transaction
   ) -> Result<(), Box<dyn error::Error>> {
     transaction.execute(
@@ -64,7 +91,20 @@ impl Session {
         insert into sessions(user_id, code) 
         values ($1, $2);
       ", 
-      &[&user_id.parse::<i32>()?, &code]
+      &[&user_id.as_i32()?, &code.as_str()]
+    )?;
+
+    Ok(())
+  }
+
+  pub fn delete_wt(
+    &self,
+    code: &models::SessionCode,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    transaction.execute(
+      "delete from sessions where code = $1;", 
+      &[&code.as_str()]
     )?;
 
     Ok(())
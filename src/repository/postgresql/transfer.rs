@@ -0,0 +1,504 @@
+use std::error;
+
+use postgres;
+
+use crate::repository;
+use crate::models;
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Transfer {
+  pool: utils::Pool
+}
+
+impl repository::Transfer for Transfer {
+  fn count(&self, entity: repository::Entity) -> Result<u64, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.count_wt(entity, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn users(&self) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.users_wt(&mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn sessions(&self) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.sessions_wt(&mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn posts(&self) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.posts_wt(&mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn likes(&self) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.likes_wt(&mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn follows(&self) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.follows_wt(&mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn insert_users(&self, users: &[repository::UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.insert_users_wt(users, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn insert_sessions(&self, sessions: &[repository::SessionRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.insert_sessions_wt(sessions, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn insert_posts(&self, posts: &[repository::PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.insert_posts_wt(posts, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn insert_likes(&self, likes: &[repository::LikeRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.insert_likes_wt(likes, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn insert_follows(&self, follows: &[repository::FollowRecord]) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.insert_follows_wt(follows, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+}
+
+impl Transfer {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn count_wt(
+    &self,
+    entity: repository::Entity,
+    transaction: &mut postgres::Transaction
+  ) -> Result<u64, Box<dyn error::Error>> {
+    // The table names come from the enum, never from the caller
+    let row = transaction.query_one(
+      &format!("select count(*) from {};", entity.name()),
+      &[]
+    )?;
+
+    let count: i64 = row.get(0);
+
+    Ok(count as u64)
+  }
+
+  pub fn users_wt(
+    &self,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          u.id, u.first_name, u.last_name, u.email, u.password,
+          s.posts_per_page, s.display_email
+        from
+          users u
+        left join
+          settings s
+          on s.user_id = u.id
+        order by
+          u.id;
+      ",
+      &[]
+    )?;
+
+    let defaults = models::Settings::new();
+
+    Ok(
+      rows.iter()
+        .map(|row| {
+          let id: i32 = row.get("id");
+          let posts_per_page: Option<i32> = row.get("posts_per_page");
+          let display_email: Option<bool> = row.get("display_email");
+
+          repository::UserRecord {
+            id: models::UserId::from(id),
+            first_name: row.get("first_name"),
+            last_name: row.get("last_name"),
+            email: row.get("email"),
+            password_hash: row.get("password"),
+            posts_per_page: posts_per_page.unwrap_or(defaults.posts_per_page),
+            display_email: display_email.unwrap_or(defaults.display_email)
+          }
+        })
+        .collect()
+    )
+  }
+
+  pub fn sessions_wt(
+    &self,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select user_id, code from sessions order by code;",
+      &[]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| {
+          let user_id: i32 = row.get("user_id");
+          let code: String = row.get("code");
+
+          repository::SessionRecord {
+            user_id: models::UserId::from(user_id),
+            code: models::SessionCode::new(&code),
+            user_agent: None,
+            ip: None
+          }
+        })
+        .collect()
+    )
+  }
+
+  pub fn posts_wt(
+    &self,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select id, user_id, title, text, abstract from posts order by id;",
+      &[]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| {
+          let id: i32 = row.get("id");
+          let user_id: Option<i32> = row.get("user_id");
+
+          repository::PostRecord {
+            id: models::PostId::from(id),
+            user_id: user_id.map(models::UserId::from),
+            title: row.get("title"),
+            text: row.get("text"),
+            description: row.get("abstract")
+          }
+        })
+        .collect()
+    )
+  }
+
+  pub fn likes_wt(
+    &self,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select user_id, post_id from likes order by id;",
+      &[]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| {
+          let user_id: i32 = row.get("user_id");
+          let post_id: i32 = row.get("post_id");
+
+          repository::LikeRecord {
+            user_id: models::UserId::from(user_id),
+            post_id: models::PostId::from(post_id)
+          }
+        })
+        .collect()
+    )
+  }
+
+  pub fn follows_wt(
+    &self,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select follower_id, author_id from follows order by created_at, follower_id, author_id;",
+      &[]
+    )?;
+
+    Ok(
+      rows.iter()
+        .map(|row| {
+          let follower_id: i32 = row.get("follower_id");
+          let author_id: i32 = row.get("author_id");
+
+          repository::FollowRecord {
+            follower_id: models::UserId::from(follower_id),
+            author_id: models::UserId::from(author_id)
+          }
+        })
+        .collect()
+    )
+  }
+
+  pub fn insert_users_wt(
+    &self,
+    users: &[repository::UserRecord],
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    let user_repository = super::User::new();
+
+    let mut ids = Vec::with_capacity(users.len());
+
+    for user in users {
+      if let Some(user_id) = self.copied_wt(repository::Entity::Users, &user.id.to_string(), transaction)? {
+        ids.push(models::UserId::from(user_id));
+
+        continue;
+      }
+
+      // The hash is copied as it is, hashing it again would lock the user out
+      let row = transaction.query_one(
+        "
+          insert into users(first_name, last_name, email, password)
+          values ($1, $2, $3, $4)
+          returning id;
+        ",
+        &[&user.first_name, &user.last_name, &user.email, &user.password_hash]
+      )?;
+
+      let user_id: i32 = row.get(0);
+
+      self.remember_wt(repository::Entity::Users, &user.id.to_string(), user_id, transaction)?;
+
+      let user_id = models::UserId::from(user_id);
+
+      user_repository.create_settings_wt(
+        &models::Settings {
+          id: String::new(),
+          user_id,
+          posts_per_page: user.posts_per_page,
+          display_email: user.display_email
+        },
+        transaction
+      )?;
+
+      ids.push(user_id);
+    }
+
+    Ok(ids)
+  }
+
+  pub fn insert_sessions_wt(
+    &self,
+    sessions: &[repository::SessionRecord],
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let session_repository = super::Session::new();
+
+    for session in sessions {
+      let present: bool = transaction.query_one(
+        "select exists (select from sessions where code = $1);",
+        &[&session.code.as_str()]
+      )?
+      .get(0);
+
+      if !present {
+        session_repository.create_wt(&session.user_id, &session.code, transaction)?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn insert_posts_wt(
+    &self,
+    posts: &[repository::PostRecord],
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    let mut ids = Vec::with_capacity(posts.len());
+
+    for post in posts {
+      if let Some(post_id) = self.copied_wt(repository::Entity::Posts, &post.id.to_string(), transaction)? {
+        ids.push(models::PostId::from(post_id));
+
+        continue;
+      }
+
+      let user_id = post.user_id
+        .map(|user_id| user_id.as_i32())
+        .transpose()?;
+
+      let row = transaction.query_one(
+        "
+          insert into posts(user_id, title, text, abstract)
+          values ($1, $2, $3, $4)
+          returning id;
+        ",
+        &[&user_id, &post.title, &post.text, &post.description]
+      )?;
+
+      let post_id: i32 = row.get(0);
+
+      self.remember_wt(repository::Entity::Posts, &post.id.to_string(), post_id, transaction)?;
+
+      ids.push(models::PostId::from(post_id));
+    }
+
+    Ok(ids)
+  }
+
+  pub fn insert_likes_wt(
+    &self,
+    likes: &[repository::LikeRecord],
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    // Copies aren't new likes, so no events are written for them
+    for like in likes {
+      let user_id = like.user_id.as_i32()?;
+      let post_id = like.post_id.as_i32()?;
+
+      let present: bool = transaction.query_one(
+        "select exists (select from likes where user_id = $1 and post_id = $2);",
+        &[&user_id, &post_id]
+      )?
+      .get(0);
+
+      if !present {
+        transaction.execute(
+          "
+            insert into likes(user_id, post_id)
+            values ($1, $2);
+          ",
+          &[&user_id, &post_id]
+        )?;
+      }
+    }
+
+    Ok(())
+  }
+
+  pub fn insert_follows_wt(
+    &self,
+    follows: &[repository::FollowRecord],
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    for follow in follows {
+      transaction.execute(
+        "
+          insert into follows(follower_id, author_id)
+          values ($1, $2)
+          on conflict do nothing;
+        ",
+        &[&follow.follower_id.as_i32()?, &follow.author_id.as_i32()?]
+      )?;
+    }
+
+    Ok(())
+  }
+
+  // The row an earlier transfer copied the source record to
+  fn copied_wt(
+    &self,
+    entity: repository::Entity, source_id: &str,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Option<i32>, Box<dyn error::Error>> {
+    let row = transaction.query_opt(
+      "select target_id from transfer_ids where entity = $1 and source_id = $2;",
+      &[&entity.name(), &source_id]
+    )?;
+
+    Ok(row.map(|row| row.get(0)))
+  }
+
+  fn remember_wt(
+    &self,
+    entity: repository::Entity, source_id: &str, target_id: i32,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    transaction.execute(
+      "insert into transfer_ids(entity, source_id, target_id) values ($1, $2, $3);",
+      &[&entity.name(), &source_id, &target_id]
+    )?;
+
+    Ok(())
+  }
+}
@@ -5,27 +5,28 @@ use postgres;
 use crate::repository;
 use crate::models;
 
-use crate::utils::error::StringError;
+use crate::utils::error::{ConflictError, NotFoundError, ValidationError};
 
 use super::utils;
 
-pub struct User {}
+#[derive(Default)]
+pub struct User {
+  pool: utils::Pool
+}
 
 impl repository::User for User {
-  fn create(&self, user: &mut models::User) -> Result<String, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
     let res = self.create_wt(user, &mut transaction);
 
-    transaction.commit()?;
-
-    res
+    utils::commit_or_rollback(transaction, res)
   }
 
-  fn get_id(&self, email: &str, password: &str) -> Result<String, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -36,8 +37,8 @@ impl repository::User for User {
     res
   }
 
-  fn get_user_settings(&self, id: &str) -> Result<models::User, Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
@@ -49,12 +50,62 @@ impl repository::User for User {
   }
 
   fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
-    let mut connection = utils::connect()?;
+    let mut connection = self.pool.get()?;
 
     let mut transaction = connection.transaction()?;
 
     let res = self.edit_wt(settings, &mut transaction);
 
+    utils::commit_or_rollback(transaction, res)
+  }
+
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.edit_profile_wt(id, first_name, last_name, &mut transaction);
+
+    utils::commit_or_rollback(transaction, res)
+  }
+
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.change_email_wt(id, email, &mut transaction);
+
+    utils::commit_or_rollback(transaction, res)
+  }
+
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.change_password_wt(id, old_password, new_password, &mut transaction);
+
+    utils::commit_or_rollback(transaction, res)
+  }
+
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.delete_wt(id, &mut transaction);
+
+    utils::commit_or_rollback(transaction, res)
+  }
+
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.export_personal_data_wt(id, &mut transaction);
+
     transaction.commit()?;
 
     res
@@ -63,22 +114,35 @@ impl repository::User for User {
 
 impl User {
   pub fn new() -> Self {
-    Self { }
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
   }
 
   pub fn create_wt(
     &self, 
     user: &mut models::User, 
     transaction: &mut postgres::Transaction
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
     let user_id = self.create_user_wt(user, transaction)?;
 
-    user.id = user_id.clone();
-    user.settings.user_id = user_id.clone();
+    user.id = user_id;
+    user.settings.user_id = user_id;
 
     let settings_id = self.create_settings_wt(&user.settings, transaction)?;
 
     user.settings.id = settings_id;
+
+    super::Outbox::new().append_wt(
+      &models::Event::UserRegistered { user_id },
+      transaction
+    )?;
     
     Ok(user_id)
   }
@@ -87,8 +151,12 @@ impl User {
     &self, 
     user: &models::User, 
     transaction: &mut postgres::Transaction
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
     if let Some(password) = user.password.as_ref() {
+      if let Some(email) = user.email.as_deref() {
+        self.check_email_wt(email, None, transaction)?;
+      }
+
       let password = format!("{:x}", md5::compute(password));
 
       let row = transaction.query_one(
@@ -98,15 +166,16 @@ impl User {
           returning id;
         ",
         &[&user.first_name, &user.last_name, &user.email, &password]
-      )?;
+      )
+      .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
 
       let user_id: i32 = row.get(0);      
 
-      Ok(user_id.to_string())
+      Ok(models::UserId::from(user_id))
     } else {
       Err(
         Box::new(
-          StringError::new("Password should be non-empty")
+          ValidationError::new("Password should be non-empty")
         )
       )
     }
@@ -123,7 +192,7 @@ impl User {
         values ($1, $2, $3)
         returning id;
       ",
-      &[&settings.user_id.parse::<i32>()?, &settings.posts_per_page, &settings.display_email]
+      &[&settings.user_id.as_i32()?, &settings.posts_per_page, &settings.display_email]
     )?;
 
     let settings_id: i32 = row.get(0);
@@ -131,29 +200,57 @@ impl User {
     Ok(settings_id.to_string())
   }
 
+  // The users_email_lower index enforces this too, and the writes turn its
+  // violation into the same ConflictError when a concurrent one got there first
+  pub fn check_email_wt(
+    &self,
+    email: &str, except: Option<&models::UserId>,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "select id from users where lower(email) = lower($1);",
+      &[&email]
+    )?;
+
+    let taken = rows.iter()
+      .map(|row| models::UserId::from(row.get::<_, i32>("id")))
+      .any(|user_id| Some(&user_id) != except);
+
+    if taken {
+      return Err(
+        Box::new(
+          ConflictError::new("User with this email already exists")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
   pub fn get_id_wt(
     &self, 
     email: &str, password: &str,
     transaction: &mut postgres::Transaction
-  ) -> Result<String, Box<dyn error::Error>> {
+  ) -> Result<models::UserId, Box<dyn error::Error>> {
     let password = format!("{:x}", md5::compute(password));
 
-    let row = transaction.query_one(
-      "select id from users where email = $1 and password = $2;", 
+    let row = transaction.query_opt(
+      "select id from users where lower(email) = lower($1) and password = $2;", 
       &[&email, &password]
-    )?;
+    )?
+    .ok_or(NotFoundError::new("User with this email and password doesn't exist"))?;
 
     let user_id: i32 = row.get("id");
 
-    Ok(user_id.to_string())
+    Ok(models::UserId::from(user_id))
   }
 
   pub fn get_user_settings_wt(
     &self,
-    id: &str,
+    id: &models::UserId,
     transaction: &mut postgres::Transaction
   ) -> Result<models::User, Box<dyn error::Error>> {
-    let row = transaction.query_one(
+    let row = transaction.query_opt(
       "
         select 
           u.id user_id, u.first_name, u.last_name, 
@@ -168,8 +265,9 @@ impl User {
           s.user_id = u.id
           and u.id = $1;
       ", 
-      &[&id.parse::<i32>()?]
-    )?;
+      &[&id.as_i32()?]
+    )?
+    .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
 
     Ok(self.read(&row))
   }
@@ -179,7 +277,7 @@ impl User {
     settings: &models::Settings, 
     transaction: &mut postgres::Transaction
   ) -> Result<(), Box<dyn error::Error>> {
-    transaction.execute(
+    let res = transaction.execute(
       "
         update 
           settings 
@@ -189,21 +287,194 @@ impl User {
         where
           user_id = $3;
       ", 
-      &[&settings.posts_per_page, &settings.display_email, &settings.user_id.parse::<i32>()?]
+      &[&settings.posts_per_page, &settings.display_email, &settings.user_id.as_i32()?]
+    )?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    super::Outbox::new().append_wt(
+      &models::Event::SettingsChanged { user_id: settings.user_id },
+      transaction
+    )?;
+
+    Ok(())
+  }
+
+  pub fn edit_profile_wt(
+    &self,
+    id: &models::UserId, first_name: &str, last_name: &str,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let res = transaction.execute(
+      "update users set first_name = $1, last_name = $2 where id = $3;",
+      &[&first_name, &last_name, &id.as_i32()?]
     )?;
 
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
     Ok(())
   }
 
+  pub fn change_email_wt(
+    &self,
+    id: &models::UserId, email: &str,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    if email.trim().is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Email should be non-empty")
+        )
+      );
+    }
+
+    self.check_email_wt(email, Some(id), transaction)?;
+
+    let res = transaction.execute(
+      "update users set email = $1 where id = $2;",
+      &[&email, &id.as_i32()?]
+    )
+    .map_err(|err| utils::conflict(err.into(), "User with this email already exists"))?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id doesn't exist")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub fn change_password_wt(
+    &self,
+    id: &models::UserId, old_password: &str, new_password: &str,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    if new_password.is_empty() {
+      return Err(
+        Box::new(
+          ValidationError::new("Password should be non-empty")
+        )
+      );
+    }
+
+    let id = id.as_i32()?;
+    let old_password = format!("{:x}", md5::compute(old_password));
+    let new_password = format!("{:x}", md5::compute(new_password));
+
+    let res = transaction.execute(
+      "update users set password = $1 where id = $2 and password = $3;",
+      &[&new_password, &id, &old_password]
+    )?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("User with this id and password doesn't exist")
+        )
+      );
+    }
+
+    transaction.execute("delete from sessions where user_id = $1;", &[&id])?;
+
+    Ok(())
+  }
+
+  // Follows, webhooks and notifications go with the user by cascade
+  pub fn delete_wt(
+    &self,
+    id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    let user_id = id.as_i32()?;
+
+    transaction.query_opt("select id from users where id = $1 for update;", &[&user_id])?
+      .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
+
+    transaction.execute("delete from sessions where user_id = $1;", &[&user_id])?;
+    transaction.execute("delete from likes where user_id = $1;", &[&user_id])?;
+    transaction.execute("delete from settings where user_id = $1;", &[&user_id])?;
+    transaction.execute("update posts set user_id = null where user_id = $1;", &[&user_id])?;
+    transaction.execute("delete from users where id = $1;", &[&user_id])?;
+
+    super::Outbox::new().append_wt(
+      &models::Event::UserDeleted { user_id: *id },
+      transaction
+    )?;
+
+    Ok(())
+  }
+
+  pub fn export_personal_data_wt(
+    &self,
+    id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    let row = transaction.query_opt(
+      "
+        select
+          u.id user_id, u.first_name, u.last_name, u.email,
+          s.id settings_id, s.posts_per_page, s.display_email
+        from
+          users u, settings s
+        where
+          s.user_id = u.id
+          and u.id = $1;
+      ",
+      &[&id.as_i32()?]
+    )?
+    .ok_or(NotFoundError::new("User with this id doesn't exist"))?;
+
+    let user = self.read(&row);
+
+    let sessions = transaction.query(
+      "select code from sessions where user_id = $1 order by code;",
+      &[&id.as_i32()?]
+    )?
+    .iter()
+    .map(|row| {
+      let code: String = row.get("code");
+
+      // Postgres doesn't keep session metadata
+      models::SessionExport::new(&models::SessionCode::new(&code), models::SessionMetadata::new())
+    })
+    .collect();
+
+    let post_repository = super::Post::new();
+
+    Ok(
+      models::PersonalData {
+        user,
+        sessions,
+        posts: post_repository.authored_list_wt(id, transaction)?,
+        liked: post_repository.liked_list_wt(id, transaction)?
+      }
+    )
+  }
+
   pub fn read(&self, row: &postgres::Row) -> models::User {
     let user_id: i32 = row.get("user_id");
     let settings_id: i32 = row.get("settings_id");
 
-    let user_id = user_id.to_string();
+    let user_id = models::UserId::from(user_id);
     let settings_id = settings_id.to_string();
 
     models::User {
-      id: user_id.clone(),
+      id: user_id,
       first_name: row.get("first_name"),
       last_name: row.get("last_name"),
       email: row.get("email"),
@@ -211,7 +482,7 @@ impl User {
 
       settings: models::Settings {
         id: settings_id,
-        user_id: user_id,
+        user_id,
         display_email: row.get("display_email"),
         posts_per_page: row.get("posts_per_page")
       }
@@ -226,14 +497,15 @@ mod tests {
   use dotenv::dotenv;
 
   use super::utils;
-  use crate::{models, repository};
+  use crate::{models, repository::{self, User}};
+  use crate::utils::error::{ConflictError, NotFoundError, StringError};
 
   #[test]
   fn test_user() -> Result<(), Box<dyn error::Error>> {
     dotenv().ok();
 
     let mut user = models::User {
-      id: String::new(),
+      id: models::UserId::new(),
       first_name: "__test_1__".to_owned(),
       last_name: "__test_1__".to_owned(),
       email: Some("__test_1__@1.again".to_owned()),
@@ -292,22 +564,171 @@ mod tests {
     assert_eq!(user_settings_2.settings.display_email, user_settings.settings.display_email);
     assert_eq!(user_settings_2.settings.posts_per_page, user_settings.settings.posts_per_page);
 
-    let code = uuid::Uuid::new_v4().to_string();
+    let code = models::SessionCode::generate();
 
     session_repository.create_wt(
-      &user_id, code.as_str(), 
+      &user_id, &code, 
       &mut transaction
     )?;    
 
     let user_id = session_repository.get_user_id_wt(
-      code.as_str(), 
+      &code, 
       &mut transaction
     )?;
 
     assert_eq!(user_id, user.id);    
 
+    session_repository.delete_wt(&code, &mut transaction)?;
+
+    assert!(session_repository.get_user_id_wt(&code, &mut transaction).is_err());
+
     transaction.rollback()?;
 
     Ok(())
   }
+
+  #[test]
+  fn test_account() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut author = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_9__".to_owned(),
+      last_name: "__test_9__".to_owned(),
+      email: Some("__test_9__@9.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let mut reader = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_10__".to_owned(),
+      last_name: "__test_10__".to_owned(),
+      email: Some("__test_10__@10.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::postgresql::User::new();
+    let session_repository = repository::postgresql::Session::new();
+    let post_repository = repository::postgresql::Post::new();
+    let like_repository = repository::postgresql::Like::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let author_id = user_repository.create_wt(&mut author, &mut transaction)?;
+    let reader_id = user_repository.create_wt(&mut reader, &mut transaction)?;
+
+    user_repository.edit_profile_wt(&author_id, "__test_9_renamed__", "__test_9__", &mut transaction)?;
+    user_repository.change_email_wt(&author_id, "__test_9_renamed__@9.again", &mut transaction)?;
+
+    assert_eq!(user_repository.get_user_settings_wt(&author_id, &mut transaction)?.first_name, "__test_9_renamed__");
+    assert_eq!(user_repository.get_id_wt("__test_9_renamed__@9.again", "test", &mut transaction)?, author_id);
+    assert_eq!(user_repository.get_id_wt("__TEST_9_RENAMED__@9.again", "test", &mut transaction)?, author_id);
+    assert!(
+      user_repository.change_email_wt(&reader_id, "__Test_9_Renamed__@9.again", &mut transaction)
+        .unwrap_err()
+        .is::<ConflictError>()
+    );
+
+    let mut duplicate = reader.clone();
+
+    duplicate.email = Some("__TEST_10__@10.again".to_owned());
+    duplicate.password = Some("test".to_owned());
+
+    assert!(user_repository.create_wt(&mut duplicate, &mut transaction).unwrap_err().is::<ConflictError>());
+
+    let code = models::SessionCode::generate();
+
+    session_repository.create_wt(&author_id, &code, &mut transaction)?;
+
+    assert!(user_repository.change_password_wt(&author_id, "wrong", "changed", &mut transaction).is_err());
+    assert_eq!(session_repository.get_user_id_wt(&code, &mut transaction)?, author_id);
+
+    user_repository.change_password_wt(&author_id, "test", "changed", &mut transaction)?;
+
+    assert_eq!(user_repository.get_id_wt("__test_9_renamed__@9.again", "changed", &mut transaction)?, author_id);
+    assert!(session_repository.get_user_id_wt(&code, &mut transaction).is_err());
+
+    let post_id = post_repository.create_wt(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "__test_9__".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author)
+      },
+      &mut transaction
+    )?;
+
+    like_repository.create_wt(&reader_id, &post_id, &mut transaction)?;
+    session_repository.create_wt(&reader_id, &code, &mut transaction)?;
+
+    let personal_data = user_repository.export_personal_data_wt(&author_id, &mut transaction)?;
+
+    assert_eq!(personal_data.user.email.as_deref(), Some("__test_9_renamed__@9.again"));
+    assert!(personal_data.sessions.is_empty());
+    assert_eq!(personal_data.posts[0].id, post_id);
+    assert!(personal_data.liked.is_empty());
+
+    let personal_data = user_repository.export_personal_data_wt(&reader_id, &mut transaction)?;
+
+    assert_eq!(personal_data.sessions.len(), 1);
+    assert_ne!(personal_data.sessions[0].code_hash, code.as_str());
+    assert!(personal_data.posts.is_empty());
+    assert_eq!(personal_data.liked[0].id, post_id);
+
+    user_repository.delete_wt(&author_id, &mut transaction)?;
+
+    assert!(user_repository.get_user_settings_wt(&author_id, &mut transaction).is_err());
+    assert!(user_repository.delete_wt(&author_id, &mut transaction).is_err());
+
+    let post = post_repository.get_wt(&post_id, Some(&reader_id), &mut transaction)?;
+
+    assert!(post.author.is_none());
+    assert!(post.liked);
+
+    transaction.rollback()?;
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_rollback() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_11__".to_owned(),
+      last_name: "__test_11__".to_owned(),
+      email: Some("__test_11__@11.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::postgresql::User::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let user_id = user_repository.create_wt(&mut user, &mut transaction)?;
+
+    // Whatever fails after the insert takes the user and its event with it
+    let res: Result<(), Box<dyn error::Error>> = Err(Box::new(StringError::new("Failed after the insert")));
+
+    assert!(utils::commit_or_rollback(transaction, res).is_err());
+
+    assert!(user_repository.get_id("__test_11__@11.again", "test").unwrap_err().is::<NotFoundError>());
+
+    let row = connection.query_one("select count(*) from outbox where user_id = $1;", &[&user_id.as_i32()?])?;
+
+    assert_eq!(row.get::<_, i64>(0), 0);
+
+    Ok(())
+  }
 }
@@ -0,0 +1,557 @@
+use std::{error, time::SystemTime};
+
+use postgres;
+
+use crate::repository;
+use crate::models;
+
+use crate::utils::error::{DecodeError, NotFoundError};
+
+use super::utils;
+
+#[derive(Default)]
+pub struct Webhook {
+  pool: utils::Pool
+}
+
+impl repository::Webhook for Webhook {
+  fn create(&self, webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.create_wt(webhook, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.list_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn delete(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.delete_wt(id, user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn subscribers(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.subscribers_wt(topic, owner, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn record(&self, delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.record_wt(delivery, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn due(
+    &self,
+    now: SystemTime, limit: usize
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.due_wt(now, limit, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn deliveries(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.deliveries_wt(id, user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+
+  fn dead_letters(&self, user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let mut connection = self.pool.get()?;
+
+    let mut transaction = connection.transaction()?;
+
+    let res = self.dead_letters_wt(user_id, &mut transaction);
+
+    transaction.commit()?;
+
+    res
+  }
+}
+
+impl Webhook {
+  pub fn new() -> Self {
+    Self::with_pool(utils::Pool::new())
+  }
+
+  pub fn with_url(url: &str) -> Self {
+    Self::with_pool(utils::Pool::with_url(url))
+  }
+
+  pub fn with_pool(pool: utils::Pool) -> Self {
+    Self { pool }
+  }
+
+  pub fn create_wt(
+    &self,
+    webhook: &models::Webhook,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    let author_id = webhook.topic.author_id().map(|author_id| author_id.as_i32()).transpose()?;
+
+    let row = transaction.query_one(
+      "
+        insert into webhooks(user_id, url, secret, kind, author_id)
+        values ($1, $2, $3, $4, $5)
+        returning id;
+      ",
+      &[&webhook.user_id.as_i32()?, &webhook.url, &webhook.secret, &webhook.topic.kind(), &author_id]
+    )?;
+
+    let webhook_id: i32 = row.get(0);
+
+    Ok(models::WebhookId::from(webhook_id))
+  }
+
+  pub fn list_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          id, user_id, url, secret, kind, author_id
+        from
+          webhooks
+        where
+          user_id = $1
+        order by
+          id;
+      ",
+      &[&user_id.as_i32()?]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read(row))
+      .collect()
+  }
+
+  pub fn delete_wt(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<(), Box<dyn error::Error>> {
+    // Deliveries go with the webhook, on delete cascade
+    let res = transaction.execute(
+      "delete from webhooks where id = $1 and user_id = $2;",
+      &[&id.as_i32()?, &user_id.as_i32()?]
+    )?;
+
+    if res == 0 {
+      return Err(
+        Box::new(
+          NotFoundError::new("Webhook with this id not found")
+        )
+      );
+    }
+
+    Ok(())
+  }
+
+  pub fn subscribers_wt(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    let author_id = topic.author_id().map(|author_id| author_id.as_i32()).transpose()?;
+    let owner = owner.map(|owner| owner.as_i32()).transpose()?;
+
+    let rows = transaction.query(
+      "
+        select
+          id, user_id, url, secret, kind, author_id
+        from
+          webhooks
+        where
+          kind = $1
+          and author_id is not distinct from $2
+          and ($3::integer is null or user_id = $3)
+        order by
+          id;
+      ",
+      &[&topic.kind(), &author_id, &owner]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read(row))
+      .collect()
+  }
+
+  pub fn record_wt(
+    &self,
+    delivery: &models::Delivery,
+    transaction: &mut postgres::Transaction
+  ) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    let row = transaction.query_one(
+      "
+        insert into webhook_deliveries(
+          webhook_id, event_id, attempt, status, response_status, error, payload, created_at, next_attempt_at
+        )
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        returning id;
+      ",
+      &[
+        &delivery.webhook_id.as_i32()?, &delivery.event_id.as_i32()?, &delivery.attempt,
+        &delivery.status.as_str(), &delivery.response_status, &delivery.error,
+        &delivery.payload, &delivery.created_at, &delivery.next_attempt_at
+      ]
+    )?;
+
+    let delivery_id: i32 = row.get(0);
+
+    transaction.execute(
+      "
+        update
+          webhook_deliveries
+        set
+          next_attempt_at = null
+        where
+          webhook_id = $1
+          and event_id = $2
+          and id <> $3
+          and next_attempt_at is not null;
+      ",
+      &[&delivery.webhook_id.as_i32()?, &delivery.event_id.as_i32()?, &delivery_id]
+    )?;
+
+    Ok(models::DeliveryId::from(delivery_id))
+  }
+
+  pub fn due_wt(
+    &self,
+    now: SystemTime, limit: usize,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          id, webhook_id, event_id, attempt, status, response_status, error, payload, created_at, next_attempt_at
+        from
+          webhook_deliveries
+        where
+          next_attempt_at <= $1
+        order by
+          next_attempt_at
+        limit $2;
+      ",
+      &[&now, &(limit as i64)]
+    )?;
+
+    let deliveries = rows.iter()
+      .map(|row| self.read_delivery(row))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    let webhook_ids = deliveries.iter()
+      .map(|delivery| delivery.webhook_id.as_i32())
+      .collect::<Result<Vec<i32>, _>>()?;
+
+    let rows = transaction.query(
+      "
+        select
+          id, user_id, url, secret, kind, author_id
+        from
+          webhooks
+        where
+          id = any($1);
+      ",
+      &[&webhook_ids]
+    )?;
+
+    let webhooks = rows.iter()
+      .map(|row| self.read(row))
+      .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(
+      deliveries.into_iter()
+        .filter_map(|delivery| {
+          webhooks.iter()
+            .find(|webhook| webhook.id == delivery.webhook_id)
+            .map(|webhook| (webhook.clone(), delivery))
+        })
+        .collect()
+    )
+  }
+
+  pub fn deliveries_wt(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    transaction.query_opt(
+      "select id from webhooks where id = $1 and user_id = $2;",
+      &[&id.as_i32()?, &user_id.as_i32()?]
+    )?
+    .ok_or(NotFoundError::new("Webhook with this id not found"))?;
+
+    let rows = transaction.query(
+      "
+        select
+          id, webhook_id, event_id, attempt, status, response_status, error, payload, created_at, next_attempt_at
+        from
+          webhook_deliveries
+        where
+          webhook_id = $1
+        order by
+          id desc;
+      ",
+      &[&id.as_i32()?]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read_delivery(row))
+      .collect()
+  }
+
+  pub fn dead_letters_wt(
+    &self,
+    user_id: &models::UserId,
+    transaction: &mut postgres::Transaction
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    let rows = transaction.query(
+      "
+        select
+          d.id, d.webhook_id, d.event_id, d.attempt, d.status,
+          d.response_status, d.error, d.payload, d.created_at, d.next_attempt_at
+        from
+          webhook_deliveries d, webhooks w
+        where
+          d.webhook_id = w.id
+          and d.status = 'dead'
+          and w.user_id = $1
+        order by
+          d.id desc;
+      ",
+      &[&user_id.as_i32()?]
+    )?;
+
+    rows.iter()
+      .map(|row| self.read_delivery(row))
+      .collect()
+  }
+
+  pub fn read(&self, row: &postgres::Row) -> Result<models::Webhook, Box<dyn error::Error>> {
+    let id: i32 = row.get("id");
+    let user_id: i32 = row.get("user_id");
+    let kind: String = row.get("kind");
+    let author_id: Option<i32> = row.get("author_id");
+
+    let topic = models::Topic::from_parts(&kind, author_id.map(models::UserId::from))
+      .ok_or(DecodeError::new("webhooks", Some(&id.to_string()), "kind", &format!("{:?} isn't a topic", kind)))?;
+
+    Ok(
+      models::Webhook {
+        id: models::WebhookId::from(id),
+        user_id: models::UserId::from(user_id),
+        url: row.get("url"),
+        secret: row.get("secret"),
+        topic
+      }
+    )
+  }
+
+  pub fn read_delivery(&self, row: &postgres::Row) -> Result<models::Delivery, Box<dyn error::Error>> {
+    let id: i32 = row.get("id");
+    let webhook_id: i32 = row.get("webhook_id");
+    let event_id: i32 = row.get("event_id");
+    let status: String = row.get("status");
+    let created_at: SystemTime = row.get("created_at");
+
+    let status = models::DeliveryStatus::parse(&status)
+      .ok_or(DecodeError::new("webhook_deliveries", Some(&id.to_string()), "status", &format!("{:?} isn't a status", status)))?;
+
+    Ok(
+      models::Delivery {
+        id: models::DeliveryId::from(id),
+        webhook_id: models::WebhookId::from(webhook_id),
+        event_id: models::EventId::from(event_id),
+        attempt: row.get("attempt"),
+        status,
+        response_status: row.get("response_status"),
+        error: row.get("error"),
+        payload: row.get("payload"),
+        created_at,
+        next_attempt_at: row.get("next_attempt_at")
+      }
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, time::SystemTime};
+
+  use dotenv::dotenv;
+
+  use super::utils;
+  use crate::{models, repository};
+
+  #[test]
+  fn test_webhook() -> Result<(), Box<dyn error::Error>> {
+    dotenv().ok();
+
+    let mut user = models::User {
+      id: models::UserId::new(),
+      first_name: "__test_4__".to_owned(),
+      last_name: "__test_4__".to_owned(),
+      email: Some("__test_4__@4.again".to_owned()),
+      password: Some("test".to_owned()),
+
+      settings: models::Settings::new()
+    };
+
+    let user_repository = repository::postgresql::User::new();
+    let webhook_repository = repository::postgresql::Webhook::new();
+
+    let mut connection = utils::connect()?;
+    let mut transaction = connection.transaction()?;
+
+    let user_id = user_repository.create_wt(&mut user, &mut transaction)?;
+
+    let webhook_id = webhook_repository.create_wt(
+      &models::Webhook {
+        id: models::WebhookId::new(),
+        user_id,
+        url: "http://127.0.0.1/hook".to_owned(),
+        secret: "secret".to_owned(),
+        topic: models::Topic::PostLiked
+      },
+      &mut transaction
+    )?;
+
+    let webhooks = webhook_repository.list_wt(&user_id, &mut transaction)?;
+
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].id, webhook_id);
+    assert_eq!(webhooks[0].topic, models::Topic::PostLiked);
+
+    let subscribers = webhook_repository.subscribers_wt(
+      &models::Topic::PostLiked, Some(&user_id),
+      &mut transaction
+    )?;
+
+    assert_eq!(subscribers.len(), 1);
+
+    let event_id = repository::postgresql::Outbox::new().append_wt(
+      &models::Event::UserRegistered { user_id },
+      &mut transaction
+    )?;
+
+    webhook_repository.record_wt(
+      &models::Delivery {
+        id: models::DeliveryId::new(),
+        webhook_id,
+        event_id,
+        attempt: 1,
+        status: models::DeliveryStatus::Dead,
+        response_status: Some(500),
+        error: None,
+        payload: "{}".to_owned(),
+        created_at: SystemTime::now(),
+        next_attempt_at: None
+      },
+      &mut transaction
+    )?;
+
+    let deliveries = webhook_repository.deliveries_wt(&webhook_id, &user_id, &mut transaction)?;
+
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].response_status, Some(500));
+
+    assert_eq!(webhook_repository.dead_letters_wt(&user_id, &mut transaction)?.len(), 1);
+
+    let failed = models::Delivery {
+      id: models::DeliveryId::new(),
+      webhook_id,
+      event_id,
+      attempt: 1,
+      status: models::DeliveryStatus::Failed,
+      response_status: Some(503),
+      error: None,
+      payload: "{}".to_owned(),
+      created_at: SystemTime::now(),
+      next_attempt_at: Some(SystemTime::now())
+    };
+
+    let failed_id = webhook_repository.record_wt(&failed, &mut transaction)?;
+
+    let due = webhook_repository.due_wt(SystemTime::now(), 1000, &mut transaction)?;
+
+    assert!(due.iter().any(|(webhook, delivery)| webhook.id == webhook_id && delivery.id == failed_id));
+
+    // Recording the retry settles the failed attempt
+    webhook_repository.record_wt(
+      &models::Delivery {
+        attempt: 2,
+        status: models::DeliveryStatus::Delivered,
+        next_attempt_at: None,
+        ..failed
+      },
+      &mut transaction
+    )?;
+
+    let due = webhook_repository.due_wt(SystemTime::now(), 1000, &mut transaction)?;
+
+    assert!(due.iter().all(|(_, delivery)| delivery.id != failed_id));
+
+    webhook_repository.delete_wt(&webhook_id, &user_id, &mut transaction)?;
+
+    assert!(webhook_repository.list_wt(&user_id, &mut transaction)?.is_empty());
+
+    transaction.rollback()?;
+
+    Ok(())
+  }
+}
@@ -1,7 +1,46 @@
 use std::error;
 
-pub trait Session {
-  fn get_user_id(&self, code: &str) -> Result<String, Box<dyn error::Error>>;
+use crate::models;
 
-  fn create(&self, user_id: &str, code: &str) -> Result<(), Box<dyn error::Error>>;
+/// Login sessions, identified by a random code
+pub trait Session: Send + Sync {
+  /// The user the session belongs to
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>>;
+
+  /// Starts a session for the user
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>>;
+
+  /// Starts a session and records where it came from, if the backend keeps that
+  fn create_with_metadata(
+    &self, 
+    user_id: &models::UserId, code: &models::SessionCode, 
+    _metadata: &models::SessionMetadata
+  ) -> Result<(), Box<dyn error::Error>> {
+    self.create(user_id, code)
+  }
+
+  /// Revokes the session, deleting an unknown code is not an error
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>>;
+}
+
+impl<T: Session + ?Sized> Session for Box<T> {
+  fn get_user_id(&self, code: &models::SessionCode) -> Result<models::UserId, Box<dyn error::Error>> {
+    (**self).get_user_id(code)
+  }
+
+  fn create(&self, user_id: &models::UserId, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    (**self).create(user_id, code)
+  }
+
+  fn create_with_metadata(
+    &self, 
+    user_id: &models::UserId, code: &models::SessionCode, 
+    metadata: &models::SessionMetadata
+  ) -> Result<(), Box<dyn error::Error>> {
+    (**self).create_with_metadata(user_id, code, metadata)
+  }
+
+  fn delete(&self, code: &models::SessionCode) -> Result<(), Box<dyn error::Error>> {
+    (**self).delete(code)
+  }
 }
@@ -0,0 +1,116 @@
+use std::{error, fmt};
+
+use crate::models;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Entity {
+  Users,
+  Settings,
+  Sessions,
+  Posts,
+  Likes,
+  Follows
+}
+
+impl Entity {
+  pub const ALL: [Entity; 6] = [
+    Entity::Users, Entity::Settings, Entity::Sessions, Entity::Posts, Entity::Likes, Entity::Follows
+  ];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Entity::Users => "users",
+      Entity::Settings => "settings",
+      Entity::Sessions => "sessions",
+      Entity::Posts => "posts",
+      Entity::Likes => "likes",
+      Entity::Follows => "follows"
+    }
+  }
+}
+
+impl fmt::Display for Entity {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.name())
+  }
+}
+
+// A user together with their settings, as stored: the password
+// is the hash the backend keeps, never the plain text
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct UserRecord {
+  pub id: models::UserId,
+  pub first_name: String,
+  pub last_name: String,
+  pub email: Option<String>,
+  pub password_hash: Option<String>,
+  pub posts_per_page: i32,
+  pub display_email: bool
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionRecord {
+  pub user_id: models::UserId,
+  pub code: models::SessionCode,
+  pub user_agent: Option<String>,
+  pub ip: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PostRecord {
+  pub id: models::PostId,
+  pub user_id: Option<models::UserId>,
+  pub title: String,
+  pub text: Option<String>,
+  pub description: Option<String>
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LikeRecord {
+  pub user_id: models::UserId,
+  pub post_id: models::PostId
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FollowRecord {
+  pub follower_id: models::UserId,
+  pub author_id: models::UserId
+}
+
+// Bulk access to the stored data, bypassing the rules the other
+// repositories apply, so that it can be copied between backends.
+// Inserted records get new ids, which are returned in order. Each
+// insert is one transaction that also records the source ids it
+// copied, so inserting a record again returns its earlier id instead
+// of a second copy, and sessions, likes and follows the target
+// already has are skipped
+pub trait Transfer {
+  fn count(&self, entity: Entity) -> Result<u64, Box<dyn error::Error>>;
+
+  fn users(&self) -> Result<Vec<UserRecord>, Box<dyn error::Error>>;
+
+  fn sessions(&self) -> Result<Vec<SessionRecord>, Box<dyn error::Error>>;
+
+  fn posts(&self) -> Result<Vec<PostRecord>, Box<dyn error::Error>>;
+
+  fn likes(&self) -> Result<Vec<LikeRecord>, Box<dyn error::Error>>;
+
+  fn follows(&self) -> Result<Vec<FollowRecord>, Box<dyn error::Error>>;
+
+  fn insert_users(&self, users: &[UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>>;
+
+  fn insert_sessions(&self, sessions: &[SessionRecord]) -> Result<(), Box<dyn error::Error>>;
+
+  fn insert_posts(&self, posts: &[PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>>;
+
+  fn insert_likes(&self, likes: &[LikeRecord]) -> Result<(), Box<dyn error::Error>>;
+
+  fn insert_follows(&self, follows: &[FollowRecord]) -> Result<(), Box<dyn error::Error>>;
+}
@@ -2,12 +2,72 @@ use std::error;
 
 use crate::models;
 
-pub trait User {
-  fn create(&self, user: &mut models::User) -> Result<String, Box<dyn error::Error>>;
+/// Accounts and their settings
+pub trait User: Send + Sync {
+  /// Stores the user with their settings and sets `user.id`
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>>;
 
-  fn get_id(&self, email: &str, password: &str) -> Result<String, Box<dyn error::Error>>;
+  /// Looks a user up by their credentials, for logging in
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>>;
 
-  fn get_user_settings(&self, id: &str) -> Result<models::User, Box<dyn error::Error>>;
+  /// The user with their settings, without the password
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>>;
 
+  /// Saves the settings of the user they belong to
   fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>>;
+
+  /// Renames the user
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>>;
+
+  /// Changes the email the user logs in with
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>>;
+
+  /// Sets a new password if the old one matches, `NotFoundError` if it doesn't,
+  /// and ends all the user's sessions
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>>;
+
+  /// Deletes the user with their sessions, likes, follows, webhooks and
+  /// notifications, their posts stay without an author
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>>;
+
+  /// The user's profile, settings, active sessions, posts and liked posts
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>>;
+}
+
+impl<T: User + ?Sized> User for Box<T> {
+  fn create(&self, user: &mut models::User) -> Result<models::UserId, Box<dyn error::Error>> {
+    (**self).create(user)
+  }
+
+  fn get_id(&self, email: &str, password: &str) -> Result<models::UserId, Box<dyn error::Error>> {
+    (**self).get_id(email, password)
+  }
+
+  fn get_user_settings(&self, id: &models::UserId) -> Result<models::User, Box<dyn error::Error>> {
+    (**self).get_user_settings(id)
+  }
+
+  fn edit(&self, settings: &models::Settings) -> Result<(), Box<dyn error::Error>> {
+    (**self).edit(settings)
+  }
+
+  fn edit_profile(&self, id: &models::UserId, first_name: &str, last_name: &str) -> Result<(), Box<dyn error::Error>> {
+    (**self).edit_profile(id, first_name, last_name)
+  }
+
+  fn change_email(&self, id: &models::UserId, email: &str) -> Result<(), Box<dyn error::Error>> {
+    (**self).change_email(id, email)
+  }
+
+  fn change_password(&self, id: &models::UserId, old_password: &str, new_password: &str) -> Result<(), Box<dyn error::Error>> {
+    (**self).change_password(id, old_password, new_password)
+  }
+
+  fn delete(&self, id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    (**self).delete(id)
+  }
+
+  fn export_personal_data(&self, id: &models::UserId) -> Result<models::PersonalData, Box<dyn error::Error>> {
+    (**self).export_personal_data(id)
+  }
 }
@@ -0,0 +1,83 @@
+use std::{error, time::SystemTime};
+
+use crate::models;
+
+/// Callback urls users register for events, and the attempts to deliver them
+pub trait Webhook: Send + Sync {
+  /// Registers the webhook for its `user_id`
+  fn create(&self, webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>>;
+
+  /// The user's webhooks, secrets included
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>>;
+
+  /// Removes the webhook and its deliveries, if the user owns it
+  fn delete(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<(), Box<dyn error::Error>>;
+
+  /// Webhooks for the topic, only the owner's if `owner` is set
+  fn subscribers(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>>;
+
+  /// Records one delivery attempt, the earlier attempts for the same
+  /// webhook and event are no longer due
+  fn record(&self, delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>>;
+
+  /// Failed attempts whose retry is due by `now`, with their webhook, soonest first
+  fn due(
+    &self,
+    now: SystemTime, limit: usize
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>>;
+
+  /// Attempts for the webhook, newest first, if the user owns it
+  fn deliveries(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>>;
+
+  /// Deliveries to the user's webhooks that ran out of attempts, newest first
+  fn dead_letters(&self, user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>>;
+}
+
+impl<T: Webhook + ?Sized> Webhook for Box<T> {
+  fn create(&self, webhook: &models::Webhook) -> Result<models::WebhookId, Box<dyn error::Error>> {
+    (**self).create(webhook)
+  }
+
+  fn list(&self, user_id: &models::UserId) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    (**self).list(user_id)
+  }
+
+  fn delete(&self, id: &models::WebhookId, user_id: &models::UserId) -> Result<(), Box<dyn error::Error>> {
+    (**self).delete(id, user_id)
+  }
+
+  fn subscribers(
+    &self,
+    topic: &models::Topic, owner: Option<&models::UserId>
+  ) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    (**self).subscribers(topic, owner)
+  }
+
+  fn record(&self, delivery: &models::Delivery) -> Result<models::DeliveryId, Box<dyn error::Error>> {
+    (**self).record(delivery)
+  }
+
+  fn due(
+    &self,
+    now: SystemTime, limit: usize
+  ) -> Result<Vec<(models::Webhook, models::Delivery)>, Box<dyn error::Error>> {
+    (**self).due(now, limit)
+  }
+
+  fn deliveries(
+    &self,
+    id: &models::WebhookId, user_id: &models::UserId
+  ) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    (**self).deliveries(id, user_id)
+  }
+
+  fn dead_letters(&self, user_id: &models::UserId) -> Result<Vec<models::Delivery>, Box<dyn error::Error>> {
+    (**self).dead_letters(user_id)
+  }
+}
@@ -0,0 +1,396 @@
+use std::{
+  collections::HashMap, error, fs,
+  io::{BufRead, BufReader, BufWriter, Write},
+  path::{Path, PathBuf}, str::FromStr
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::models;
+use crate::repository::{self, Entity, Transfer};
+
+use crate::utils::error::StringError;
+
+use super::{Migration, State};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+  // One JSON object per line
+  #[default]
+  Jsonl,
+  // One row per record with a header line. Empty strings and nulls
+  // are both written as empty fields, so empty strings are read back
+  // as nulls
+  Csv
+}
+
+impl Format {
+  pub fn extension(&self) -> &'static str {
+    match self {
+      Format::Jsonl => "jsonl",
+      Format::Csv => "csv"
+    }
+  }
+}
+
+impl FromStr for Format {
+  type Err = StringError;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    match s {
+      "jsonl" => Ok(Format::Jsonl),
+      "csv" => Ok(Format::Csv),
+      format => Err(StringError::new(&format!("Unknown dump format: {}", format)))
+    }
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExportOptions {
+  pub format: Format,
+  pub include_password_hashes: bool
+}
+
+// Settings get their own file, like in Postgres, so that each
+// file is flat enough for CSV
+#[derive(Debug, Serialize, Deserialize)]
+struct UserRow {
+  id: models::UserId,
+  first_name: String,
+  last_name: String,
+  email: Option<String>,
+  #[serde(default)]
+  password_hash: Option<String>
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SettingsRow {
+  user_id: models::UserId,
+  posts_per_page: i32,
+  display_email: bool
+}
+
+const ENTITIES: [Entity; 5] = [Entity::Users, Entity::Settings, Entity::Posts, Entity::Likes, Entity::Follows];
+
+// Writes users, settings, posts, likes and follows into one file
+// each inside the directory. Sessions are never exported
+pub fn export(
+  source: &dyn Transfer,
+  dir: &Path,
+  options: &ExportOptions
+) -> Result<Vec<(Entity, usize)>, Box<dyn error::Error>> {
+  fs::create_dir_all(dir)?;
+
+  let dump = Dump::new(dir, options.format);
+
+  let users = source.users()?;
+
+  let user_rows: Vec<UserRow> = users.iter()
+    .map(|user| UserRow {
+      id: user.id,
+      first_name: user.first_name.clone(),
+      last_name: user.last_name.clone(),
+      email: user.email.clone(),
+      password_hash: if options.include_password_hashes { user.password_hash.clone() } else { None }
+    })
+    .collect();
+
+  let settings_rows: Vec<SettingsRow> = users.iter()
+    .map(|user| SettingsRow {
+      user_id: user.id,
+      posts_per_page: user.posts_per_page,
+      display_email: user.display_email
+    })
+    .collect();
+
+  Ok(
+    vec![
+      (Entity::Users, dump.write(Entity::Users, &user_rows)?),
+      (Entity::Settings, dump.write(Entity::Settings, &settings_rows)?),
+      (Entity::Posts, dump.write(Entity::Posts, &source.posts()?)?),
+      (Entity::Likes, dump.write(Entity::Likes, &source.likes()?)?),
+      (Entity::Follows, dump.write(Entity::Follows, &source.follows()?)?)
+    ]
+  )
+}
+
+// Restores a dump into an empty database, the target assigns new
+// ids and the references are remapped like in a migration
+pub fn import(
+  dir: &Path,
+  format: Format,
+  target: &dyn Transfer
+) -> Result<Vec<(Entity, usize)>, Box<dyn error::Error>> {
+  for entity in ENTITIES {
+    let count = target.count(entity)?;
+
+    if count > 0 {
+      return Err(
+        Box::new(
+          StringError::new(&format!("Target database is not empty: {} {}", count, entity))
+        )
+      );
+    }
+  }
+
+  let dump = Dump::new(dir, format);
+
+  Migration::new(&dump, target).run(&mut State::new(), |_| Ok(()))
+}
+
+// A dump directory read through the Transfer trait, so that it
+// can be the source of a Migration
+pub struct Dump {
+  dir: PathBuf,
+  format: Format
+}
+
+impl Dump {
+  pub fn new(dir: &Path, format: Format) -> Self {
+    Self {
+      dir: dir.to_owned(),
+      format
+    }
+  }
+
+  pub fn path(&self, entity: Entity) -> PathBuf {
+    self.dir.join(format!("{}.{}", entity.name(), self.format.extension()))
+  }
+
+  fn write<T: Serialize>(&self, entity: Entity, rows: &[T]) -> Result<usize, Box<dyn error::Error>> {
+    let path = self.path(entity);
+
+    match self.format {
+      Format::Jsonl => {
+        let mut writer = BufWriter::new(fs::File::create(path)?);
+
+        for row in rows {
+          serde_json::to_writer(&mut writer, row)?;
+          writer.write_all(b"\n")?;
+        }
+
+        writer.flush()?;
+      },
+      Format::Csv => {
+        let mut writer = csv::Writer::from_path(path)?;
+
+        for row in rows {
+          writer.serialize(row)?;
+        }
+
+        writer.flush()?;
+      }
+    }
+
+    Ok(rows.len())
+  }
+
+  fn read<T: DeserializeOwned>(&self, entity: Entity) -> Result<Vec<T>, Box<dyn error::Error>> {
+    let path = self.path(entity);
+
+    let mut rows = Vec::new();
+
+    match self.format {
+      Format::Jsonl => {
+        let reader = BufReader::new(fs::File::open(&path)?);
+
+        for (i, line) in reader.lines().enumerate() {
+          let line = line?;
+
+          if line.trim().is_empty() {
+            continue;
+          }
+
+          rows.push(
+            serde_json::from_str(&line)
+              .map_err(|err| StringError::new(&format!("{}:{}: {}", path.display(), i + 1, err)))?
+          );
+        }
+      },
+      Format::Csv => {
+        let mut reader = csv::Reader::from_path(&path)?;
+
+        for row in reader.deserialize() {
+          rows.push(
+            row.map_err(|err| StringError::new(&format!("{}: {}", path.display(), err)))?
+          );
+        }
+      }
+    }
+
+    Ok(rows)
+  }
+
+  fn read_only(&self) -> Box<dyn error::Error> {
+    Box::new(
+      StringError::new("Dumps are written with export, not through Transfer")
+    )
+  }
+}
+
+impl repository::Transfer for Dump {
+  fn count(&self, entity: Entity) -> Result<u64, Box<dyn error::Error>> {
+    let count = match entity {
+      Entity::Users => self.read::<UserRow>(entity)?.len(),
+      Entity::Settings => self.read::<SettingsRow>(entity)?.len(),
+      Entity::Sessions => 0,
+      Entity::Posts => self.read::<repository::PostRecord>(entity)?.len(),
+      Entity::Likes => self.read::<repository::LikeRecord>(entity)?.len(),
+      Entity::Follows => self.follows()?.len()
+    };
+
+    Ok(count as u64)
+  }
+
+  fn users(&self) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+    let mut settings: HashMap<models::UserId, SettingsRow> = self.read::<SettingsRow>(Entity::Settings)?
+      .into_iter()
+      .map(|row| (row.user_id, row))
+      .collect();
+
+    let defaults = models::Settings::new();
+
+    Ok(
+      self.read::<UserRow>(Entity::Users)?
+        .into_iter()
+        .map(|user| {
+          let settings = settings.remove(&user.id);
+
+          repository::UserRecord {
+            id: user.id,
+            first_name: user.first_name,
+            last_name: user.last_name,
+            email: user.email,
+            // Without the hash the user is restored with an empty one,
+            // which no password matches, instead of a null the
+            // backends reject
+            password_hash: Some(user.password_hash.unwrap_or_default()),
+            posts_per_page: settings.as_ref()
+              .map(|settings| settings.posts_per_page)
+              .unwrap_or(defaults.posts_per_page),
+            display_email: settings.as_ref()
+              .map(|settings| settings.display_email)
+              .unwrap_or(defaults.display_email)
+          }
+        })
+        .collect()
+    )
+  }
+
+  fn sessions(&self) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+    Ok(Vec::new())
+  }
+
+  fn posts(&self) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+    self.read(Entity::Posts)
+  }
+
+  fn likes(&self) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+    self.read(Entity::Likes)
+  }
+
+  // Dumps from before follows were exported have no file for them
+  fn follows(&self) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+    if !self.path(Entity::Follows).exists() {
+      return Ok(Vec::new());
+    }
+
+    self.read(Entity::Follows)
+  }
+
+  fn insert_users(&self, _users: &[repository::UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+    Err(self.read_only())
+  }
+
+  fn insert_sessions(&self, _sessions: &[repository::SessionRecord]) -> Result<(), Box<dyn error::Error>> {
+    Err(self.read_only())
+  }
+
+  fn insert_posts(&self, _posts: &[repository::PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+    Err(self.read_only())
+  }
+
+  fn insert_likes(&self, _likes: &[repository::LikeRecord]) -> Result<(), Box<dyn error::Error>> {
+    Err(self.read_only())
+  }
+
+  fn insert_follows(&self, _follows: &[repository::FollowRecord]) -> Result<(), Box<dyn error::Error>> {
+    Err(self.read_only())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{env, fs};
+
+  use crate::repository::{Entity, Transfer};
+  use crate::transfer::tests::{source, Memory};
+
+  use super::{export, import, Dump, ExportOptions, Format};
+
+  #[test]
+  fn test_export_import() {
+    for format in [Format::Jsonl, Format::Csv] {
+      let dir = env::temp_dir().join(format!("db_rust_dump_{}", uuid::Uuid::new_v4()));
+
+      let source = source();
+
+      let counts = export(&source, &dir, &ExportOptions { format, include_password_hashes: false }).unwrap();
+
+      assert_eq!(counts[0], (Entity::Users, 2));
+
+      let dump = Dump::new(&dir, format);
+
+      assert!(fs::read_to_string(dump.path(Entity::Users)).unwrap().contains("a@test.test"));
+
+      let users = dump.users().unwrap();
+
+      assert_eq!(users[0].password_hash.as_deref(), Some(""));
+      assert_eq!(users[1].posts_per_page, 10);
+
+      let target = Memory::default();
+
+      import(&dir, format, &target).unwrap();
+
+      assert_eq!(target.users.borrow().len(), 2);
+      assert_eq!(target.likes.borrow().len(), 1);
+      assert_eq!(target.follows.borrow()[0].author_id, target.users.borrow()[1].id);
+      assert!(target.sessions.borrow().is_empty());
+      assert_eq!(target.posts.borrow()[0].user_id, Some(target.users.borrow()[1].id));
+
+      // Restoring twice would duplicate everything
+      assert!(import(&dir, format, &target).is_err());
+
+      export(&source, &dir, &ExportOptions { format, include_password_hashes: true }).unwrap();
+
+      assert_eq!(dump.users().unwrap()[0].password_hash, source.users.borrow()[0].password_hash);
+
+      // An older dump without follows still restores
+      fs::remove_file(dump.path(Entity::Follows)).unwrap();
+
+      assert!(dump.follows().unwrap().is_empty());
+
+      fs::remove_dir_all(&dir).unwrap();
+    }
+  }
+
+  #[test]
+  fn test_empty_strings() {
+    for (format, text) in [(Format::Jsonl, Some("")), (Format::Csv, None)] {
+      let dir = env::temp_dir().join(format!("db_rust_dump_{}", uuid::Uuid::new_v4()));
+
+      let source = source();
+
+      source.posts.borrow_mut()[0].text = Some("".to_owned());
+
+      export(&source, &dir, &ExportOptions { format, include_password_hashes: false }).unwrap();
+
+      let posts = Dump::new(&dir, format).posts().unwrap();
+
+      assert_eq!(posts[0].text.as_deref(), text);
+      assert_eq!(posts[0].description, None);
+
+      fs::remove_dir_all(&dir).unwrap();
+    }
+  }
+}
@@ -0,0 +1,609 @@
+use std::{collections::{HashMap, HashSet}, error};
+
+#[cfg(feature = "dump")]
+pub mod dump;
+
+use crate::models;
+use crate::repository::{self, Entity, Transfer};
+
+use crate::utils::error::NotFoundError;
+
+pub const BATCH_SIZE: usize = 500;
+
+// Progress of a migration, saved after every batch so that an
+// interrupted run can be resumed. Ids map source ids to target ids
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct State {
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub users: HashMap<models::UserId, models::UserId>,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub posts: HashMap<models::PostId, models::PostId>,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub sessions: HashSet<models::SessionCode>,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub likes: HashSet<(models::UserId, models::PostId)>,
+  #[cfg_attr(feature = "serde", serde(default))]
+  pub follows: HashSet<(models::UserId, models::UserId)>
+}
+
+impl State {
+  pub fn new() -> Self {
+    Self::default()
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct Validation {
+  pub counts: Vec<(Entity, u64)>,
+  pub problems: Vec<String>
+}
+
+impl Validation {
+  pub fn is_valid(&self) -> bool {
+    self.problems.is_empty()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Count {
+  pub entity: Entity,
+  pub source: u64,
+  pub target: u64
+}
+
+impl Count {
+  pub fn matches(&self) -> bool {
+    self.source == self.target
+  }
+}
+
+pub struct Migration<'a> {
+  source: &'a dyn Transfer,
+  target: &'a dyn Transfer
+}
+
+impl<'a> Migration<'a> {
+  pub fn new(source: &'a dyn Transfer, target: &'a dyn Transfer) -> Self {
+    Self { source, target }
+  }
+
+  // Reads everything from the source without writing anything and
+  // reports the records the target would reject or lose
+  pub fn validate(&self) -> Result<Validation, Box<dyn error::Error>> {
+    let users = self.source.users()?;
+    let sessions = self.source.sessions()?;
+    let posts = self.source.posts()?;
+    let likes = self.source.likes()?;
+    let follows = self.source.follows()?;
+
+    let mut validation = Validation::default();
+
+    for entity in Entity::ALL {
+      validation.counts.push((entity, self.source.count(entity)?));
+    }
+
+    let mut emails = HashMap::new();
+
+    for user in &users {
+      if user.password_hash.is_none() {
+        validation.problems.push(format!("User {} has no password", user.id));
+      }
+
+      if user.posts_per_page < 1 {
+        validation.problems.push(format!("User {} has posts_per_page {}", user.id, user.posts_per_page));
+      }
+
+      // The targets keep emails unique ignoring case
+      if let Some(email) = user.email.as_ref() {
+        if let Some(other) = emails.insert(email.to_lowercase(), email) {
+          validation.problems.push(format!("User {} has email {}, which differs only in case from {}", user.id, email, other));
+        }
+      }
+    }
+
+    let user_ids: HashSet<_> = users.iter().map(|user| user.id).collect();
+    let post_ids: HashSet<_> = posts.iter().map(|post| post.id).collect();
+
+    let mut codes = HashSet::new();
+
+    for session in &sessions {
+      if !user_ids.contains(&session.user_id) {
+        validation.problems.push(format!("Session {} belongs to unknown user {}", session.code, session.user_id));
+      }
+
+      if !codes.insert(&session.code) {
+        validation.problems.push(format!("Session {} is duplicated", session.code));
+      }
+    }
+
+    for post in &posts {
+      if let Some(user_id) = post.user_id {
+        if !user_ids.contains(&user_id) {
+          validation.problems.push(format!("Post {} has unknown author {}", post.id, user_id));
+        }
+      }
+    }
+
+    let mut pairs = HashSet::new();
+
+    for like in &likes {
+      if !user_ids.contains(&like.user_id) || !post_ids.contains(&like.post_id) {
+        validation.problems.push(format!("Like of post {} by user {} is dangling", like.post_id, like.user_id));
+      }
+
+      if !pairs.insert((like.user_id, like.post_id)) {
+        validation.problems.push(format!("Like of post {} by user {} is duplicated", like.post_id, like.user_id));
+      }
+    }
+
+    let mut follow_pairs = HashSet::new();
+
+    for follow in &follows {
+      if !user_ids.contains(&follow.follower_id) || !user_ids.contains(&follow.author_id) {
+        validation.problems.push(format!("Follow of user {} by user {} is dangling", follow.author_id, follow.follower_id));
+      }
+
+      if follow.follower_id == follow.author_id {
+        validation.problems.push(format!("User {} follows themselves", follow.follower_id));
+      }
+
+      if !follow_pairs.insert((follow.follower_id, follow.author_id)) {
+        validation.problems.push(format!("Follow of user {} by user {} is duplicated", follow.author_id, follow.follower_id));
+      }
+    }
+
+    Ok(validation)
+  }
+
+  // Copies whatever the state doesn't record as copied yet. Every batch
+  // is written in one transaction with the ids it was copied to, so
+  // after a crash before the save the target skips what it already has
+  pub fn run<F>(&self, state: &mut State, mut save: F) -> Result<Vec<(Entity, usize)>, Box<dyn error::Error>>
+  where
+    F: FnMut(&State) -> Result<(), Box<dyn error::Error>>
+  {
+    let users: Vec<_> = self.source.users()?
+      .into_iter()
+      .filter(|user| !state.users.contains_key(&user.id))
+      .collect();
+
+    for batch in users.chunks(BATCH_SIZE) {
+      let ids = self.target.insert_users(batch)?;
+
+      for (user, id) in batch.iter().zip(ids) {
+        state.users.insert(user.id, id);
+      }
+
+      save(state)?;
+    }
+
+    let mut posts = Vec::new();
+
+    for post in self.source.posts()? {
+      if !state.posts.contains_key(&post.id) {
+        posts.push(post);
+      }
+    }
+
+    for batch in posts.chunks(BATCH_SIZE) {
+      let records = batch.iter()
+        .map(|post| Ok(
+          repository::PostRecord {
+            user_id: post.user_id.map(|user_id| map_user(state, &user_id)).transpose()?,
+            ..post.clone()
+          }
+        ))
+        .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?;
+
+      let ids = self.target.insert_posts(&records)?;
+
+      for (post, id) in batch.iter().zip(ids) {
+        state.posts.insert(post.id, id);
+      }
+
+      save(state)?;
+    }
+
+    let sessions: Vec<_> = self.source.sessions()?
+      .into_iter()
+      .filter(|session| !state.sessions.contains(&session.code))
+      .collect();
+
+    for batch in sessions.chunks(BATCH_SIZE) {
+      let records = batch.iter()
+        .map(|session| Ok(
+          repository::SessionRecord {
+            user_id: map_user(state, &session.user_id)?,
+            ..session.clone()
+          }
+        ))
+        .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?;
+
+      self.target.insert_sessions(&records)?;
+
+      for session in batch {
+        state.sessions.insert(session.code.clone());
+      }
+
+      save(state)?;
+    }
+
+    let likes: Vec<_> = self.source.likes()?
+      .into_iter()
+      .filter(|like| !state.likes.contains(&(like.user_id, like.post_id)))
+      .collect();
+
+    for batch in likes.chunks(BATCH_SIZE) {
+      let records = batch.iter()
+        .map(|like| Ok(
+          repository::LikeRecord {
+            user_id: map_user(state, &like.user_id)?,
+            post_id: map_post(state, &like.post_id)?
+          }
+        ))
+        .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?;
+
+      self.target.insert_likes(&records)?;
+
+      for like in batch {
+        state.likes.insert((like.user_id, like.post_id));
+      }
+
+      save(state)?;
+    }
+
+    let follows: Vec<_> = self.source.follows()?
+      .into_iter()
+      .filter(|follow| !state.follows.contains(&(follow.follower_id, follow.author_id)))
+      .collect();
+
+    for batch in follows.chunks(BATCH_SIZE) {
+      let records = batch.iter()
+        .map(|follow| Ok(
+          repository::FollowRecord {
+            follower_id: map_user(state, &follow.follower_id)?,
+            author_id: map_user(state, &follow.author_id)?
+          }
+        ))
+        .collect::<Result<Vec<_>, Box<dyn error::Error>>>()?;
+
+      self.target.insert_follows(&records)?;
+
+      for follow in batch {
+        state.follows.insert((follow.follower_id, follow.author_id));
+      }
+
+      save(state)?;
+    }
+
+    Ok(
+      vec![
+        (Entity::Users, users.len()),
+        (Entity::Settings, users.len()),
+        (Entity::Sessions, sessions.len()),
+        (Entity::Posts, posts.len()),
+        (Entity::Likes, likes.len()),
+        (Entity::Follows, follows.len())
+      ]
+    )
+  }
+
+  pub fn verify(&self) -> Result<Vec<Count>, Box<dyn error::Error>> {
+    Entity::ALL.iter()
+      .map(|entity| Ok(
+        Count {
+          entity: *entity,
+          source: self.source.count(*entity)?,
+          target: self.target.count(*entity)?
+        }
+      ))
+      .collect()
+  }
+}
+
+fn map_user(state: &State, id: &models::UserId) -> Result<models::UserId, Box<dyn error::Error>> {
+  state.users.get(id)
+    .copied()
+    .ok_or(Box::new(NotFoundError::new(&format!("User {} was not copied", id))))
+}
+
+fn map_post(state: &State, id: &models::PostId) -> Result<models::PostId, Box<dyn error::Error>> {
+  state.posts.get(id)
+    .copied()
+    .ok_or(Box::new(NotFoundError::new(&format!("Post {} was not copied", id))))
+}
+
+#[cfg(all(test, feature = "serde"))]
+pub(crate) mod tests {
+  use std::{cell::RefCell, error};
+
+  use crate::models;
+  use crate::repository::{self, Entity, Transfer};
+  use crate::utils::error::StringError;
+
+  use super::{Migration, State};
+
+  #[derive(Default)]
+  pub(crate) struct Memory {
+    pub(crate) users: RefCell<Vec<repository::UserRecord>>,
+    pub(crate) sessions: RefCell<Vec<repository::SessionRecord>>,
+    pub(crate) posts: RefCell<Vec<repository::PostRecord>>,
+    pub(crate) likes: RefCell<Vec<repository::LikeRecord>>,
+    pub(crate) follows: RefCell<Vec<repository::FollowRecord>>,
+    next_id: RefCell<i32>
+  }
+
+  impl Memory {
+    fn next_id(&self) -> i32 {
+      *self.next_id.borrow_mut() += 1;
+
+      *self.next_id.borrow() + 100
+    }
+  }
+
+  impl repository::Transfer for Memory {
+    fn count(&self, entity: Entity) -> Result<u64, Box<dyn error::Error>> {
+      let count = match entity {
+        Entity::Users | Entity::Settings => self.users.borrow().len(),
+        Entity::Sessions => self.sessions.borrow().len(),
+        Entity::Posts => self.posts.borrow().len(),
+        Entity::Likes => self.likes.borrow().len(),
+        Entity::Follows => self.follows.borrow().len()
+      };
+
+      Ok(count as u64)
+    }
+
+    fn users(&self) -> Result<Vec<repository::UserRecord>, Box<dyn error::Error>> {
+      Ok(self.users.borrow().clone())
+    }
+
+    fn sessions(&self) -> Result<Vec<repository::SessionRecord>, Box<dyn error::Error>> {
+      Ok(self.sessions.borrow().clone())
+    }
+
+    fn posts(&self) -> Result<Vec<repository::PostRecord>, Box<dyn error::Error>> {
+      Ok(self.posts.borrow().clone())
+    }
+
+    fn likes(&self) -> Result<Vec<repository::LikeRecord>, Box<dyn error::Error>> {
+      Ok(self.likes.borrow().clone())
+    }
+
+    fn follows(&self) -> Result<Vec<repository::FollowRecord>, Box<dyn error::Error>> {
+      Ok(self.follows.borrow().clone())
+    }
+
+    fn insert_users(&self, users: &[repository::UserRecord]) -> Result<Vec<models::UserId>, Box<dyn error::Error>> {
+      Ok(
+        users.iter()
+          .map(|user| {
+            let id = models::UserId::from(self.next_id());
+
+            self.users.borrow_mut().push(repository::UserRecord { id, ..user.clone() });
+
+            id
+          })
+          .collect()
+      )
+    }
+
+    fn insert_sessions(&self, sessions: &[repository::SessionRecord]) -> Result<(), Box<dyn error::Error>> {
+      self.sessions.borrow_mut().extend_from_slice(sessions);
+
+      Ok(())
+    }
+
+    fn insert_posts(&self, posts: &[repository::PostRecord]) -> Result<Vec<models::PostId>, Box<dyn error::Error>> {
+      Ok(
+        posts.iter()
+          .map(|post| {
+            let id = models::PostId::from(self.next_id());
+
+            self.posts.borrow_mut().push(repository::PostRecord { id, ..post.clone() });
+
+            id
+          })
+          .collect()
+      )
+    }
+
+    fn insert_likes(&self, likes: &[repository::LikeRecord]) -> Result<(), Box<dyn error::Error>> {
+      self.likes.borrow_mut().extend_from_slice(likes);
+
+      Ok(())
+    }
+
+    fn insert_follows(&self, follows: &[repository::FollowRecord]) -> Result<(), Box<dyn error::Error>> {
+      self.follows.borrow_mut().extend_from_slice(follows);
+
+      Ok(())
+    }
+  }
+
+  fn user(id: i32, email: &str) -> repository::UserRecord {
+    repository::UserRecord {
+      id: models::UserId::from(id),
+      first_name: "first".to_owned(),
+      last_name: "last".to_owned(),
+      email: Some(email.to_owned()),
+      password_hash: Some(format!("{:x}", md5::compute("secret"))),
+      posts_per_page: 10,
+      display_email: false
+    }
+  }
+
+  pub(crate) fn source() -> Memory {
+    let source = Memory::default();
+
+    source.users.borrow_mut().extend([user(1, "a@test.test"), user(2, "b@test.test")]);
+
+    source.posts.borrow_mut().push(
+      repository::PostRecord {
+        id: models::PostId::from(1),
+        user_id: Some(models::UserId::from(2)),
+        title: "title".to_owned(),
+        text: None,
+        description: None
+      }
+    );
+
+    source.sessions.borrow_mut().push(
+      repository::SessionRecord {
+        user_id: models::UserId::from(1),
+        code: models::SessionCode::new("code"),
+        user_agent: None,
+        ip: None
+      }
+    );
+
+    source.likes.borrow_mut().push(
+      repository::LikeRecord {
+        user_id: models::UserId::from(1),
+        post_id: models::PostId::from(1)
+      }
+    );
+
+    source.follows.borrow_mut().push(
+      repository::FollowRecord {
+        follower_id: models::UserId::from(1),
+        author_id: models::UserId::from(2)
+      }
+    );
+
+    source
+  }
+
+  #[test]
+  fn test_validate() {
+    let source = source();
+    let target = Memory::default();
+
+    assert!(Migration::new(&source, &target).validate().unwrap().is_valid());
+
+    source.users.borrow_mut().push(user(3, "a@test.test"));
+    source.likes.borrow_mut().push(
+      repository::LikeRecord {
+        user_id: models::UserId::from(4),
+        post_id: models::PostId::from(1)
+      }
+    );
+    source.follows.borrow_mut().push(
+      repository::FollowRecord {
+        follower_id: models::UserId::from(2),
+        author_id: models::UserId::from(5)
+      }
+    );
+
+    let validation = Migration::new(&source, &target).validate().unwrap();
+
+    assert_eq!(validation.problems.len(), 3);
+    assert!(validation.problems[2].contains("Follow of user 5 by user 2 is dangling"));
+
+    // Emails that differ only in case collide as well
+    let cased = self::source();
+
+    cased.users.borrow_mut().push(user(3, "A@Test.test"));
+
+    let validation = Migration::new(&cased, &target).validate().unwrap();
+
+    assert_eq!(validation.problems, vec!["User 3 has email A@Test.test, which differs only in case from a@test.test"]);
+    assert!(target.users.borrow().is_empty());
+  }
+
+  #[test]
+  fn test_run() {
+    let source = source();
+    let target = Memory::default();
+
+    let migration = Migration::new(&source, &target);
+
+    let mut state = State::new();
+    let mut saves = 0;
+
+    // Interrupted after the users are copied
+    let res = migration.run(
+      &mut state,
+      |_| {
+        saves += 1;
+
+        if saves > 1 {
+          Err(Box::new(StringError::new("interrupted")))
+        } else {
+          Ok(())
+        }
+      }
+    );
+
+    assert!(res.is_err());
+    assert_eq!(state.users.len(), 2);
+
+    let json = serde_json::to_string(&state).unwrap();
+    let mut state: State = serde_json::from_str(&json).unwrap();
+
+    migration.run(&mut state, |_| Ok(())).unwrap();
+
+    assert!(migration.verify().unwrap().iter().all(|count| count.matches()));
+
+    let author = target.posts.borrow()[0].user_id.unwrap();
+
+    assert_eq!(author, state.users[&models::UserId::from(2)]);
+    assert_eq!(target.likes.borrow()[0].post_id, target.posts.borrow()[0].id);
+    assert_eq!(
+      target.follows.borrow()[0],
+      repository::FollowRecord {
+        follower_id: state.users[&models::UserId::from(1)],
+        author_id: author
+      }
+    );
+
+    // Nothing is left to copy
+    let copied = migration.run(&mut state, |_| Ok(())).unwrap();
+
+    assert!(copied.iter().all(|(_, count)| *count == 0));
+  }
+
+  #[test]
+  fn test_resume() {
+    let source = source();
+    let target = repository::memory::Transfer::new();
+
+    let migration = Migration::new(&source, &target);
+
+    let mut saved = serde_json::to_string(&State::new()).unwrap();
+    let mut saves = 0;
+
+    // The posts are written but the crash comes before they are saved
+    let res = migration.run(
+      &mut State::new(),
+      |state| {
+        saves += 1;
+
+        if saves > 1 {
+          return Err(Box::new(StringError::new("interrupted")));
+        }
+
+        saved = serde_json::to_string(state).unwrap();
+
+        Ok(())
+      }
+    );
+
+    assert!(res.is_err());
+    assert_eq!(target.count(Entity::Posts).unwrap(), 1);
+
+    let mut state: State = serde_json::from_str(&saved).unwrap();
+
+    assert!(state.posts.is_empty());
+
+    migration.run(&mut state, |_| Ok(())).unwrap();
+
+    assert!(migration.verify().unwrap().iter().all(|count| count.matches()));
+
+    let posts = target.posts().unwrap();
+
+    assert_eq!(state.posts[&models::PostId::from(1)], posts[0].id);
+    assert_eq!(target.likes().unwrap()[0].post_id, posts[0].id);
+  }
+}
@@ -1,5 +1,6 @@
 use std::{fmt, error};
 
+/// A plain message, for failures without more structure
 #[derive(Debug)]
 pub struct StringError(String);
 
@@ -16,3 +17,113 @@ impl fmt::Display for StringError {
 }
 
 impl error::Error for StringError { }
+
+/// An id that doesn't fit the backend, e.g. an ObjectId given to Postgres
+#[derive(Debug)]
+pub struct InvalidIdError(String);
+
+impl InvalidIdError {
+  pub fn new(id: &str) -> Self {
+    Self(id.to_owned())
+  }
+
+  pub fn id(&self) -> &str {
+    &self.0
+  }
+}
+
+impl fmt::Display for InvalidIdError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Invalid id: {:?}", self.0)
+  }
+}
+
+impl error::Error for InvalidIdError { }
+
+/// A stored document that doesn't match the model
+#[derive(Debug)]
+pub struct DecodeError {
+  pub collection: String,
+  pub id: Option<String>,
+  pub field: String,
+  pub message: String
+}
+
+impl DecodeError {
+  pub fn new(collection: &str, id: Option<&str>, field: &str, message: &str) -> Self {
+    Self {
+      collection: collection.to_owned(),
+      id: id.map(|id| id.to_owned()),
+      field: field.to_owned(),
+      message: message.to_owned()
+    }
+  }
+}
+
+impl fmt::Display for DecodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f, "Malformed {} document {}: field `{}` {}", 
+      self.collection, 
+      self.id.as_deref().unwrap_or("without _id"), 
+      self.field, 
+      self.message
+    )
+  }
+}
+
+impl error::Error for DecodeError { }
+
+/// The requested record doesn't exist
+#[derive(Debug)]
+pub struct NotFoundError(String);
+
+impl NotFoundError {
+  pub fn new(message: &str) -> Self {
+    Self(message.to_owned())
+  }
+}
+
+impl fmt::Display for NotFoundError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl error::Error for NotFoundError { }
+
+/// The record would take something another one already has, like an email
+#[derive(Debug)]
+pub struct ConflictError(String);
+
+impl ConflictError {
+  pub fn new(message: &str) -> Self {
+    Self(message.to_owned())
+  }
+}
+
+impl fmt::Display for ConflictError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl error::Error for ConflictError { }
+
+/// Input that breaks a rule of the model, like an empty password
+#[derive(Debug)]
+pub struct ValidationError(String);
+
+impl ValidationError {
+  pub fn new(message: &str) -> Self {
+    Self(message.to_owned())
+  }
+}
+
+impl fmt::Display for ValidationError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}", self.0)
+  }
+}
+
+impl error::Error for ValidationError { }
@@ -1 +1,4 @@
 pub mod error;
+pub mod url;
+#[cfg(feature = "postgres")]
+pub mod tls;
@@ -0,0 +1,58 @@
+use crate::utils::error::StringError;
+
+#[cfg(not(any(feature = "tls-openssl", feature = "tls-rustls")))]
+compile_error!("The postgres feature needs either the tls-openssl or the tls-rustls feature");
+
+// openssl wins when both are enabled, to keep the behavior of existing builds
+#[cfg(feature = "tls-openssl")]
+pub type MakeTlsConnector = postgres_openssl::MakeTlsConnector;
+
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-openssl")))]
+pub type MakeTlsConnector = tokio_postgres_rustls::MakeRustlsConnect;
+
+#[cfg(feature = "tls-openssl")]
+pub fn connector() -> Result<MakeTlsConnector, StringError> {
+  use openssl::ssl::{SslConnector, SslMethod, SslVerifyMode};
+
+  let mut builder = SslConnector::builder(SslMethod::tls())
+    .map_err(|err| StringError::new(&err.to_string()))?;
+
+  builder.set_verify(SslVerifyMode::NONE);
+
+  Ok(postgres_openssl::MakeTlsConnector::new(builder.build()))
+}
+
+// Unlike the openssl connector this one verifies the server, against
+// the CA in POSTGRES_CA_CERT if set or the webpki roots otherwise
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-openssl")))]
+pub fn connector() -> Result<MakeTlsConnector, StringError> {
+  use std::{env, sync::Arc};
+
+  use rustls::{ClientConfig, RootCertStore};
+  use rustls::pki_types::{pem::PemObject, CertificateDer};
+
+  let mut roots = RootCertStore::empty();
+
+  match env::var("POSTGRES_CA_CERT") {
+    Ok(path) => {
+      let certs = CertificateDer::pem_file_iter(&path)
+        .map_err(|err| StringError::new(&format!("{}: {}", path, err)))?;
+
+      for cert in certs {
+        let cert = cert.map_err(|err| StringError::new(&format!("{}: {}", path, err)))?;
+
+        roots.add(cert)
+          .map_err(|err| StringError::new(&format!("{}: {}", path, err)))?;
+      }
+    },
+    Err(_) => roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned())
+  }
+
+  let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+    .with_safe_default_protocol_versions()
+    .map_err(|err| StringError::new(&err.to_string()))?
+    .with_root_certificates(roots)
+    .with_no_client_auth();
+
+  Ok(tokio_postgres_rustls::MakeRustlsConnect::new(config))
+}
@@ -0,0 +1,186 @@
+use std::{
+  fmt::Write,
+  net::{IpAddr, SocketAddr, ToSocketAddrs}
+};
+
+use super::error::ValidationError;
+
+/// An http or https url as webhooks use it. Whitespace and control
+/// characters are refused anywhere, so nothing taken from the url can
+/// end a line of the request, and the path is sent percent-encoded
+#[derive(Debug, PartialEq)]
+pub struct Url<'a> {
+  pub tls: bool,
+  pub host: &'a str,
+  pub port: u16,
+  path: &'a str
+}
+
+impl<'a> Url<'a> {
+  pub fn parse(url: &'a str) -> Result<Self, ValidationError> {
+    if url.chars().any(|c| c.is_whitespace() || c.is_control()) {
+      return Err(ValidationError::new("url should have no whitespace or control characters"));
+    }
+
+    let (tls, rest) = if let Some(rest) = url.strip_prefix("http://") {
+      (false, rest)
+    } else if let Some(rest) = url.strip_prefix("https://") {
+      (true, rest)
+    } else {
+      return Err(ValidationError::new(&format!("{} should start with http:// or https://", url)));
+    };
+
+    let (authority, path) = match rest.find(['/', '?', '#']) {
+      Some(index) => rest.split_at(index),
+      None => (rest, "/")
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+      Some((host, port)) => (
+        host,
+        port.parse()
+          .map_err(|_| ValidationError::new(&format!("{} has an invalid port", url)))?
+      ),
+      None => (authority, if tls { 443 } else { 80 })
+    };
+
+    if host.is_empty() {
+      return Err(ValidationError::new(&format!("{} has no host", url)));
+    }
+
+    // Names and IPv4 addresses only, which also keeps user info out
+    if !host.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.') {
+      return Err(ValidationError::new(&format!("{} has an invalid host", url)));
+    }
+
+    // The fragment stays with the client
+    let path = path.split('#').next().unwrap_or_default();
+
+    Ok(Self { tls, host, port, path })
+  }
+
+  /// The path and query for the request line, percent-encoded
+  pub fn target(&self) -> String {
+    let mut target = String::new();
+
+    if !self.path.starts_with('/') {
+      target.push('/');
+    }
+
+    for byte in self.path.bytes() {
+      if byte.is_ascii_alphanumeric() || b"-._~!$&'()*+,;=:@/?%".contains(&byte) {
+        target.push(byte as char);
+      } else {
+        write!(target, "%{:02X}", byte).unwrap();
+      }
+    }
+
+    target
+  }
+
+  /// The addresses the host resolves to. Unless `allow_private` is set,
+  /// a host with a loopback, private or link-local address among them is
+  /// refused, so webhooks can't be pointed at services inside the network.
+  /// Connect to these addresses, resolving again could give other ones
+  pub fn resolve(&self, allow_private: bool) -> Result<Vec<SocketAddr>, ValidationError> {
+    let addresses: Vec<SocketAddr> = (self.host, self.port).to_socket_addrs()
+      .map_err(|err| ValidationError::new(&format!("{} doesn't resolve: {}", self.host, err)))?
+      .collect();
+
+    if addresses.is_empty() {
+      return Err(ValidationError::new(&format!("{} doesn't resolve", self.host)));
+    }
+
+    if !allow_private {
+      if let Some(address) = addresses.iter().find(|address| !is_public(address.ip())) {
+        return Err(
+          ValidationError::new(&format!("{} resolves to {}, which isn't a public address", self.host, address.ip()))
+        );
+      }
+    }
+
+    Ok(addresses)
+  }
+}
+
+fn is_public(ip: IpAddr) -> bool {
+  match ip {
+    IpAddr::V4(ip) => {
+      let [a, b, ..] = ip.octets();
+
+      !(ip.is_private()
+        || ip.is_loopback()
+        || ip.is_link_local()
+        || ip.is_unspecified()
+        || ip.is_broadcast()
+        || ip.is_multicast()
+        || ip.is_documentation()
+        // "This network" and the carrier-grade NAT range
+        || a == 0
+        || (a == 100 && (64..128).contains(&b)))
+    },
+    IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+      Some(ip) => is_public(IpAddr::V4(ip)),
+      None => {
+        let first = ip.segments()[0];
+
+        !(ip.is_loopback()
+          || ip.is_unspecified()
+          || ip.is_multicast()
+          // Unique local and link-local
+          || (first & 0xfe00) == 0xfc00
+          || (first & 0xffc0) == 0xfe80)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use std::net::IpAddr;
+
+  use super::{is_public, Url};
+
+  #[test]
+  fn test_parse() {
+    let url = Url::parse("http://127.0.0.1:8081/hooks?id=1#top").unwrap();
+
+    assert_eq!((url.tls, url.host, url.port), (false, "127.0.0.1", 8081));
+    assert_eq!(url.target(), "/hooks?id=1");
+
+    let url = Url::parse("https://example.com").unwrap();
+
+    assert_eq!((url.tls, url.host, url.port), (true, "example.com", 443));
+    assert_eq!(url.target(), "/");
+
+    assert_eq!(Url::parse("https://example.com?a=1").unwrap().target(), "/?a=1");
+    assert_eq!(Url::parse("http://example.com/a\"b/ü").unwrap().target(), "/a%22b/%C3%BC");
+
+    assert!(Url::parse("ftp://example.com/").is_err());
+    assert!(Url::parse("http://example.com:port/").is_err());
+    assert!(Url::parse("http:///path").is_err());
+    assert!(Url::parse("http://user@example.com/").is_err());
+    assert!(Url::parse("http://example.com/ HTTP/1.1").is_err());
+    assert!(Url::parse("http://example.com/\r\nX-Injected: 1").is_err());
+    assert!(Url::parse("http://example.com\r\n/").is_err());
+  }
+
+  #[test]
+  fn test_resolve() {
+    for ip in ["127.0.0.1", "10.1.2.3", "172.16.0.1", "192.168.1.1", "169.254.169.254", "100.64.0.1", "0.0.0.0", "::1", "fd00::1", "fe80::1", "::ffff:127.0.0.1"] {
+      assert!(!is_public(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+
+    for ip in ["93.184.215.14", "8.8.8.8", "2606:4700::1111"] {
+      assert!(is_public(ip.parse::<IpAddr>().unwrap()), "{}", ip);
+    }
+
+    let url = Url::parse("http://127.0.0.1:8081/hook").unwrap();
+
+    assert!(url.resolve(false).is_err());
+    assert_eq!(url.resolve(true).unwrap()[0].port(), 8081);
+
+    assert!(Url::parse("http://169.254.169.254/latest/meta-data").unwrap().resolve(false).is_err());
+    assert!(Url::parse("http://93.184.215.14/hook").unwrap().resolve(false).is_ok());
+  }
+}
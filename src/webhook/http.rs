@@ -0,0 +1,162 @@
+use std::{
+  error,
+  io::{BufRead, BufReader, Read, Write},
+  net::{SocketAddr, TcpStream},
+  time::Duration
+};
+
+use crate::utils::error::StringError;
+use crate::utils::url::Url;
+
+/// Sends a signed payload, a service can bring its own HTTP client
+pub trait Transport: Send + Sync {
+  /// POSTs the body and returns the response status
+  fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, Box<dyn error::Error>>;
+}
+
+// A plain HTTP/1.1 client, one connection per request. https urls
+// need the tls-openssl or the tls-rustls feature. Hosts that resolve
+// to loopback, private or link-local addresses are refused by default
+pub struct Http {
+  timeout: Duration,
+  allow_private: bool
+}
+
+impl Default for Http {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl Transport for Http {
+  fn post(&self, url: &str, headers: &[(&str, String)], body: &str) -> Result<u16, Box<dyn error::Error>> {
+    let url = Url::parse(url)?;
+
+    let stream = self.connect(&url.resolve(self.allow_private)?)?;
+
+    stream.set_read_timeout(Some(self.timeout))?;
+    stream.set_write_timeout(Some(self.timeout))?;
+
+    let mut request = format!(
+      "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+      url.target(), url.host, body.len()
+    );
+
+    for (name, value) in headers {
+      request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+
+    request.push_str("\r\n");
+    request.push_str(body);
+
+    if url.tls {
+      exchange(tls::connect(url.host, stream)?, &request)
+    } else {
+      exchange(stream, &request)
+    }
+  }
+}
+
+impl Http {
+  pub fn new() -> Self {
+    Self::with_timeout(Duration::from_secs(10))
+  }
+
+  pub fn with_timeout(timeout: Duration) -> Self {
+    Self { timeout, allow_private: false }
+  }
+
+  // For receivers on the same host or network, like tests and local setups
+  pub fn allow_private(self, allow_private: bool) -> Self {
+    Self { allow_private, ..self }
+  }
+
+  // Tries each address in turn, an unreachable one costs at most the timeout
+  fn connect(&self, addresses: &[SocketAddr]) -> Result<TcpStream, Box<dyn error::Error>> {
+    let mut last = None;
+
+    for address in addresses {
+      match TcpStream::connect_timeout(address, self.timeout) {
+        Ok(stream) => return Ok(stream),
+        Err(err) => last = Some(err)
+      }
+    }
+
+    Err(
+      last.map_or_else(
+        || Box::new(StringError::new("No address to connect to")) as Box<dyn error::Error>,
+        |err| Box::new(err)
+      )
+    )
+  }
+}
+
+fn exchange<S: Read + Write>(mut stream: S, request: &str) -> Result<u16, Box<dyn error::Error>> {
+  stream.write_all(request.as_bytes())?;
+  stream.flush()?;
+
+  // Only the status line matters, the rest of the response is dropped
+  let mut status_line = String::new();
+
+  BufReader::new(stream).read_line(&mut status_line)?;
+
+  status_line.split_whitespace()
+    .nth(1)
+    .and_then(|status| status.parse().ok())
+    .ok_or(
+      Box::new(
+        StringError::new(&format!("Invalid HTTP status line {:?}", status_line.trim_end()))
+      )
+    )
+}
+
+// openssl wins when both are enabled, as for Postgres
+#[cfg(feature = "tls-openssl")]
+mod tls {
+  use std::{error, net::TcpStream};
+
+  use openssl::ssl::{SslConnector, SslMethod, SslStream};
+
+  pub fn connect(host: &str, stream: TcpStream) -> Result<SslStream<TcpStream>, Box<dyn error::Error>> {
+    let connector = SslConnector::builder(SslMethod::tls())?.build();
+
+    Ok(connector.connect(host, stream)?)
+  }
+}
+
+#[cfg(all(feature = "tls-rustls", not(feature = "tls-openssl")))]
+mod tls {
+  use std::{error, net::TcpStream, sync::Arc};
+
+  use rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+  use rustls::pki_types::ServerName;
+
+  pub fn connect(
+    host: &str,
+    stream: TcpStream
+  ) -> Result<StreamOwned<ClientConnection, TcpStream>, Box<dyn error::Error>> {
+    let mut roots = RootCertStore::empty();
+
+    roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+      .with_safe_default_protocol_versions()?
+      .with_root_certificates(roots)
+      .with_no_client_auth();
+
+    let connection = ClientConnection::new(Arc::new(config), ServerName::try_from(host.to_owned())?)?;
+
+    Ok(StreamOwned::new(connection, stream))
+  }
+}
+
+#[cfg(not(any(feature = "tls-openssl", feature = "tls-rustls")))]
+mod tls {
+  use std::{error, net::TcpStream};
+
+  use crate::utils::error::StringError;
+
+  pub fn connect(_host: &str, _stream: TcpStream) -> Result<TcpStream, Box<dyn error::Error>> {
+    Err(Box::new(StringError::new("https urls need the tls-openssl or the tls-rustls feature")))
+  }
+}
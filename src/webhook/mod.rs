@@ -0,0 +1,399 @@
+//! Delivers outbox events to the webhooks users registered for them.
+//!
+//! [`Dispatcher::dispatch`] polls a batch from `Repositories::outbox`, posts
+//! every event to its subscribers, signed with [`signature::sign`], and
+//! acknowledges it as the [`CONSUMER`] consumer, which leaves the event
+//! pending for the outbox's other consumers. A failed post is recorded with
+//! when to retry it, following [`Backoff`], and a later call makes the retry,
+//! so nothing waits in between. The last failure is kept as a dead letter.
+//! Every attempt is recorded, see `repository::Webhook::deliveries`.
+
+use std::{
+  error,
+  sync::Arc,
+  time::{Duration, SystemTime, UNIX_EPOCH}
+};
+
+use serde_json::json;
+
+use crate::models;
+use crate::repository::Repositories;
+use crate::utils::error::NotFoundError;
+
+mod http;
+pub mod signature;
+
+pub use http::{Http, Transport};
+
+const BATCH: usize = 100;
+
+/// The name the dispatcher polls and acknowledges the outbox with
+pub const CONSUMER: &str = "webhooks";
+
+#[derive(Debug, Clone, Copy)]
+pub struct Backoff {
+  pub attempts: u32,
+  // Before the second attempt, multiplied by factor before each next one
+  pub delay: Duration,
+  pub factor: u32
+}
+
+impl Default for Backoff {
+  fn default() -> Self {
+    Self {
+      attempts: 5,
+      delay: Duration::from_secs(1),
+      factor: 2
+    }
+  }
+}
+
+impl Backoff {
+  // The wait after the failed attempt, attempts count from 1
+  pub fn delay(&self, attempt: u32) -> Duration {
+    self.delay * self.factor.saturating_pow(attempt.saturating_sub(1))
+  }
+}
+
+pub struct Dispatcher {
+  repositories: Arc<Repositories>,
+  transport: Box<dyn Transport>,
+  backoff: Backoff
+}
+
+impl Dispatcher {
+  pub fn new(repositories: Arc<Repositories>) -> Self {
+    Self::with_transport(repositories, Box::new(Http::new()), Backoff::default())
+  }
+
+  pub fn with_transport(
+    repositories: Arc<Repositories>,
+    transport: Box<dyn Transport>,
+    backoff: Backoff
+  ) -> Self {
+    Self { repositories, transport, backoff }
+  }
+
+  /// Makes the retries that are due, delivers one batch of new events
+  /// and returns how many of both were handled
+  pub fn dispatch(&self) -> Result<usize, Box<dyn error::Error>> {
+    let retries = self.repositories.webhook.due(SystemTime::now(), BATCH)?;
+
+    for (webhook, delivery) in &retries {
+      self.attempt(webhook, delivery.event_id, delivery.attempt + 1, &delivery.payload)?;
+    }
+
+    let events = self.repositories.outbox.poll(CONSUMER, BATCH)?;
+
+    for event in &events {
+      for webhook in self.subscribers(&event.event)? {
+        self.attempt(&webhook, event.id, 1, &payload(&webhook, event)?)?;
+      }
+
+      // One at a time, so an error later in the batch doesn't resend this one
+      self.repositories.outbox.acknowledge(CONSUMER, &[event.id])?;
+    }
+
+    Ok(retries.len() + events.len())
+  }
+
+  fn subscribers(&self, event: &models::Event) -> Result<Vec<models::Webhook>, Box<dyn error::Error>> {
+    match *event {
+      models::Event::PostLiked { user_id, post_id } => {
+        let author_id = match self.repositories.post.get(&post_id, None) {
+          Ok(post) => post.author.map(|author| author.id),
+          Err(err) if err.is::<NotFoundError>() => None,
+          Err(err) => return Err(err)
+        };
+
+        match author_id {
+          // Nobody is told about liking their own post
+          Some(author_id) if author_id != user_id => {
+            self.repositories.webhook.subscribers(&models::Topic::PostLiked, Some(&author_id))
+          },
+          _ => Ok(Vec::new())
+        }
+      },
+      models::Event::PostPublished { user_id: Some(author_id), .. } => {
+        self.repositories.webhook.subscribers(&models::Topic::PostPublished { author_id }, None)
+      },
+      _ => Ok(Vec::new())
+    }
+  }
+
+  // Posts the payload once and records the attempt, a failed one
+  // with when to retry it unless it was the last
+  fn attempt(
+    &self,
+    webhook: &models::Webhook, event_id: models::EventId,
+    attempt: i32, payload: &str
+  ) -> Result<(), Box<dyn error::Error>> {
+    let headers = [
+      ("Content-Type", "application/json".to_owned()),
+      ("User-Agent", "db_rust-webhooks".to_owned()),
+      ("X-Webhook-Id", webhook.id.to_string()),
+      ("X-Webhook-Event", webhook.topic.kind().to_owned()),
+      ("X-Webhook-Signature", signature::sign(&webhook.secret, payload))
+    ];
+
+    let (response_status, error) = match self.transport.post(&webhook.url, &headers, payload) {
+      Ok(status) if (200..300).contains(&status) => (Some(status), None),
+      Ok(status) => (Some(status), Some(format!("Responded with {}", status))),
+      Err(err) => (None, Some(err.to_string()))
+    };
+
+    let status = if error.is_none() {
+      models::DeliveryStatus::Delivered
+    } else if (attempt as u32) < self.backoff.attempts {
+      models::DeliveryStatus::Failed
+    } else {
+      models::DeliveryStatus::Dead
+    };
+
+    let created_at = SystemTime::now();
+
+    self.repositories.webhook.record(
+      &models::Delivery {
+        id: models::DeliveryId::new(),
+        webhook_id: webhook.id,
+        event_id,
+        attempt,
+        status,
+        response_status: response_status.map(i32::from),
+        error,
+        payload: payload.to_owned(),
+        created_at,
+        next_attempt_at: (status == models::DeliveryStatus::Failed)
+          .then(|| created_at + self.backoff.delay(attempt as u32))
+      }
+    )?;
+
+    Ok(())
+  }
+}
+
+fn payload(webhook: &models::Webhook, event: &models::OutboxEvent) -> Result<String, Box<dyn error::Error>> {
+  let created_at = event.created_at
+    .duration_since(UNIX_EPOCH)
+    .map(|duration| duration.as_secs())
+    .unwrap_or_default();
+
+  Ok(
+    serde_json::to_string(
+      &json!({
+        "id": event.id,
+        "webhook_id": webhook.id,
+        "created_at": created_at,
+        "event": event.event
+      })
+    )?
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use std::{error, sync::Arc, thread, time::{Duration, Instant}};
+
+  use tiny_http::{Response, Server};
+
+  use crate::{models, repository::{Backend, Repositories}};
+
+  use super::{signature, Backoff, Dispatcher, Http, Transport};
+
+  // Answers each request with the next status and hands back its signature and body
+  fn stub(statuses: Vec<u16>) -> (String, thread::JoinHandle<Vec<(String, String)>>) {
+    let server = Server::http("127.0.0.1:0").unwrap();
+    let url = format!("http://{}/hook", server.server_addr().to_ip().unwrap());
+
+    let handle = thread::spawn(move || {
+      statuses.into_iter()
+        .map(|status| {
+          let mut request = server.recv().unwrap();
+          let mut body = String::new();
+
+          request.as_reader().read_to_string(&mut body).unwrap();
+
+          let signature = request.headers().iter()
+            .find(|header| header.field.equiv("X-Webhook-Signature"))
+            .map(|header| header.value.to_string())
+            .unwrap_or_default();
+
+          request.respond(Response::empty(status)).unwrap();
+
+          (signature, body)
+        })
+        .collect()
+    });
+
+    (url, handle)
+  }
+
+  #[test]
+  fn test_dispatch() -> Result<(), Box<dyn error::Error>> {
+    let repositories = Arc::new(Repositories::with_backend(Backend::Memory));
+
+    let mut author = models::User::new();
+    let mut reader = models::User::new();
+
+    author.password = Some("secret".to_owned());
+    reader.password = Some("secret".to_owned());
+
+    let author_id = repositories.user.create(&mut author)?;
+    let reader_id = repositories.user.create(&mut reader)?;
+
+    let (liked_url, liked) = stub(vec![500, 200]);
+    let (published_url, published) = stub(vec![503, 503]);
+
+    let liked_id = repositories.webhook.create(
+      &models::Webhook {
+        id: models::WebhookId::new(),
+        user_id: author_id,
+        url: liked_url,
+        secret: "liked".to_owned(),
+        topic: models::Topic::PostLiked
+      }
+    )?;
+
+    let published_id = repositories.webhook.create(
+      &models::Webhook {
+        id: models::WebhookId::new(),
+        user_id: reader_id,
+        url: published_url,
+        secret: "published".to_owned(),
+        topic: models::Topic::PostPublished { author_id }
+      }
+    )?;
+
+    let post_id = repositories.post.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(author.clone())
+      }
+    )?;
+
+    repositories.like.create(&reader_id, &post_id)?;
+    // Liking one's own post calls no webhook
+    repositories.like.create(&author_id, &post_id)?;
+
+    let dispatcher = Dispatcher::with_transport(
+      repositories.clone(),
+      Box::new(Http::with_timeout(Duration::from_secs(5)).allow_private(true)),
+      Backoff { attempts: 2, delay: Duration::from_millis(200), factor: 2 }
+    );
+
+    // Two registrations, a post and two likes
+    assert_eq!(dispatcher.dispatch()?, 5);
+    // Both first attempts failed, and their retries aren't due yet
+    assert_eq!(dispatcher.dispatch()?, 0);
+
+    thread::sleep(Duration::from_millis(300));
+
+    assert_eq!(dispatcher.dispatch()?, 2);
+    assert_eq!(dispatcher.dispatch()?, 0);
+
+    // Other consumers still have all of them
+    assert_eq!(repositories.outbox.poll("other", 10)?.len(), 5);
+
+    for (signature, body) in liked.join().unwrap() {
+      assert!(signature::verify("liked", &body, &signature));
+      assert!(body.contains("\"type\":\"post_liked\""));
+    }
+
+    for (signature, body) in published.join().unwrap() {
+      assert!(signature::verify("published", &body, &signature));
+    }
+
+    let deliveries = repositories.webhook.deliveries(&liked_id, &author_id)?;
+
+    assert_eq!(
+      deliveries.iter().map(|delivery| (delivery.attempt, delivery.status)).collect::<Vec<_>>(),
+      vec![(2, models::DeliveryStatus::Delivered), (1, models::DeliveryStatus::Failed)]
+    );
+    assert_eq!(deliveries[1].response_status, Some(500));
+    assert!(deliveries.iter().all(|delivery| delivery.next_attempt_at.is_none()));
+
+    assert!(repositories.webhook.dead_letters(&author_id)?.is_empty());
+
+    let dead_letters = repositories.webhook.dead_letters(&reader_id)?;
+
+    assert_eq!(dead_letters.len(), 1);
+    assert_eq!(dead_letters[0].webhook_id, published_id);
+    assert_eq!(dead_letters[0].attempt, 2);
+
+    assert!(repositories.webhook.deliveries(&liked_id, &reader_id).is_err());
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_private_addresses() -> Result<(), Box<dyn error::Error>> {
+    let repositories = Arc::new(Repositories::with_backend(Backend::Memory));
+
+    let mut user = models::User::new();
+
+    user.password = Some("secret".to_owned());
+
+    let user_id = repositories.user.create(&mut user)?;
+
+    // Registered directly, past the server's check, or since resolving elsewhere
+    let webhook_id = repositories.webhook.create(
+      &models::Webhook {
+        id: models::WebhookId::new(),
+        user_id,
+        url: "http://127.0.0.1:9/hook".to_owned(),
+        secret: "secret".to_owned(),
+        topic: models::Topic::PostPublished { author_id: user_id }
+      }
+    )?;
+
+    repositories.post.create(
+      &models::Post {
+        id: models::PostId::new(),
+        title: "title".to_owned(),
+        text: None,
+        description: None,
+        liked: false,
+        author: Some(user)
+      }
+    )?;
+
+    let dispatcher = Dispatcher::with_transport(
+      repositories.clone(),
+      Box::new(Http::new()),
+      Backoff { attempts: 1, delay: Duration::from_millis(1), factor: 2 }
+    );
+
+    dispatcher.dispatch()?;
+
+    let deliveries = repositories.webhook.deliveries(&webhook_id, &user_id)?;
+
+    assert_eq!(deliveries.len(), 1);
+    assert_eq!(deliveries[0].status, models::DeliveryStatus::Dead);
+    assert!(deliveries[0].error.as_ref().unwrap().contains("isn't a public address"));
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_connect_timeout() {
+    let http = Http::with_timeout(Duration::from_millis(200)).allow_private(true);
+    let started = Instant::now();
+
+    // Nothing answers there, the connect gives up after the timeout instead of the OS default
+    assert!(http.post("http://10.255.255.1/hook", &[], "{}").is_err());
+    assert!(started.elapsed() < Duration::from_secs(2));
+  }
+
+  #[test]
+  fn test_backoff() {
+    let backoff = Backoff { attempts: 4, delay: Duration::from_secs(1), factor: 3 };
+
+    assert_eq!(backoff.delay(1), Duration::from_secs(1));
+    assert_eq!(backoff.delay(2), Duration::from_secs(3));
+    assert_eq!(backoff.delay(3), Duration::from_secs(9));
+  }
+}
@@ -0,0 +1,46 @@
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+const PREFIX: &str = "sha256=";
+
+/// The `X-Webhook-Signature` value for the body: `sha256=` and the hex HMAC-SHA256
+pub fn sign(secret: &str, body: &str) -> String {
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+    .expect("HMAC takes keys of any length");
+
+  mac.update(body.as_bytes());
+
+  format!("{}{}", PREFIX, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Checks a signature made by [`sign`], in constant time, for receivers
+pub fn verify(secret: &str, body: &str, signature: &str) -> bool {
+  let Some(signature) = signature.strip_prefix(PREFIX).and_then(|hex| hex::decode(hex).ok()) else {
+    return false;
+  };
+
+  let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+    .expect("HMAC takes keys of any length");
+
+  mac.update(body.as_bytes());
+
+  mac.verify_slice(&signature).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::{sign, verify};
+
+  #[test]
+  fn test_sign() {
+    let body = "The quick brown fox jumps over the lazy dog";
+    let signature = sign("key", body);
+
+    assert_eq!(signature, "sha256=f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8");
+
+    assert!(verify("key", body, &signature));
+    assert!(!verify("other", body, &signature));
+    assert!(!verify("key", "The quick brown fox", &signature));
+    assert!(!verify("key", body, "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"));
+  }
+}